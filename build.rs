@@ -3,6 +3,11 @@ use std::process::Command;
 fn main() {
     println!("cargo:rerun-if-changed=frontend/");
 
+    // only the web server serves the frontend
+    if std::env::var_os("CARGO_FEATURE_WEB").is_none() {
+        return;
+    }
+
     let status = Command::new("npm")
         .arg("run")
         .arg("build")
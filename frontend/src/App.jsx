@@ -3,6 +3,7 @@ import './App.css';
 import GameBoard from './GameBoard'
 import { BrowserRouter, Route, Routes, useNavigate } from 'react-router-dom';
 import Home from './pages/Home';
+import Editor from './pages/Editor';
 
 
 // Main app conponent
@@ -19,6 +20,8 @@ export default function App() {
 
         <Route path='join' element={<GameBoard />} />
 
+        <Route path='editor' element={<Editor />} />
+
       </Routes>
     </BrowserRouter>
   );
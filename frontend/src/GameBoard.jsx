@@ -14,7 +14,7 @@ import whiteRook from '/icons/white-rook.svg';
 import whitePawn from '/icons/white-pawn.svg';
 
 
-const CHAR_PIECE_MAP = new Map([
+export const CHAR_PIECE_MAP = new Map([
   ["k", blackKing],
   ["q", blackQueen],
   ["b", blackBishop],
@@ -30,6 +30,49 @@ const CHAR_PIECE_MAP = new Map([
 ]);
 
 
+/**
+ * Converts a square name like "e2" to its index in the grid
+ */
+const squareToIndex = (square) =>
+  "abcdefgh".indexOf(square[0]) + (8 - parseInt(square[1])) * 8;
+
+/**
+ * Converts a move like "e2e4" to the indices of its two squares, or a drop
+ * like "N@f3" to the index of its square
+ */
+const moveToIndices = (move) => {
+  if (!move)
+    return [];
+  if (move[1] === '@')
+    return [squareToIndex(move.slice(2, 4))];
+  return [squareToIndex(move.slice(0, 2)), squareToIndex(move.slice(2, 4))];
+};
+
+// FEN letters of the piece types the server names in pockets
+const PIECE_LETTERS = { queen: 'q', rook: 'r', bishop: 'b', knight: 'n', pawn: 'p' };
+
+/**
+ * Converts the piece placement of a FEN to the piece icons by grid index
+ */
+export const fenToGrid = (fen) => {
+  // pockets are shown from the game state, promoted marks not at all
+  const placement = fen.split(" ")[0].split("[")[0].replaceAll("~", "");
+
+  const grid = {};
+  for (let [y, row] of placement.split("/").entries()) {
+    let x = 0;
+    for (let c of row.split("")) {
+      if (CHAR_PIECE_MAP.has(c)) {
+        grid[[(x++) + y * 8]] = CHAR_PIECE_MAP.get(c);
+      } else {
+        x += parseInt(c);
+      }
+    }
+  }
+  return grid;
+};
+
+
 /**
  * Represents the chess board and handles socket IO
  */
@@ -40,7 +83,7 @@ export default function GameBoard() {
 
   // Connect to the server using a WebSocket
   useEffect(() => {
-    const socketLink = `ws://${window.location.host}/ws`;
+    const socketLink = `ws://${window.location.host}/ws${window.location.search}`;
     const socket = new WebSocket(socketLink);
 
     socketRef.current = socket;
@@ -50,28 +93,35 @@ export default function GameBoard() {
     };
 
     socket.onmessage = event => {
-      const { result, message } = JSON.parse(event.data);
+      const { result, message, game } = JSON.parse(event.data);
       if (result === "error") {
         alert(message);
         return;
       }
 
-      const [board, turn] = message.split(" ");
-
-      const grid = {};
-      for (let [y, row] of board.split("/").entries()) {
-        let x = 0;
-        for (let c of row.split("")) {
-          if (CHAR_PIECE_MAP.has(c)) {
-            grid[[(x++) + y * 8]] = CHAR_PIECE_MAP.get(c);
-          } else {
-            x += parseInt(c);
-          }
+      if (result === "hints") {
+        setSelected(squareToIndex(message.square));
+        setHints(message.destinations.map(squareToIndex));
+        setLastMove(moveToIndices(message.last_move));
+        setCheck(message.check.map(squareToIndex));
+        return;
+      }
+
+      if (game) {
+        setPockets(game.pockets);
+        setColor(game.color);
+        // in Bughouse the other board is shown next to ours
+        setPartner(game.boards ? game.boards[1 - game.board] : null);
+        setLastMove(moveToIndices(game.last_move));
+        setCheck(game.check.map(squareToIndex));
+        if (!game.selected) {
+          setSelected(null);
+          setHints([]);
         }
       }
 
-      setGrid(grid);
-      setTurn(turn);
+      setGrid(fenToGrid(message));
+      setTurn(message.split(" ")[1]);
     }
 
     socket.onclose = () => {
@@ -84,6 +134,20 @@ export default function GameBoard() {
   }, []);
 
   const [turn, setTurn] = useState('w');
+  const [color, setColor] = useState(null);
+
+  // Pieces in hand in Crazyhouse, and the one picked to drop next
+  const [pockets, setPockets] = useState({ white: [], black: [] });
+  const [dropping, setDropping] = useState(null);
+
+  // The state of the other board of a Bughouse game
+  const [partner, setPartner] = useState(null);
+
+  // Squares to highlight, as sent by the server
+  const [selected, setSelected] = useState(null);
+  const [hints, setHints] = useState([]);
+  const [lastMove, setLastMove] = useState([]);
+  const [check, setCheck] = useState([]);
 
   // Initialize the chess grid with the default layout
   const [grid, setGrid] = useState(() => {
@@ -118,18 +182,55 @@ export default function GameBoard() {
       return;
     const col = "abcdefgh"[x % 8];
     const row = 8 - Math.floor(x / 8);
+    if (dropping) {
+      socketRef.current.send(`${dropping.toUpperCase()}@${col}${row}`);
+      setDropping(null);
+      return;
+    }
     socketRef.current.send(`${col}${row}`);
   };
 
+  const pocketOf = (side) => (
+    <div className='pocket'>
+      {pockets[side].map((pieceType, i) => {
+        const letter = PIECE_LETTERS[pieceType];
+        const picked = side === color && dropping === letter;
+        return (
+          <img
+            key={i}
+            className={picked ? 'selected' : ''}
+            src={CHAR_PIECE_MAP.get(side === 'white' ? letter.toUpperCase() : letter)}
+            onClick={() => side === color && setDropping(picked ? null : letter)}
+          />
+        );
+      })}
+    </div>
+  );
+
+  const cellClassName = (x) => {
+    let className = 'cell';
+    if (x === selected)
+      className += ' selected';
+    if (lastMove.includes(x))
+      className += ' last-move';
+    if (check.includes(x))
+      className += ' check';
+    return className;
+  };
+
+  const partnerGrid = partner && fenToGrid(partner.fen);
+
   return (
     <>
       <h1>{"turn: " + turn}</h1>
+      {pocketOf('black')}
       <div id='board'>
         {[...Array(8 * 8).keys()].map((x) => {
           return (
             <>
-              <div className='cell' onClick={() => onCellClicked(x)} key={x}>
+              <div className={cellClassName(x)} onClick={() => onCellClicked(x)} key={x}>
                 {grid[x] && <img src={grid[x]} />}
+                {hints.includes(x) && <div className={grid[x] ? 'capture-hint' : 'move-hint'} />}
                 <p style={{position: "absolute"}}>{
                   `${"abcdefgh"[x % 8]}${8 - Math.floor(x / 8)}`
                 }</p>
@@ -139,7 +240,22 @@ export default function GameBoard() {
           );
         })}
       </div>
-
+      {pocketOf('white')}
+      {partnerGrid && (
+        <>
+          <h2>{"partner board, turn: " + partner.turn}</h2>
+          <div id='partner-board'>
+            {[...Array(8 * 8).keys()].map((x) => (
+              <>
+                <div className='cell' key={x}>
+                  {partnerGrid[x] && <img src={partnerGrid[x]} />}
+                </div>
+                {x % 8 === 7 && <div className='cell' key={1} hidden></div>}
+              </>
+            ))}
+          </div>
+        </>
+      )}
     </>
   )
 }
@@ -0,0 +1,106 @@
+import { useEffect, useState } from 'react';
+import { useNavigate } from 'react-router-dom';
+import { CHAR_PIECE_MAP, fenToGrid } from '../GameBoard';
+import '../GameBoard.css';
+
+const EMPTY_FEN = '8/8/8/8/8/8/8/8 w - - 0 1';
+
+/**
+ * Sets up a position square by square, the server checking it can be
+ * played before a game starts from it
+ */
+export default function Editor() {
+  const navigate = useNavigate();
+
+  const [fen, setFen] = useState(EMPTY_FEN);
+  const [problem, setProblem] = useState(null);
+  // The FEN letter of the piece to put, or 'x' to empty squares
+  const [brush, setBrush] = useState('K');
+  const [castling, setCastling] = useState('-');
+  const [enPassant, setEnPassant] = useState('-');
+
+  // Sends editor commands to the server, which answers with the new position
+  const edit = async (commands, from = fen) => {
+    const response = await fetch('/api/positions', {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({ fen: from, commands }),
+    });
+    const position = await response.json();
+    if (position.result === 'error') {
+      alert(position.message);
+      return;
+    }
+    setFen(position.fen);
+    setProblem(position.problem && position.problem.message);
+  };
+
+  useEffect(() => {
+    edit([], EMPTY_FEN);
+  }, []);
+
+  const onCellClicked = (x) => {
+    const square = `${"abcdefgh"[x % 8]}${8 - Math.floor(x / 8)}`;
+    edit([brush === 'x' ? `remove ${square}` : `put ${brush}${square}`]);
+  };
+
+  const createGame = async (engine) => {
+    const response = await fetch('/api/games', {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({ fen, engine }),
+    });
+    const game = await response.json();
+    if (game.result === 'error') {
+      alert(game.message);
+      return;
+    }
+    navigate(`/join?game=${game.id}`);
+  };
+
+  const grid = fenToGrid(fen);
+  const turn = fen.split(' ')[1];
+
+  return (
+    <>
+      <h1>{problem || `${turn === 'w' ? 'white' : 'black'} to move`}</h1>
+      <div className='pocket'>
+        {[...CHAR_PIECE_MAP.entries()].map(([letter, icon]) => (
+          <img
+            key={letter}
+            className={brush === letter ? 'selected' : ''}
+            src={icon}
+            onClick={() => setBrush(letter)}
+          />
+        ))}
+        <button className={brush === 'x' ? 'selected' : ''} onClick={() => setBrush('x')}>Erase</button>
+      </div>
+      <div id='board'>
+        {[...Array(8 * 8).keys()].map((x) => (
+          <>
+            <div className='cell' onClick={() => onCellClicked(x)} key={x}>
+              {grid[x] && <img src={grid[x]} />}
+            </div>
+            {x % 8 === 7 && <div className='cell' key={1} hidden></div>}
+          </>
+        ))}
+      </div>
+      <div>
+        <button onClick={() => edit(['start'])}>Starting Position</button>
+        <button onClick={() => edit(['clear'])}>Clear</button>
+        <select value={turn} onChange={e => edit([`turn ${e.target.value}`])}>
+          <option value='w'>White to move</option>
+          <option value='b'>Black to move</option>
+        </select>
+        <input value={castling} onChange={e => setCastling(e.target.value)} size={4} />
+        <button onClick={() => edit([`castling ${castling || '-'}`])}>Set Castling</button>
+        <input value={enPassant} onChange={e => setEnPassant(e.target.value)} size={2} />
+        <button onClick={() => edit([`ep ${enPassant || '-'}`])}>Set En Passant</button>
+      </div>
+      <div>
+        <button disabled={!!problem} onClick={() => createGame(null)}>Create Match</button>
+        <button disabled={!!problem} onClick={() => createGame({ depth: 3 })}>Play Computer</button>
+      </div>
+    </>
+  );
+}
@@ -1,8 +1,38 @@
+import { useState } from 'react';
 import { useNavigate } from 'react-router-dom';
 
+// Search depth of the computer opponent for each level
+const ENGINE_LEVELS = [1, 2, 3, 4, 5];
+
+const VARIANTS = {
+  standard: 'Standard',
+  king_of_the_hill: 'King of the Hill',
+  three_check: 'Three-check',
+  antichess: 'Antichess',
+  crazyhouse: 'Crazyhouse',
+  atomic: 'Atomic',
+  horde: 'Horde',
+  racing_kings: 'Racing Kings',
+};
+
+// Material the computer gives up in a handicap game
+const ODDS = {
+  none: 'No odds',
+  pawn_and_move: 'Pawn and move',
+  knight: 'Knight odds',
+  rook: 'Rook odds',
+  queen: 'Queen odds',
+};
+
 export default function Home() {
   const navigate = useNavigate();
 
+  const [engineLevel, setEngineLevel] = useState(3);
+  const [engineColor, setEngineColor] = useState('white');
+  const [chess960, setChess960] = useState(false);
+  const [variant, setVariant] = useState('standard');
+  const [odds, setOdds] = useState('none');
+
   const joinRandom = () => {
     setTimeout(() => navigate('/random'), 250);
   };
@@ -11,10 +41,73 @@ export default function Home() {
     setTimeout(() => navigate('/join'), 250);
   };
 
+  const openEditor = () => {
+    setTimeout(() => navigate('/editor'), 250);
+  };
+
+  // Creates a Bughouse game for four players, then takes the first seat
+  const playBughouse = async () => {
+    const response = await fetch('/api/games', {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({ variant: 'bughouse' }),
+    });
+    const game = await response.json();
+    if (game.result === 'error') {
+      alert(game.message);
+      return;
+    }
+    navigate(`/join?game=${game.id}`);
+  };
+
+  // Creates a game against the engine, then joins it
+  const playComputer = async () => {
+    const response = await fetch('/api/games', {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({
+        color: engineColor,
+        engine: { depth: ENGINE_LEVELS[engineLevel - 1] },
+        chess960,
+        variant,
+        odds: odds === 'none' ? undefined : odds,
+      }),
+    });
+    const game = await response.json();
+    if (game.result === 'error') {
+      alert(game.message);
+      return;
+    }
+    navigate(`/join?game=${game.id}`);
+  };
+
   return (
     <>
       <button onClick={joinRandom}>Join Random</button>
       <button onClick={joinMatch}>Join Match</button>
+      <button onClick={playBughouse}>Play Bughouse</button>
+      <button onClick={openEditor}>Set Up Position</button>
+      <div>
+        <button onClick={playComputer}>Play Computer</button>
+        <select value={engineLevel} onChange={e => setEngineLevel(parseInt(e.target.value))}>
+          {ENGINE_LEVELS.map((_, i) => <option key={i} value={i + 1}>{`Level ${i + 1}`}</option>)}
+        </select>
+        <select value={engineColor} onChange={e => setEngineColor(e.target.value)}>
+          <option value='white'>White</option>
+          <option value='black'>Black</option>
+          <option value='random'>Random</option>
+        </select>
+        <label>
+          <input type='checkbox' checked={chess960} onChange={e => setChess960(e.target.checked)} />
+          Chess960
+        </label>
+        <select value={variant} onChange={e => setVariant(e.target.value)}>
+          {Object.entries(VARIANTS).map(([value, name]) => <option key={value} value={value}>{name}</option>)}
+        </select>
+        <select value={odds} onChange={e => setOdds(e.target.value)}>
+          {Object.entries(ODDS).map(([value, name]) => <option key={value} value={value}>{name}</option>)}
+        </select>
+      </div>
     </>
   );
 }
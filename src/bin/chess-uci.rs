@@ -0,0 +1,5 @@
+//! The engine alone, talking UCI from the start so chess GUIs can launch it
+
+fn main() {
+    chess_rs::uci::start_uci();
+}
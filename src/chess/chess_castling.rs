@@ -0,0 +1,277 @@
+use super::*;
+use std::hash::{BuildHasher, Hasher, RandomState};
+
+/// The column of the rook each side can still castle with, indexed by color,
+/// then by [`KINGSIDE`] or [`QUEENSIDE`]
+pub(super) type CastlingRights = [[Option<i32>; 2]; 2];
+
+/// How many starting positions Chess960 has
+pub const CHESS960_POSITIONS: u16 = 960;
+
+/// Where the two knights go among the five squares left once the bishops and
+/// the queen are placed, in the standard numbering
+const KNIGHT_SQUARES: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+impl ChessBoard {
+    /// The Chess960 starting position number `index`, from 0 to 959 in the
+    /// standard numbering where 518 is the usual setup
+    pub fn chess960(index: u16) -> Result<Self, ChessError> {
+        if index >= CHESS960_POSITIONS {
+            return Err(ChessError::InvalidChess960Index(index));
+        }
+        let mut index = index as usize;
+        let mut back_row = [None; 8];
+        // the bishops go on squares of both colors, the other pieces fill
+        // the gaps left, from the a-file on
+        back_row[2 * (index % 4) + 1] = Some(BISHOP);
+        index /= 4;
+        back_row[2 * (index % 4)] = Some(BISHOP);
+        index /= 4;
+        let mut place = |nth_empty: usize, piece_type: PieceType| {
+            let col = (0..back_row.len())
+                .filter(|&col| back_row[col].is_none())
+                .nth(nth_empty)
+                .unwrap();
+            back_row[col] = Some(piece_type);
+        };
+        place(index % 6, QUEEN);
+        index /= 6;
+        let (first, second) = KNIGHT_SQUARES[index];
+        place(second, KNIGHT);
+        place(first, KNIGHT);
+        for piece_type in [ROOK, KING, ROOK] {
+            place(0, piece_type);
+        }
+
+        let mut board = Self::new();
+        let mut rooks = Vec::new();
+        for (col, piece_type) in back_row.into_iter().enumerate() {
+            let piece_type = piece_type.unwrap();
+            for color in [WHITE, BLACK] {
+                let pos = cvec!(col as i32, board.home_row(color));
+                board[pos] = Some(ChessPiece::new(piece_type, color));
+                if piece_type == KING {
+                    board.kings_pos[color as usize] = pos;
+                }
+            }
+            if piece_type == ROOK {
+                rooks.push(col as i32);
+            }
+        }
+        board.castling_rights = [[Some(rooks[1]), Some(rooks[0])]; 2];
+        board.chess960 = true;
+        board.rehash();
+        Ok(board)
+    }
+
+    /// A Chess960 starting position picked at random
+    pub fn chess960_random() -> Self {
+        let index = RandomState::new().build_hasher().finish() % CHESS960_POSITIONS as u64;
+        Self::chess960(index as u16).unwrap()
+    }
+
+    /// Whether castling is written as the king taking its own rook, as
+    /// Chess960 needs since the king may move a single square or none
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Switches how castling is written, for the moves played so far as well.
+    /// Undone moves are forgotten
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+        self.redo_stack.clear();
+        for played in &mut self.move_stack {
+            if let Casteling {
+                king_to, rook_from, ..
+            } = played.move_type
+            {
+                played.to = if chess960 { rook_from } else { king_to };
+            }
+        }
+    }
+
+    /// The columns the king and the rook land on when castling to `side`,
+    /// next to the corner the rook starts from
+    pub(super) fn castled_cols(&self, side: usize) -> (i32, i32) {
+        match side {
+            KINGSIDE => (self.width as i32 - 2, self.width as i32 - 3),
+            _ => (2, 3),
+        }
+    }
+
+    /// The side the king castles to with `mv`, if it is a castling move
+    pub(super) fn castling_side(&self, mv: Move) -> Option<usize> {
+        let king = self[mv.from].filter(|piece| piece.piece_type == KING)?;
+        let home_row = self.home_row(king.color);
+        if mv.from.row != home_row || mv.to.row != home_row {
+            return None;
+        }
+        let rights = self.castling_rights[king.color as usize];
+        [KINGSIDE, QUEENSIDE].into_iter().find(|&side| {
+            let Some(rook_col) = rights[side] else {
+                return false;
+            };
+            if self.chess960 {
+                mv.to.col == rook_col
+            } else {
+                mv.to.col == self.castled_cols(side).0 && (mv.to.col - mv.from.col).abs() >= 2
+            }
+        })
+    }
+
+    /// The legal castling move meant by `mv` when it is written the other
+    /// way: the king landing on its castling square, or taking its rook
+    pub(super) fn as_castling(&self, mv: Move) -> Option<Move> {
+        let color = self.turn;
+        self.legal_moves_from(mv.from)
+            .into_iter()
+            .find(|&castling| {
+                self.castling_side(castling).is_some_and(|side| {
+                    let rook_col = self.castling_rights[color as usize][side].unwrap();
+                    let king_col = self.castled_cols(side).0;
+                    mv.to.row == mv.from.row
+                        && (mv.to.col == rook_col || mv.to.col == king_col && mv.to != mv.from)
+                })
+            })
+    }
+
+    pub(super) fn castling_moves(&self, from: ChessVec, moves: &mut Vec<Move>) {
+        let color = self.turn;
+        let home_row = self.home_row(color);
+        if from.row != home_row || self.in_check(color) {
+            return;
+        }
+
+        let span = |a: i32, b: i32| a.min(b)..=a.max(b);
+        for side in [KINGSIDE, QUEENSIDE] {
+            let Some(rook_col) = self.castling_rights[color as usize][side] else {
+                continue;
+            };
+            let rook_from = cvec!(rook_col, home_row);
+            if !self[rook_from].is_some_and(|rook| rook.piece_type == ROOK && rook.color == color) {
+                continue;
+            }
+            let (king_col, rook_to_col) = self.castled_cols(side);
+
+            // the squares both pieces cross or land on must be empty but for
+            // themselves, and the ones of the king safe
+            let path_is_empty = span(from.col, king_col)
+                .chain(span(rook_col, rook_to_col))
+                .all(|col| {
+                    col == from.col || col == rook_col || self[cvec!(col, home_row)].is_none()
+                });
+            let path_is_safe = span(from.col, king_col)
+                .all(|col| !self.is_attacked(cvec!(col, home_row), color.opponent()));
+
+            if path_is_empty && path_is_safe {
+                let to = if self.chess960 {
+                    rook_from
+                } else {
+                    cvec!(king_col, home_row)
+                };
+                moves.push(Move::new(from, to));
+            }
+        }
+    }
+
+    /// The column of the rook furthest from the king of `color` on `side`
+    fn outermost_rook(&self, color: ChessColor, side: usize) -> Option<i32> {
+        let king_col = self.kings_pos[color as usize].col;
+        let home_row = self.home_row(color);
+        let is_rook = |&col: &i32| {
+            self[cvec!(col, home_row)]
+                .is_some_and(|piece| piece.piece_type == ROOK && piece.color == color)
+        };
+        match side {
+            KINGSIDE => (king_col + 1..self.width as i32).rev().find(is_rook),
+            _ => (0..king_col).find(is_rook),
+        }
+    }
+
+    /// The castling field of FEN. Rights name the file of their rook when
+    /// another rook stands further out (X-FEN), or always in Shredder-FEN
+    pub(super) fn castling_fen(&self, shredder: bool) -> String {
+        let mut field = String::new();
+        for color in [WHITE, BLACK] {
+            for side in [KINGSIDE, QUEENSIDE] {
+                let Some(col) = self.castling_rights[color as usize][side] else {
+                    continue;
+                };
+                let letter = if shredder
+                    || self
+                        .outermost_rook(color, side)
+                        .is_some_and(|outer| outer != col)
+                {
+                    (b'A' + col as u8) as char
+                } else {
+                    ['K', 'Q'][side]
+                };
+                field.push(match color {
+                    WHITE => letter,
+                    BLACK => letter.to_ascii_lowercase(),
+                });
+            }
+        }
+        if field.is_empty() {
+            field.push('-');
+        }
+        field
+    }
+
+    /// Reads the castling field of FEN, X-FEN or Shredder-FEN, switching to
+    /// Chess960 unless the kings and rooks stand where standard chess has them
+    pub(super) fn read_castling_fen(&mut self, field: &str) -> Result<(), ChessError> {
+        let invalid = ChessError::InvalidFen {
+            reason: "invalid castling rights",
+        };
+        let mut files_named = false;
+        for c in field.chars() {
+            let color = if c.is_ascii_uppercase() { WHITE } else { BLACK };
+            let king_col = self.kings_pos[color as usize].col;
+            let (side, col) = match c.to_ascii_uppercase() {
+                'K' => (
+                    KINGSIDE,
+                    self.outermost_rook(color, KINGSIDE)
+                        .unwrap_or(self.width as i32 - 1),
+                ),
+                'Q' => (
+                    QUEENSIDE,
+                    self.outermost_rook(color, QUEENSIDE).unwrap_or(0),
+                ),
+                file @ 'A'..='L' if ((file as u8 - b'A') as usize) < self.width => {
+                    files_named = true;
+                    let col = (file as u8 - b'A') as i32;
+                    match col.cmp(&king_col) {
+                        std::cmp::Ordering::Greater => (KINGSIDE, col),
+                        std::cmp::Ordering::Less => (QUEENSIDE, col),
+                        std::cmp::Ordering::Equal => return Err(invalid),
+                    }
+                }
+                _ => return Err(invalid),
+            };
+            self.castling_rights[color as usize][side] = Some(col);
+        }
+
+        let standard_rights = [WHITE, BLACK].into_iter().all(|color| {
+            let rights = self.castling_rights[color as usize];
+            rights == [None; 2]
+                || self.kings_pos[color as usize].col == self.width as i32 / 2
+                    && rights[KINGSIDE].is_none_or(|col| col == self.width as i32 - 1)
+                    && rights[QUEENSIDE].is_none_or(|col| col == 0)
+        });
+        self.chess960 = files_named || !standard_rights;
+        Ok(())
+    }
+}
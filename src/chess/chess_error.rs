@@ -0,0 +1,189 @@
+use super::*;
+
+/// Everything that can go wrong while playing, reading or hosting a game
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum ChessError {
+    /// No square could be read from the text
+    InvalidSquare(String),
+    NoPieceAt(ChessVec),
+    /// The piece on the square belongs to the side not to move
+    NotYourPiece {
+        square: ChessVec,
+        piece: ChessPiece,
+    },
+    NoPieceSelected,
+    IllegalMove(Move),
+    /// No move was played yet, or every move was undone
+    NoMoveToUndo,
+    /// Nothing was undone since the last move was played
+    NoMoveToRedo,
+    /// The line of moves is shorter than the ply asked for
+    NoSuchPly(usize),
+    /// The move has fewer answers in the game tree than the index asked for
+    NoSuchVariation(usize),
+    /// The cursor of the game tree is on the mainline
+    NotInVariation,
+    /// The text isn't a move in coordinate or algebraic notation
+    InvalidMoveNotation(String),
+    /// The move in algebraic notation doesn't match any legal move
+    NoMatchingMove(String),
+    /// The move in algebraic notation matches several legal moves
+    AmbiguousMove(String),
+    InvalidFen {
+        reason: &'static str,
+    },
+    InvalidPgn {
+        reason: &'static str,
+    },
+    /// The position set up can't be played from
+    InvalidPosition {
+        reason: &'static str,
+    },
+    /// Boards are from 1x1 up to `MAX_WIDTH` columns and `MAX_HEIGHT` rows
+    InvalidBoardSize {
+        width: usize,
+        height: usize,
+    },
+    /// The movement can't be given to the piece type
+    InvalidMovement {
+        reason: &'static str,
+    },
+    /// Chess960 starting positions are numbered from 0 to 959
+    InvalidChess960Index(u16),
+    /// No variant goes by the name
+    UnknownVariant(String),
+    /// No odds preset goes by the name
+    UnknownOdds(String),
+    /// The variant has its own starting position, which the setup named
+    /// would replace
+    IncompatibleVariant {
+        variant: Variant,
+        setup: &'static str,
+    },
+    /// The built-in engine can't sit at a game of this variant
+    EngineUnsupported(Variant),
+    /// The game already ended, or the position it would start from did
+    GameOver,
+    WaitingForOpponent,
+    NotYourTurn,
+    GameNotFound(u64),
+    NoFreeSeat,
+    TooManyGames,
+    /// A command of a text protocol wasn't understood
+    InvalidCommand(String),
+    /// A line of a file of evaluation weights couldn't be read
+    InvalidWeights {
+        line: usize,
+        reason: &'static str,
+    },
+    /// The text isn't the result of a game, like `1-0` or `0.5`
+    InvalidResult(String),
+    /// A neural network file couldn't be read
+    InvalidNetwork {
+        reason: &'static str,
+    },
+}
+
+impl ChessError {
+    /// A stable identifier of the kind of error, for clients to branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            ChessError::InvalidSquare(_) => "invalid_square",
+            ChessError::NoPieceAt(_) => "no_piece",
+            ChessError::NotYourPiece { .. } => "not_your_piece",
+            ChessError::NoPieceSelected => "no_piece_selected",
+            ChessError::IllegalMove(_) => "illegal_move",
+            ChessError::NoMoveToUndo => "no_move_to_undo",
+            ChessError::NoMoveToRedo => "no_move_to_redo",
+            ChessError::NoSuchPly(_) => "no_such_ply",
+            ChessError::NoSuchVariation(_) => "no_such_variation",
+            ChessError::NotInVariation => "not_in_variation",
+            ChessError::InvalidMoveNotation(_) => "invalid_move_notation",
+            ChessError::NoMatchingMove(_) => "no_matching_move",
+            ChessError::AmbiguousMove(_) => "ambiguous_move",
+            ChessError::InvalidFen { .. } => "invalid_fen",
+            ChessError::InvalidPgn { .. } => "invalid_pgn",
+            ChessError::InvalidPosition { .. } => "invalid_position",
+            ChessError::InvalidBoardSize { .. } => "invalid_board_size",
+            ChessError::InvalidMovement { .. } => "invalid_movement",
+            ChessError::InvalidChess960Index(_) => "invalid_chess960_index",
+            ChessError::UnknownVariant(_) => "unknown_variant",
+            ChessError::UnknownOdds(_) => "unknown_odds",
+            ChessError::IncompatibleVariant { .. } => "incompatible_variant",
+            ChessError::EngineUnsupported(_) => "engine_unsupported",
+            ChessError::GameOver => "game_over",
+            ChessError::WaitingForOpponent => "waiting_for_opponent",
+            ChessError::NotYourTurn => "not_your_turn",
+            ChessError::GameNotFound(_) => "game_not_found",
+            ChessError::NoFreeSeat => "no_free_seat",
+            ChessError::TooManyGames => "too_many_games",
+            ChessError::InvalidCommand(_) => "invalid_command",
+            ChessError::InvalidWeights { .. } => "invalid_weights",
+            ChessError::InvalidResult(_) => "invalid_result",
+            ChessError::InvalidNetwork { .. } => "invalid_network",
+        }
+    }
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChessError::InvalidSquare(text) => write!(f, "no square was found in '{text}'"),
+            ChessError::NoPieceAt(square) => write!(f, "there is no piece on {square}"),
+            ChessError::NotYourPiece { square, piece } => write!(
+                f,
+                "the {} on {square} belongs to {}",
+                format!("{:?}", piece.piece_type).to_lowercase(),
+                piece.color
+            ),
+            ChessError::NoPieceSelected => write!(f, "no piece is selected"),
+            ChessError::IllegalMove(mv) => write!(f, "{mv} is not a legal move"),
+            ChessError::NoMoveToUndo => write!(f, "there is no move to undo"),
+            ChessError::NoMoveToRedo => write!(f, "there is no move to redo"),
+            ChessError::NoSuchPly(ply) => write!(f, "the game has no ply {ply}"),
+            ChessError::NoSuchVariation(index) => write!(f, "there is no variation {index}"),
+            ChessError::NotInVariation => write!(f, "the move is on the mainline"),
+            ChessError::InvalidMoveNotation(text) => write!(f, "'{text}' is not a move"),
+            ChessError::NoMatchingMove(san) => write!(f, "{san} is not a legal move"),
+            ChessError::AmbiguousMove(san) => write!(f, "{san} could be played by several pieces"),
+            ChessError::InvalidFen { reason } => write!(f, "invalid FEN: {reason}"),
+            ChessError::InvalidPgn { reason } => write!(f, "invalid PGN: {reason}"),
+            ChessError::InvalidPosition { reason } => write!(f, "invalid position: {reason}"),
+            ChessError::InvalidBoardSize { width, height } => write!(
+                f,
+                "a board can't be {width}x{height}, it goes up to {MAX_WIDTH}x{MAX_HEIGHT}"
+            ),
+            ChessError::InvalidMovement { reason } => write!(f, "invalid movement: {reason}"),
+            ChessError::InvalidChess960Index(index) => {
+                write!(
+                    f,
+                    "there is no Chess960 position {index}, they go from 0 to 959"
+                )
+            }
+            ChessError::UnknownVariant(name) => write!(f, "unknown variant '{name}'"),
+            ChessError::UnknownOdds(name) => write!(f, "unknown odds '{name}'"),
+            ChessError::IncompatibleVariant { variant, setup } => write!(
+                f,
+                "{variant} has its own starting position and can't be played with {setup}"
+            ),
+            ChessError::EngineUnsupported(variant) => {
+                write!(f, "the engine can't play in {variant} games")
+            }
+            ChessError::GameOver => write!(f, "the game is over"),
+            ChessError::WaitingForOpponent => write!(f, "waiting for an opponent"),
+            ChessError::NotYourTurn => write!(f, "it is not your turn"),
+            ChessError::GameNotFound(id) => write!(f, "there is no game {id}"),
+            ChessError::NoFreeSeat => write!(f, "this game has no free seat"),
+            ChessError::TooManyGames => write!(f, "the server can't host more games right now"),
+            ChessError::InvalidCommand(command) => write!(f, "unknown command '{command}'"),
+            ChessError::InvalidWeights { line, reason } => {
+                write!(f, "invalid weights on line {line}: {reason}")
+            }
+            ChessError::InvalidResult(text) => write!(f, "'{text}' is not a game result"),
+            ChessError::InvalidNetwork { reason } => write!(f, "invalid network: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ChessError {}
@@ -0,0 +1,356 @@
+use super::*;
+use serde::{Deserializer, Serializer};
+use std::str::FromStr;
+
+/// A move from one square to another, as a player would request it.
+///
+/// It reads and displays in coordinate notation as used by UCI, e.g. `e7e8q`
+/// or `N@f3` for a drop, and serializes the same way; [`ChessBoard::to_san`] and
+/// [`ChessBoard::parse_san`] convert it to and from algebraic notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    /// The square of the moving piece; the king's one when castling, and
+    /// the landing square when dropping
+    pub from: ChessVec,
+    /// Where the piece lands; the king's destination when castling
+    pub to: ChessVec,
+    /// The piece a pawn reaching the last row turns into
+    pub promotion: Option<PieceType>,
+    /// The piece taken from the pocket and dropped on `to`, in Crazyhouse
+    pub drop: Option<PieceType>,
+}
+
+impl Move {
+    /// A move without promotion
+    pub const fn new(from: ChessVec, to: ChessVec) -> Self {
+        Self {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        }
+    }
+
+    /// A pawn move to the last row, turning the pawn into `piece_type`
+    pub const fn with_promotion(from: ChessVec, to: ChessVec, piece_type: PieceType) -> Self {
+        Self {
+            from,
+            to,
+            promotion: Some(piece_type),
+            drop: None,
+        }
+    }
+
+    /// A piece of the pocket put on the empty square `to`
+    pub const fn new_drop(piece_type: PieceType, to: ChessVec) -> Self {
+        Self {
+            from: to,
+            to,
+            promotion: None,
+            drop: Some(piece_type),
+        }
+    }
+}
+
+/// Coordinate notation, e.g. `e2e4`, `e7e8q` or `N@f3`
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(piece_type) = self.drop {
+            return write!(f, "{}@{}", piece_type.to_char(), self.to);
+        }
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(piece_type) = self.promotion {
+            write!(f, "{}", piece_type.to_char().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads coordinate notation, without checking the move against any position
+impl FromStr for Move {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ChessError::InvalidMoveNotation(s.to_string());
+        if let Some((piece, to)) = s.split_once('@') {
+            let piece_type = match piece.chars().collect::<Vec<_>>()[..] {
+                [c] => PieceType::from_char(c).filter(|&piece_type| piece_type != KING),
+                _ => None,
+            };
+            let to = to.parse().map_err(|_| invalid())?;
+            return Ok(Self::new_drop(piece_type.ok_or_else(invalid)?, to));
+        }
+        if !s.is_ascii() {
+            return Err(invalid());
+        }
+        // squares are a file letter followed by a rank of one or two digits
+        let square_end = |start: usize| {
+            s[start..]
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| !c.is_ascii_digit())
+                .map_or(s.len(), |(i, _)| start + i)
+        };
+        let from_end = square_end(0);
+        let to_end = square_end(from_end.min(s.len()));
+        let from = s[..from_end].parse().map_err(|_| invalid())?;
+        let to = s[from_end..to_end].parse().map_err(|_| invalid())?;
+        let promotion = match s[to_end..].chars().collect::<Vec<_>>()[..] {
+            [] => None,
+            [c] if c.is_ascii_lowercase() && c != 'p' => PieceType::from_char(c),
+            _ => return Err(invalid()),
+        };
+        if promotion.is_none() && to_end < s.len() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            from,
+            to,
+            promotion,
+            drop: None,
+        })
+    }
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A move of the game history, along with what it did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayedMove {
+    #[serde(rename = "uci")]
+    #[allow(missing_docs)]
+    pub mv: Move,
+    /// The move in standard algebraic notation
+    pub san: String,
+    /// The piece that moved, as it was before moving
+    pub piece: ChessPiece,
+    /// The piece taken by the move, en passant included
+    pub captured: Option<ChessPiece>,
+    /// The position right after the move
+    pub fen: String,
+}
+
+impl ChessMove {
+    pub(super) fn as_move(&self) -> Move {
+        Move {
+            from: self.from,
+            to: self.to,
+            promotion: match self.move_type {
+                Promoting(piece_type) => Some(piece_type),
+                _ => None,
+            },
+            drop: match self.move_type {
+                Drop(piece_type) => Some(piece_type),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl ChessBoard {
+    /// Every move played on this board, oldest first
+    pub fn history(&self) -> Vec<PlayedMove> {
+        let mut board = self.starting_position();
+        self.move_stack
+            .iter()
+            .map(|played| {
+                let mv = played.as_move();
+                let san = board.to_san(mv);
+                let piece = match mv.drop {
+                    Some(piece_type) => ChessPiece::new(piece_type, board.turn),
+                    None => board[mv.from].unwrap(),
+                };
+                board.apply_move(mv);
+                PlayedMove {
+                    mv,
+                    san,
+                    piece,
+                    captured: played.taken_piece,
+                    fen: board.to_fen(),
+                }
+            })
+            .collect()
+    }
+
+    /// The move played last, if any
+    pub fn last_move(&self) -> Option<Move> {
+        self.move_stack.last().map(ChessMove::as_move)
+    }
+
+    /// Whether any piece of color `by` attacks `pos`
+    pub fn is_attacked(&self, pos: ChessVec, by: ChessColor) -> bool {
+        let attacker = |at: ChessVec| {
+            Some(at)
+                .filter(|&at| self.contains(at))
+                .and_then(|at| self[at])
+                .filter(|piece| piece.color == by)
+        };
+
+        let pawn_row = pos.row - self.pawn_direction[by as usize];
+        if [-1, 1].into_iter().any(|side| {
+            attacker(cvec!(pos.col + side, pawn_row)).is_some_and(|piece| piece.piece_type == PAWN)
+        }) {
+            return true;
+        }
+        // leaps and slides go both ways, so a piece attacks `pos` when it
+        // could move from `pos` to its own square
+        let (leaps, slides) = self.attack_offsets();
+        if leaps.iter().any(|&offset| {
+            attacker(pos + offset)
+                .is_some_and(|piece| self.movement(piece.piece_type).leaps.contains(&offset))
+        }) {
+            return true;
+        }
+        for &direction in slides {
+            let mut at = pos + direction;
+            while self.contains(at) && self[at].is_none() {
+                at = at + direction;
+            }
+            if attacker(at)
+                .is_some_and(|piece| self.movement(piece.piece_type).slides.contains(&direction))
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Whether the king of `color` is attacked
+    pub fn in_check(&self, color: ChessColor) -> bool {
+        let rules = self.variant.rules();
+        rules.has_checks() && rules.in_check(self, color)
+    }
+
+    /// Every legal move of the side to move
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.clone().generate_moves(None)
+    }
+
+    /// The legal moves of the piece standing on `from`, if it is its turn
+    pub fn legal_moves_from(&self, from: ChessVec) -> Vec<Move> {
+        if !self.contains(from) {
+            return Vec::new();
+        }
+        self.clone().generate_moves(Some(from))
+    }
+
+    /// The squares the piece on `from` can legally move to
+    pub fn legal_destinations(&self, from: ChessVec) -> Vec<ChessVec> {
+        let mut destinations: Vec<ChessVec> = self
+            .legal_moves_from(from)
+            .into_iter()
+            .map(|mv| mv.to)
+            .collect();
+        // promotions reach the same square once per piece type
+        destinations.dedup();
+        destinations
+    }
+
+    /// When the side to move is in check, the square of its king followed by
+    /// the squares of the pieces giving check
+    pub fn check_squares(&self) -> Vec<ChessVec> {
+        let king_pos = self.kings_pos[self.turn as usize];
+        if !self.in_check(self.turn) {
+            return Vec::new();
+        }
+
+        let mut squares = vec![king_pos];
+        let mut moves = Vec::new();
+        for row in 0..self.height as i32 {
+            for col in 0..self.width as i32 {
+                let from = cvec!(col, row);
+                let Some(piece) = self[from].filter(|piece| piece.color != self.turn) else {
+                    continue;
+                };
+                moves.clear();
+                piece.pseudo_moves(self, from, &mut moves);
+                if moves.iter().any(|mv| mv.to == king_pos) {
+                    squares.push(from);
+                }
+            }
+        }
+        squares
+    }
+
+    /// Generates legal moves by playing every candidate and keeping the ones
+    /// that don't leave the mover's king attacked
+    pub(crate) fn generate_moves(&mut self, only_from: Option<ChessVec>) -> Vec<Move> {
+        let rules = self.variant.rules();
+        let mut moves = Vec::new();
+        for row in 0..self.height as i32 {
+            for col in 0..self.width as i32 {
+                let from = cvec!(col, row);
+                if only_from.is_some_and(|only_from| only_from != from) {
+                    continue;
+                }
+                match self[from] {
+                    Some(piece) if piece.color == self.turn => {
+                        piece.pseudo_moves(self, from, &mut moves);
+                        if piece.piece_type == KING && rules.can_castle() {
+                            self.castling_moves(from, &mut moves);
+                        }
+                    }
+                    None if rules.has_pockets() => self.drop_moves(from, &mut moves),
+                    _ => (),
+                }
+            }
+        }
+
+        let color = self.turn;
+        if rules.has_checks() {
+            moves.retain(|&mv| !self.peek_move(mv, |board| board.in_check(color)));
+        }
+        rules.restrict_moves(self, &mut moves);
+        moves
+    }
+
+    /// Counts the leaf nodes of the move tree `depth` plies deep, to check
+    /// move generation against known results
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.generate_moves(None);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .into_iter()
+            .map(|mv| {
+                self.apply_move(mv);
+                let nodes = self.perft(depth - 1);
+                self.retract_move().unwrap();
+                nodes
+            })
+            .sum()
+    }
+
+    /// [`ChessBoard::perft`] one ply shallower after each legal move
+    pub fn perft_divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        self.generate_moves(None)
+            .into_iter()
+            .map(|mv| {
+                self.apply_move(mv);
+                let nodes = self.perft(depth - 1);
+                self.retract_move().unwrap();
+                (mv, nodes)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,629 @@
+use super::*;
+use std::fmt::Write;
+
+/// The tag pairs of a PGN game, e.g. `("White", "Carlsen")`
+pub type PgnTags = Vec<(String, String)>;
+
+impl ChessBoard {
+    /// The position in Forsyth–Edwards Notation, castling rights being
+    /// written as in X-FEN when Chess960 needs it
+    pub fn to_fen(&self) -> String {
+        self.fen(false)
+    }
+
+    /// The position in Shredder-FEN, which names castling rights by the
+    /// file of their rook
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen(true)
+    }
+
+    fn fen(&self, shredder: bool) -> String {
+        let mut result = String::new();
+        let has_pockets = self.variant.rules().has_pockets();
+        // add board state
+        for y in (0..self.height).rev() {
+            let row = &self.grid[y][..self.width];
+            let spaces = row.iter().enumerate().fold(0, |mut spaces, (x, i)| {
+                match i {
+                    Some(piece) => {
+                        if spaces > 0 {
+                            result.push_str(&spaces.to_string());
+                            spaces = 0;
+                        }
+                        result.push_str(&piece.to_string());
+                        if has_pockets && self.is_promoted(cvec!(x as i32, y as i32)) {
+                            result.push('~');
+                        }
+                    }
+                    None => spaces += 1,
+                }
+                spaces
+            });
+            if spaces > 0 {
+                result.push_str(&spaces.to_string());
+            }
+            result.push('/');
+        }
+        let _ = result.pop();
+        if has_pockets {
+            result.push_str(&self.pockets_fen());
+        }
+        // add current turn
+        write!(result, " {}", ['w', 'b'][self.turn as usize]).unwrap();
+        // add castling rights
+        write!(result, " {}", self.castling_fen(shredder)).unwrap();
+        // add en passant target and move counters
+        match self.en_passant {
+            Some(pos) => write!(result, " {pos}").unwrap(),
+            None => result.push_str(" -"),
+        }
+        if let Some(field) = self.variant.rules().fen_field(self) {
+            write!(result, " {field}").unwrap();
+        }
+        write!(result, " {} {}", self.halfmove_clock, self.fullmove_number).unwrap();
+        return result;
+    }
+
+    /// Sets up a board from Forsyth–Edwards Notation, X-FEN or Shredder-FEN;
+    /// the fields after the piece placement are optional
+    pub fn from_fen(fen: &str) -> Result<Self, ChessError> {
+        Self::from_variant_fen(fen, Variant::Standard)
+    }
+
+    /// Sets up a board playing `variant` from FEN, which may count the
+    /// checks of three-check after the en passant square as in `3+3`, or at
+    /// the end as in `+0+0`
+    pub fn from_variant_fen(fen: &str, variant: Variant) -> Result<Self, ChessError> {
+        let mut checks = None;
+        let fields: Vec<&str> = fen
+            .split_whitespace()
+            .filter(|field| {
+                let is_checks = field.contains('+');
+                if is_checks {
+                    checks = Some(*field);
+                }
+                !is_checks
+            })
+            .collect();
+        let mut fields = fields.into_iter();
+        let mut board = Self::new();
+        board.grid = [[None; MAX_WIDTH]; MAX_HEIGHT];
+        board.castling_rights = [[None; 2]; 2];
+
+        let placement = fields.next().ok_or(ChessError::InvalidFen {
+            reason: "it is empty",
+        })?;
+        // pockets follow the placement in brackets, or as a ninth row
+        let (placement, mut pockets) = match placement.split_once('[') {
+            Some((placement, pockets)) => {
+                let pockets = pockets.strip_suffix(']').ok_or(ChessError::InvalidFen {
+                    reason: "a pocket isn't closed",
+                })?;
+                (placement, Some(pockets))
+            }
+            None => (placement, None),
+        };
+        let mut rows: Vec<&str> = placement.split('/').collect();
+        if pockets.is_none() && variant.rules().has_pockets() && rows.len() == 9 {
+            pockets = rows.pop();
+        }
+        if !(1..=MAX_HEIGHT).contains(&rows.len()) {
+            return Err(ChessError::InvalidFen {
+                reason: "it should describe from 1 to 10 rows",
+            });
+        }
+        // the height is the number of rows, the width the number of
+        // columns of the first one
+        board.height = rows.len();
+        let mut width = None;
+
+        let mut kings = [0; 2];
+        for (i, pieces) in rows.into_iter().enumerate() {
+            let row = board.height - 1 - i;
+            let mut col = 0;
+            let mut spaces = 0;
+            for c in pieces.chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    // empty squares past the 9th take two digits
+                    col -= spaces;
+                    spaces = spaces * 10 + digit as usize;
+                    col += spaces;
+                    // stop before a long digit run overflows
+                    if col > width.unwrap_or(MAX_WIDTH) {
+                        return Err(ChessError::InvalidFen {
+                            reason: "rows should have the same number of columns, from 1 to 12",
+                        });
+                    }
+                    continue;
+                }
+                spaces = 0;
+                if c == '~' && col > 0 {
+                    board.mark_promoted(cvec!(col as i32 - 1, row as i32));
+                    continue;
+                }
+                let piece = ChessPiece::from_char(c).ok_or(ChessError::InvalidFen {
+                    reason: "unknown piece",
+                })?;
+                if col >= MAX_WIDTH {
+                    return Err(ChessError::InvalidFen {
+                        reason: "a row has more than 12 columns",
+                    });
+                }
+                let pos = cvec!(col as i32, row as i32);
+                if piece.piece_type == KING {
+                    kings[piece.color as usize] += 1;
+                    board.kings_pos[piece.color as usize] = pos;
+                }
+                board[pos] = Some(piece);
+                col += 1;
+            }
+            if col == 0 || col > MAX_WIDTH || *width.get_or_insert(col) != col {
+                return Err(ChessError::InvalidFen {
+                    reason: "rows should have the same number of columns, from 1 to 12",
+                });
+            }
+        }
+        board.width = width.unwrap();
+        match variant.rules().kings() {
+            Some([1, 1]) if kings != [1, 1] => {
+                return Err(ChessError::InvalidFen {
+                    reason: "each side needs exactly one king",
+                })
+            }
+            Some(expected) if kings != expected => {
+                return Err(ChessError::InvalidFen {
+                    reason: "the kings don't match the variant",
+                })
+            }
+            _ => (),
+        }
+
+        board.turn = match fields.next() {
+            None | Some("w") => WHITE,
+            Some("b") => BLACK,
+            Some(_) => {
+                return Err(ChessError::InvalidFen {
+                    reason: "the turn should be 'w' or 'b'",
+                })
+            }
+        };
+
+        match fields.next() {
+            None | Some("-") => (),
+            Some(castling) => board.read_castling_fen(castling)?,
+        }
+
+        board.en_passant = match fields.next() {
+            None | Some("-") => None,
+            Some(square) => match ChessVec::try_from(&mut square.to_string()) {
+                Ok(pos) if board.contains(pos) && pos.to_string() == square => Some(pos),
+                _ => {
+                    return Err(ChessError::InvalidFen {
+                        reason: "invalid en passant square",
+                    })
+                }
+            },
+        };
+
+        board.halfmove_clock = match fields.next() {
+            None => 0,
+            Some(clock) => clock.parse().map_err(|_| ChessError::InvalidFen {
+                reason: "invalid halfmove clock",
+            })?,
+        };
+        board.fullmove_number = match fields.next() {
+            None => 1,
+            Some(number) => number.parse().map_err(|_| ChessError::InvalidFen {
+                reason: "invalid move number",
+            })?,
+        };
+
+        if let Some(checks) = checks {
+            board.read_checks_fen(checks)?;
+        }
+        if let Some(pockets) = pockets {
+            board.read_pockets_fen(pockets)?;
+        }
+        board.set_variant(variant);
+        Ok(board)
+    }
+
+    /// Standard algebraic notation of the legal move `mv`, e.g. `Nbd7`, `exd5`, `N@f3` or `O-O+`
+    pub fn to_san(&self, mv: Move) -> String {
+        let mut san = String::new();
+
+        if mv.drop.is_some() {
+            san.push_str(&mv.to_string());
+        } else if let Some(side) = self.castling_side(mv) {
+            san.push_str(["O-O", "O-O-O"][side]);
+        } else {
+            let Some(piece) = self[mv.from] else {
+                return mv.to_string();
+            };
+            let from = mv.from.to_string();
+            let is_capture =
+                self[mv.to].is_some() || (piece.piece_type == PAWN && mv.from.col != mv.to.col);
+
+            if piece.piece_type == PAWN {
+                if is_capture {
+                    san.push_str(&from[..1]);
+                }
+            } else {
+                san.push(piece.piece_type.to_char());
+
+                let rivals: Vec<ChessVec> = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|other| other.to == mv.to && other.from != mv.from)
+                    .filter(|other| {
+                        self[other.from].is_some_and(|p| p.piece_type == piece.piece_type)
+                    })
+                    .map(|other| other.from)
+                    .collect();
+                if !rivals.is_empty() {
+                    if rivals.iter().all(|rival| rival.col != mv.from.col) {
+                        san.push_str(&from[..1]);
+                    } else if rivals.iter().all(|rival| rival.row != mv.from.row) {
+                        san.push_str(&from[1..]);
+                    } else {
+                        san.push_str(&from);
+                    }
+                }
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_string());
+            if let Some(piece_type) = mv.promotion {
+                write!(san, "={}", piece_type.to_char()).unwrap();
+            }
+        }
+
+        let mut board = self.clone();
+        board.apply_move(mv);
+        // a move winning by the rules of a variant is marked like a mate
+        if board.status().winner() == Some(self.turn) {
+            san.push('#');
+        } else if board.in_check(board.turn) {
+            san.push('+');
+        }
+        san
+    }
+
+    /// Reads a legal move in coordinate notation, e.g. `e2e4` or `e7e8q`
+    pub fn parse_uci(&self, text: &str) -> Result<Move, ChessError> {
+        let mv: Move = text.trim().parse()?;
+        if !self.legal_moves_from(mv.from).contains(&mv) {
+            return Err(ChessError::IllegalMove(mv));
+        }
+        Ok(mv)
+    }
+
+    /// Reads a legal move in standard algebraic notation, the capture sign,
+    /// check suffixes and annotations being optional
+    pub fn parse_san(&self, text: &str) -> Result<Move, ChessError> {
+        let san = text.trim().trim_end_matches(['+', '#', '!', '?']);
+        let moves = self.legal_moves();
+
+        let castling_side = match san {
+            "O-O" | "0-0" => Some(KINGSIDE),
+            "O-O-O" | "0-0-0" => Some(QUEENSIDE),
+            _ => None,
+        };
+        if let Some(side) = castling_side {
+            return moves
+                .into_iter()
+                .find(|&mv| self.castling_side(mv) == Some(side))
+                .ok_or_else(|| ChessError::NoMatchingMove(text.to_string()));
+        }
+
+        // drops name their piece, pawns included, though `@e4` is read too
+        if san.contains('@') {
+            let drop = match san.starts_with('@') {
+                true => format!("P{san}"),
+                false => san.to_string(),
+            };
+            let mv: Move = drop
+                .parse()
+                .map_err(|_| ChessError::InvalidMoveNotation(text.to_string()))?;
+            return match moves.contains(&mv) {
+                true => Ok(mv),
+                false => Err(ChessError::NoMatchingMove(text.to_string())),
+            };
+        }
+
+        let mut chars: Vec<char> = san.chars().filter(|&c| c != 'x' && c != '=').collect();
+        let promotion = match chars.last() {
+            Some(&c) if "QRBNKACM".contains(c) && chars.len() > 2 => {
+                chars.pop();
+                PieceType::from_char(c)
+            }
+            _ => None,
+        };
+        let piece_type = match chars.first() {
+            Some(&c) if "KQRBNACM".contains(c) => {
+                chars.remove(0);
+                PieceType::from_char(c).unwrap()
+            }
+            _ => PAWN,
+        };
+        // the destination is a file letter followed by a rank of one or two digits
+        let rank_len = chars
+            .iter()
+            .rev()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+        if !(1..=2).contains(&rank_len) || chars.len() < rank_len + 1 || chars.len() > 6 {
+            return Err(ChessError::InvalidMoveNotation(text.to_string()));
+        }
+        let to: String = chars
+            .split_off(chars.len() - rank_len - 1)
+            .into_iter()
+            .collect();
+        let to = ChessVec::try_from(&mut to.clone())
+            .ok()
+            .filter(|pos| pos.to_string() == to)
+            .ok_or_else(|| ChessError::InvalidMoveNotation(text.to_string()))?;
+
+        // whatever is left disambiguates the piece by its file and/or rank
+        let hint: String = chars.into_iter().collect();
+        let (file_hint, rank_hint) = hint.split_at(
+            hint.find(|c: char| c.is_ascii_digit())
+                .unwrap_or(hint.len()),
+        );
+        let candidates: Vec<Move> = moves
+            .into_iter()
+            .filter(|mv| mv.to == to && mv.promotion == promotion)
+            .filter(|mv| self[mv.from].is_some_and(|piece| piece.piece_type == piece_type))
+            .filter(|mv| {
+                let from = mv.from.to_string();
+                let (file, rank) = from.split_at(1);
+                (file_hint.is_empty() || file_hint == file)
+                    && (rank_hint.is_empty() || rank_hint == rank)
+            })
+            .collect();
+        match candidates[..] {
+            [mv] => Ok(mv),
+            [] => Err(ChessError::NoMatchingMove(text.to_string())),
+            _ => Err(ChessError::AmbiguousMove(text.to_string())),
+        }
+    }
+
+    /// The moves played on this board, oldest first
+    pub fn moves(&self) -> Vec<Move> {
+        self.move_stack.iter().map(ChessMove::as_move).collect()
+    }
+
+    /// The position before the first move of the stack was played
+    pub fn starting_position(&self) -> ChessBoard {
+        let mut board = self.clone();
+        while board.retract_move().is_ok() {}
+        board.redo_stack.clear();
+        board
+    }
+
+    /// The moves played on this board in standard algebraic notation
+    pub fn san_history(&self) -> Vec<String> {
+        let mut board = self.starting_position();
+        self.moves()
+            .into_iter()
+            .map(|mv| {
+                let san = board.to_san(mv);
+                board.apply_move(mv);
+                san
+            })
+            .collect()
+    }
+
+    /// Exports the game in Portable Game Notation. `tags` override the seven
+    /// tag roster, a missing `Result` tag is taken from [`ChessBoard::status`]
+    pub fn to_pgn(&self, tags: &[(&str, String)]) -> String {
+        let start = self.starting_position();
+        let roster = pgn_roster(&start, self.status().result(), tags);
+
+        let mut tokens = Vec::new();
+        let mut number = start.fullmove_number;
+        let mut turn = start.turn;
+        if turn == BLACK {
+            tokens.push(format!("{number}..."));
+        }
+        for san in self.san_history() {
+            if turn == WHITE {
+                tokens.push(format!("{number}."));
+            } else {
+                number += 1;
+            }
+            tokens.push(san);
+            turn = turn.opponent();
+        }
+        write_pgn(&roster, tokens)
+    }
+
+    /// Replays a game in Portable Game Notation, returning the board along with
+    /// its tag pairs. Comments, variations and annotation glyphs are skipped,
+    /// [`GameTree::from_pgn`] keeps them
+    pub fn from_pgn(pgn: &str) -> Result<(Self, PgnTags), ChessError> {
+        let (tags, movetext) = split_pgn(pgn)?;
+        let mut board = pgn_start(&tags)?;
+
+        let mut depth = 0;
+        // the character closing the comment being skipped
+        let mut comment_end = None;
+        let mut token = String::new();
+        for c in movetext.chars().chain(Some(' ')) {
+            match c {
+                _ if comment_end == Some(c) => comment_end = None,
+                _ if comment_end.is_some() => (),
+                '{' => comment_end = Some('}'),
+                ';' => comment_end = Some('\n'),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ if depth > 0 => (),
+                c if c.is_whitespace() => {
+                    board.play_pgn_token(&token)?;
+                    token.clear();
+                }
+                c => token.push(c),
+            }
+        }
+        if comment_end == Some('}') {
+            return Err(ChessError::InvalidPgn {
+                reason: "a comment isn't closed",
+            });
+        }
+        if depth != 0 {
+            return Err(ChessError::InvalidPgn {
+                reason: "a variation isn't closed",
+            });
+        }
+        Ok((board, tags))
+    }
+
+    fn play_pgn_token(&mut self, token: &str) -> Result<(), ChessError> {
+        let token = strip_move_number(token);
+        if token.is_empty() || token.starts_with('$') || is_pgn_result(token) {
+            return Ok(());
+        }
+        let mv = self.parse_san(token)?;
+        self.apply_move(mv);
+        Ok(())
+    }
+}
+
+/// Splits a PGN game into its tag pairs and its movetext
+pub(super) fn split_pgn(pgn: &str) -> Result<(PgnTags, String), ChessError> {
+    let mut tags = Vec::new();
+    let mut movetext = String::new();
+    for line in pgn.lines() {
+        let line = line.trim();
+        if let Some(tag) = line.strip_prefix('[').and_then(|tag| tag.strip_suffix(']')) {
+            let (name, value) =
+                tag.split_once(char::is_whitespace)
+                    .ok_or(ChessError::InvalidPgn {
+                        reason: "a tag has no value",
+                    })?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            tags.push((
+                name.to_string(),
+                value.replace("\\\"", "\"").replace("\\\\", "\\"),
+            ));
+        } else if !line.starts_with('%') {
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+    }
+    Ok((tags, movetext))
+}
+
+/// The position a PGN game starts from, given by its `FEN` tag if any, and
+/// the rules it is played with, given by its `Variant` tag
+pub(super) fn pgn_start(tags: &PgnTags) -> Result<ChessBoard, ChessError> {
+    let tag = |name: &str| {
+        tags.iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let variant_tag = tag("Variant").unwrap_or_default();
+    let is_chess960 =
+        ["chess960", "fischerandom", "chess 960"].contains(&variant_tag.to_lowercase().as_str());
+    let variant = match is_chess960 {
+        true => Variant::Standard,
+        false => variant_tag.parse()?,
+    };
+    let mut board = match tag("FEN") {
+        Some(fen) => ChessBoard::from_variant_fen(fen, variant)?,
+        None => ChessBoard::new_variant(variant),
+    };
+    if is_chess960 {
+        board.set_chess960(true);
+    }
+    Ok(board)
+}
+
+/// Removes a move number sticking to a move, as in `1.e4`
+pub(super) fn strip_move_number(token: &str) -> &str {
+    token.rsplit_once('.').map_or(token, |(_, token)| token)
+}
+
+pub(super) fn is_pgn_result(token: &str) -> bool {
+    ["1-0", "0-1", "1/2-1/2", "*"].contains(&token)
+}
+
+/// The seven tag roster of a game played from `start`, along with the
+/// position it starts from when it isn't the initial one, then `tags`
+pub(super) fn pgn_roster<'a>(
+    start: &ChessBoard,
+    result: &str,
+    tags: &[(&'a str, String)],
+) -> Vec<(&'a str, String)> {
+    let start_fen = start.to_fen();
+    let initial = ChessBoard::new_variant(start.variant);
+    let mut roster = vec![
+        ("Event", "?".to_string()),
+        ("Site", "?".to_string()),
+        ("Date", "????.??.??".to_string()),
+        ("Round", "?".to_string()),
+        ("White", "?".to_string()),
+        ("Black", "?".to_string()),
+        ("Result", result.to_string()),
+    ];
+    if start.variant != Variant::Standard {
+        roster.push(("Variant", start.variant.to_string()));
+    } else if start.chess960 {
+        roster.push(("Variant", "Chess960".to_string()));
+    }
+    if start_fen != initial.to_fen() {
+        roster.push(("SetUp", "1".to_string()));
+        roster.push(("FEN", start_fen));
+    }
+    for (name, value) in tags {
+        match roster.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.clone(),
+            None => roster.push((name, value.clone())),
+        }
+    }
+    roster
+}
+
+/// Writes the tag pairs, then the movetext `tokens` and the result wrapped
+/// to 79 columns
+pub(super) fn write_pgn(roster: &[(&str, String)], mut tokens: Vec<String>) -> String {
+    let mut result = String::new();
+    for (name, value) in roster {
+        writeln!(
+            result,
+            "[{name} \"{}\"]",
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+        .unwrap();
+    }
+    result.push('\n');
+
+    tokens.push(
+        roster
+            .iter()
+            .find(|(tag, _)| *tag == "Result")
+            .unwrap()
+            .1
+            .clone(),
+    );
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + token.len() + 1 > 79 {
+            result.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            result.push(' ');
+            line_len += 1;
+        }
+        result.push_str(&token);
+        line_len += token.len();
+    }
+    result.push('\n');
+    result
+}
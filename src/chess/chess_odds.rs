@@ -0,0 +1,75 @@
+use super::*;
+use std::str::FromStr;
+
+/// What the stronger player gives up at the start of a handicap game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Odds {
+    /// The f-pawn, and the first move to the opponent
+    PawnAndMove,
+    /// The queenside knight
+    Knight,
+    /// The queenside rook, along with castling on that side
+    Rook,
+    #[allow(missing_docs)]
+    Queen,
+}
+
+/// Every odds preset, from the smallest to the largest
+pub const ODDS: [Odds; 4] = [Odds::PawnAndMove, Odds::Knight, Odds::Rook, Odds::Queen];
+
+impl ChessBoard {
+    /// The starting position with `giver` playing without the material of
+    /// `odds`; the PGN of the game records it in its `FEN` tag
+    pub fn with_odds(odds: Odds, giver: ChessColor) -> Self {
+        let mut board = Self::new();
+        let home_row = board.home_row(giver);
+        let removed = match odds {
+            Odds::PawnAndMove => cvec!(5, board.pawn_row(giver)),
+            Odds::Knight => cvec!(1, home_row),
+            Odds::Rook => cvec!(0, home_row),
+            Odds::Queen => cvec!(3, home_row),
+        };
+        board[removed] = None;
+        match odds {
+            Odds::PawnAndMove => board.turn = giver.opponent(),
+            Odds::Rook => board.castling_rights[giver as usize][QUEENSIDE] = None,
+            Odds::Knight | Odds::Queen => (),
+        }
+        board.rehash();
+        board
+    }
+}
+
+impl fmt::Display for Odds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Odds::PawnAndMove => "pawn and move",
+            Odds::Knight => "knight",
+            Odds::Rook => "rook",
+            Odds::Queen => "queen",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Reads the name of an odds preset, ignoring case, spaces, dashes and
+/// underscores, or the letter of the piece given
+impl FromStr for Odds {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        match key.as_str() {
+            "pawnandmove" | "pawn" | "p" => Ok(Odds::PawnAndMove),
+            "knight" | "n" => Ok(Odds::Knight),
+            "rook" | "r" => Ok(Odds::Rook),
+            "queen" | "q" => Ok(Odds::Queen),
+            _ => Err(ChessError::UnknownOdds(s.to_string())),
+        }
+    }
+}
@@ -1,12 +1,20 @@
 use super::*;
 
+/// A piece standing on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChessPiece {
+    #[allow(missing_docs)]
     pub piece_type: PieceType,
+    #[allow(missing_docs)]
     pub color: ChessColor,
+    /// How many times the piece moved, so far in the game
     pub total_moves: u32,
 }
 
-#[derive(Clone, Copy)]
+/// The kinds of chess pieces
+#[allow(clippy::upper_case_acronyms, missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PieceType {
     KING,
     QUEEN,
@@ -14,10 +22,182 @@ pub enum PieceType {
     KNIGHT,
     ROOK,
     PAWN,
+    /// Moves as a bishop or a knight
+    ARCHBISHOP,
+    /// Moves as a rook or a knight
+    CHANCELLOR,
+    /// Moves as a queen or a knight
+    AMAZON,
 }
 pub use PieceType::*;
 
+/// How many kinds of pieces there are, for tables indexed by [`PieceType`]
+pub(super) const PIECE_TYPES: usize = 9;
+
+/// Every piece type, in [`PieceType`] order
+pub const ALL_PIECE_TYPES: [PieceType; PIECE_TYPES] = [
+    KING, QUEEN, BISHOP, KNIGHT, ROOK, PAWN, ARCHBISHOP, CHANCELLOR, AMAZON,
+];
+
+const WHITE_PIECE_REPR: &str = "KQBNRPACM";
+const BLACK_PIECE_REPR: &str = "kqbnrpacm";
+
+/// How a piece other than a pawn moves: a leap lands `from + offset` whatever
+/// stands between, a slide goes on in its direction until blocked. Every
+/// offset and direction has its opposite in the same list, as capturing
+/// goes both ways
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Movement {
+    #[allow(missing_docs)]
+    pub leaps: &'static [ChessVec],
+    #[allow(missing_docs)]
+    pub slides: &'static [ChessVec],
+}
+
+pub(super) const KNIGHT_OFFSETS: [ChessVec; 8] = [
+    cvec!(1, 2),
+    cvec!(2, 1),
+    cvec!(2, -1),
+    cvec!(1, -2),
+    cvec!(-1, -2),
+    cvec!(-2, -1),
+    cvec!(-2, 1),
+    cvec!(-1, 2),
+];
+pub(super) const KING_OFFSETS: [ChessVec; 8] = [
+    cvec!(0, 1),
+    cvec!(1, 1),
+    cvec!(1, 0),
+    cvec!(1, -1),
+    cvec!(0, -1),
+    cvec!(-1, -1),
+    cvec!(-1, 0),
+    cvec!(-1, 1),
+];
+const ROOK_DIRECTIONS: [ChessVec; 4] = [cvec!(0, 1), cvec!(1, 0), cvec!(0, -1), cvec!(-1, 0)];
+const BISHOP_DIRECTIONS: [ChessVec; 4] = [cvec!(1, 1), cvec!(1, -1), cvec!(-1, -1), cvec!(-1, 1)];
+/// Every offset some built-in piece leaps by, to find the leapers attacking
+/// a square
+const LEAP_OFFSETS: [ChessVec; 16] = {
+    let mut offsets = [cvec!(0, 0); 16];
+    let mut i = 0;
+    while i < 8 {
+        offsets[i] = KING_OFFSETS[i];
+        offsets[i + 8] = KNIGHT_OFFSETS[i];
+        i += 1;
+    }
+    offsets
+};
+
+impl PieceType {
+    /// The uppercase letter used for this piece in FEN and SAN
+    pub fn to_char(self) -> char {
+        WHITE_PIECE_REPR.chars().nth(self as usize).unwrap()
+    }
+
+    /// Reads a piece letter of either case
+    pub fn from_char(c: char) -> Option<Self> {
+        WHITE_PIECE_REPR
+            .find(c.to_ascii_uppercase())
+            .map(|idx| ALL_PIECE_TYPES[idx])
+    }
+
+    /// How the piece moves unless the board redefines it with
+    /// [`ChessBoard::set_movement`]; pawns move by rules of their own, so
+    /// they have neither leaps nor slides here
+    pub const fn movement(self) -> Movement {
+        const fn movement(leaps: &'static [ChessVec], slides: &'static [ChessVec]) -> Movement {
+            Movement { leaps, slides }
+        }
+        match self {
+            KING => movement(&KING_OFFSETS, &[]),
+            QUEEN => movement(&[], &KING_OFFSETS),
+            BISHOP => movement(&[], &BISHOP_DIRECTIONS),
+            KNIGHT => movement(&KNIGHT_OFFSETS, &[]),
+            ROOK => movement(&[], &ROOK_DIRECTIONS),
+            PAWN => movement(&[], &[]),
+            ARCHBISHOP => movement(&KNIGHT_OFFSETS, &BISHOP_DIRECTIONS),
+            CHANCELLOR => movement(&KNIGHT_OFFSETS, &ROOK_DIRECTIONS),
+            AMAZON => movement(&KNIGHT_OFFSETS, &KING_OFFSETS),
+        }
+    }
+}
+
+/// The movements a board gives its pieces in place of the built-in ones,
+/// with every leap and slide among them to find the pieces attacking a square
+#[derive(Debug)]
+pub(super) struct PieceMovements {
+    movements: [Movement; PIECE_TYPES],
+    leaps: Vec<ChessVec>,
+    slides: Vec<ChessVec>,
+}
+
+impl ChessBoard {
+    /// How pieces of `piece_type` move on this board
+    pub fn movement(&self, piece_type: PieceType) -> Movement {
+        match &self.movements {
+            Some(movements) => movements.movements[piece_type as usize],
+            None => piece_type.movement(),
+        }
+    }
+
+    /// Makes pieces of `piece_type` move by `movement` on this board, to play
+    /// fairy pieces under the letter of a built-in one. Pawns keep their own
+    /// rules, and every offset and direction needs its opposite
+    pub fn set_movement(
+        &mut self,
+        piece_type: PieceType,
+        movement: Movement,
+    ) -> Result<(), ChessError> {
+        let invalid = |reason| Err(ChessError::InvalidMovement { reason });
+        if piece_type == PAWN {
+            return invalid("pawns move by rules of their own");
+        }
+        for offsets in [movement.leaps, movement.slides] {
+            if offsets.contains(&cvec!(0, 0)) {
+                return invalid("pieces can't move in place");
+            }
+            if offsets
+                .iter()
+                .any(|&offset| !offsets.contains(&cvec!(-offset.col, -offset.row)))
+            {
+                return invalid("every offset needs its opposite");
+            }
+        }
+
+        let mut movements = ALL_PIECE_TYPES.map(|piece_type| self.movement(piece_type));
+        movements[piece_type as usize] = movement;
+        let mut leaps: Vec<ChessVec> = Vec::new();
+        let mut slides: Vec<ChessVec> = Vec::new();
+        for movement in movements {
+            for (offsets, all) in [(movement.leaps, &mut leaps), (movement.slides, &mut slides)] {
+                for &offset in offsets {
+                    if !all.contains(&offset) {
+                        all.push(offset);
+                    }
+                }
+            }
+        }
+        self.movements = Some(Arc::new(PieceMovements {
+            movements,
+            leaps,
+            slides,
+        }));
+        Ok(())
+    }
+
+    /// Every offset some piece leaps by and every direction some piece
+    /// slides in, to find the pieces attacking a square
+    pub(super) fn attack_offsets(&self) -> (&[ChessVec], &[ChessVec]) {
+        match &self.movements {
+            Some(movements) => (&movements.leaps, &movements.slides),
+            None => (&LEAP_OFFSETS, &KING_OFFSETS),
+        }
+    }
+}
+
 impl ChessPiece {
+    /// A piece that didn't move yet
     pub const fn new(piece_type: PieceType, color: ChessColor) -> Self {
         Self {
             piece_type,
@@ -26,56 +206,101 @@ impl ChessPiece {
         }
     }
 
-    fn is_move_valid(&self, board: &ChessBoard, from: ChessVec, to: ChessVec) -> bool {
-        match self.piece_type {
-            KING => self.is_king_move_valid(board, from, to),
-            QUEEN => self.is_queen_move_valid(board, from, to),
-            BISHOP => self.is_bishop_move_valid(board, from, to),
-            KNIGHT => self.is_knight_move_valid(board, from, to),
-            ROOK => self.is_rook_move_valid(board, from, to),
-            PAWN => self.is_pawn_move_valid(board, from, to),
-        }
-    }
-
-    fn is_king_move_valid(&self, board: &ChessBoard, from: ChessVec, to: ChessVec) -> bool {
-        let diff = (from - to).abs();
-        return diff.row <= 1 && diff.col <= 1;
+    /// Parses a FEN piece letter, uppercase being white
+    pub fn from_char(c: char) -> Option<Self> {
+        let color = if c.is_ascii_uppercase() { WHITE } else { BLACK };
+        PieceType::from_char(c).map(|piece_type| Self::new(piece_type, color))
     }
 
-    fn is_queen_move_valid(&self, board: &ChessBoard, from: ChessVec, to: ChessVec) -> bool {
-        let diff = (from - to).abs();
-        return diff.row == 0 || diff.col == 0 || diff.row == diff.col;
+    /// Pushes the moves this piece could make from `from`, without checking
+    /// whether they leave its own king in check
+    pub(super) fn pseudo_moves(&self, board: &ChessBoard, from: ChessVec, moves: &mut Vec<Move>) {
+        if self.piece_type == PAWN {
+            return self.pawn_moves(board, from, moves);
+        }
+        let movement = board.movement(self.piece_type);
+        self.step_moves(board, from, movement.leaps, moves);
+        self.slide_moves(board, from, movement.slides, moves);
     }
 
-    fn is_bishop_move_valid(&self, board: &ChessBoard, from: ChessVec, to: ChessVec) -> bool {
-        let diff = (from - to).abs();
-        return diff.row == diff.col;
+    fn can_land_on(&self, board: &ChessBoard, to: ChessVec) -> bool {
+        board.contains(to) && board[to].is_none_or(|piece| piece.color != self.color)
     }
 
-    fn is_knight_move_valid(&self, board: &ChessBoard, from: ChessVec, to: ChessVec) -> bool {
-        let diff = (from - to).abs();
-        return (diff.row == 1 && diff.col == 2) || (diff.row == 2 && diff.col == 1);
+    fn step_moves(
+        &self,
+        board: &ChessBoard,
+        from: ChessVec,
+        offsets: &[ChessVec],
+        moves: &mut Vec<Move>,
+    ) {
+        for &offset in offsets {
+            let to = from + offset;
+            if self.can_land_on(board, to) {
+                moves.push(Move::new(from, to));
+            }
+        }
     }
 
-    fn is_rook_move_valid(&self, board: &ChessBoard, from: ChessVec, to: ChessVec) -> bool {
-        let diff = (from - to).abs();
-        return diff.row == 0 || diff.col == 0;
+    fn slide_moves(
+        &self,
+        board: &ChessBoard,
+        from: ChessVec,
+        directions: &[ChessVec],
+        moves: &mut Vec<Move>,
+    ) {
+        for &direction in directions {
+            let mut to = from + direction;
+            while self.can_land_on(board, to) {
+                moves.push(Move::new(from, to));
+                if board[to].is_some() {
+                    break;
+                }
+                to = to + direction;
+            }
+        }
     }
 
-    fn is_pawn_move_valid(&self, board: &ChessBoard, from: ChessVec, to: ChessVec) -> bool {
-        // pawn shouldn't be able to take pieces in front of it!!!
-        let diff = (from - to).abs();
+    fn pawn_moves(&self, board: &ChessBoard, from: ChessVec, moves: &mut Vec<Move>) {
         let direction = board.pawn_direction[self.color as usize];
-        let max_distance = if self.total_moves == 0 { 2 } else { 1 };
-        return diff.col <= max_distance * direction;
+        let mut push = |to: ChessVec| {
+            if to.row == board.promotion_row(self.color) {
+                for &piece_type in board.variant.rules().promotions() {
+                    moves.push(Move::with_promotion(from, to, piece_type));
+                }
+            } else {
+                moves.push(Move::new(from, to));
+            }
+        };
+
+        let one_step = from + cvec!(0, direction);
+        if board.contains(one_step) && board[one_step].is_none() {
+            push(one_step);
+
+            let two_steps = one_step + cvec!(0, direction);
+            let double_steps = from.row == board.pawn_row(self.color)
+                || from.row == board.home_row(self.color)
+                    && board.variant.rules().first_rank_pawns_double_step();
+            if double_steps && board.contains(two_steps) && board[two_steps].is_none() {
+                push(two_steps);
+            }
+        }
+
+        for side in [-1, 1] {
+            let to = from + cvec!(side, direction);
+            if !board.contains(to) {
+                continue;
+            }
+            let takes = board[to].is_some_and(|piece| piece.color != self.color);
+            if takes || board.en_passant == Some(to) {
+                push(to);
+            }
+        }
     }
 }
 
 impl fmt::Display for ChessPiece {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        const WHITE_PIECE_REPR: &str = "KQBNRP";
-        const BLACK_PIECE_REPR: &str = "kqbnrp";
-
         write!(
             f,
             "{}",
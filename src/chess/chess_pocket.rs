@@ -0,0 +1,122 @@
+use super::*;
+
+/// The pieces a pocket can hold, in the order FEN lists them
+const POCKET_PIECES: [PieceType; 8] = [
+    AMAZON, QUEEN, CHANCELLOR, ARCHBISHOP, ROOK, BISHOP, KNIGHT, PAWN,
+];
+
+/// The bit of `pos` in a set of squares
+pub(super) const fn square_bit(pos: ChessVec) -> u128 {
+    1 << (pos.row as usize * MAX_WIDTH + pos.col as usize)
+}
+
+impl ChessBoard {
+    /// The pieces `color` holds and may drop, strongest first
+    pub fn pocket(&self, color: ChessColor) -> Vec<PieceType> {
+        let counts = self.variant_state.pockets[color as usize];
+        POCKET_PIECES
+            .into_iter()
+            .flat_map(|piece_type| (0..counts[piece_type as usize]).map(move |_| piece_type))
+            .collect()
+    }
+
+    /// Whether the piece on `pos` was a pawn once, and goes back to the
+    /// pocket as one when taken
+    pub fn is_promoted(&self, pos: ChessVec) -> bool {
+        self.variant_state.promoted & square_bit(pos) != 0
+    }
+
+    /// Gives `color` a piece to drop, as Bughouse does with the pieces
+    /// taken on the partner board
+    pub fn add_to_pocket(&mut self, color: ChessColor, piece_type: PieceType) {
+        self.toggle_pocket_key(color, piece_type);
+        self.variant_state.pockets[color as usize][piece_type as usize] += 1;
+        self.toggle_pocket_key(color, piece_type);
+    }
+
+    /// The piece taken by the last move as it goes to a pocket, a promoted
+    /// one turning back into a pawn
+    pub fn last_capture(&self) -> Option<ChessPiece> {
+        let last = self.move_stack.last()?;
+        let taken = last.taken_piece?;
+        // the state saved with the move is the one from before it
+        match last.variant_state.promoted & square_bit(last.to) != 0 {
+            true => Some(ChessPiece::new(PAWN, taken.color)),
+            false => Some(ChessPiece::new(taken.piece_type, taken.color)),
+        }
+    }
+
+    pub(super) fn take_from_pocket(&mut self, piece_type: PieceType) -> ChessPiece {
+        self.toggle_pocket_key(self.turn, piece_type);
+        self.variant_state.pockets[self.turn as usize][piece_type as usize] -= 1;
+        self.toggle_pocket_key(self.turn, piece_type);
+        ChessPiece::new(piece_type, self.turn)
+    }
+
+    /// The drops of the side to move on the empty square `to`; pawns can't
+    /// be dropped on the first or last row
+    pub(super) fn drop_moves(&self, to: ChessVec, moves: &mut Vec<Move>) {
+        let pocket = self.variant_state.pockets[self.turn as usize];
+        for piece_type in POCKET_PIECES {
+            let on_back_row = to.row == 0 || to.row == self.height as i32 - 1;
+            if pocket[piece_type as usize] > 0 && !(piece_type == PAWN && on_back_row) {
+                moves.push(Move::new_drop(piece_type, to));
+            }
+        }
+    }
+
+    /// Puts the piece taken by the last move into the pocket of `color`,
+    /// a promoted one going back as a pawn
+    pub(super) fn pocket_last_capture(&mut self, color: ChessColor) {
+        if let Some(taken) = self.last_capture() {
+            self.add_to_pocket(color, taken.piece_type);
+        }
+    }
+
+    /// Follows promoted pieces around the board, as they go back to a
+    /// pocket as pawns once taken
+    pub(super) fn track_promoted(&mut self) {
+        let Some(last) = self.move_stack.last() else {
+            return;
+        };
+        let state = &mut self.variant_state;
+        let moved_promoted = state.promoted & square_bit(last.from) != 0;
+        state.promoted &= !(square_bit(last.from) | square_bit(last.to));
+        if moved_promoted || matches!(last.move_type, Promoting(_)) {
+            state.promoted |= square_bit(last.to);
+        }
+    }
+
+    /// The pockets as FEN writes them after the piece placement, e.g. `[QNp]`
+    pub(super) fn pockets_fen(&self) -> String {
+        let mut field = String::from("[");
+        for color in [WHITE, BLACK] {
+            for piece_type in self.pocket(color) {
+                field.push_str(&ChessPiece::new(piece_type, color).to_string());
+            }
+        }
+        field.push(']');
+        field
+    }
+
+    /// Reads the pieces of the pockets, written in either case for either
+    /// color and in any order
+    pub(super) fn read_pockets_fen(&mut self, field: &str) -> Result<(), ChessError> {
+        let mut pockets = [[0; PIECE_TYPES]; 2];
+        for c in field.chars().filter(|&c| c != '-') {
+            let piece = ChessPiece::from_char(c)
+                .filter(|piece| piece.piece_type != KING)
+                .ok_or(ChessError::InvalidFen {
+                    reason: "invalid piece in a pocket",
+                })?;
+            pockets[piece.color as usize][piece.piece_type as usize] += 1;
+        }
+        self.variant_state.pockets = pockets;
+        Ok(())
+    }
+
+    /// Marks the piece on `pos` as promoted, as FEN does with `~`
+    pub(super) fn mark_promoted(&mut self, pos: ChessVec) {
+        self.variant_state.promoted |= square_bit(pos);
+    }
+}
@@ -0,0 +1,277 @@
+use super::{chess_pocket::square_bit, *};
+use std::str::FromStr;
+
+/// A position set up square by square, checked to be playable before a
+/// game starts from it with [`PositionBuilder::build`]
+#[derive(Clone)]
+pub struct PositionBuilder {
+    board: ChessBoard,
+}
+
+impl Default for PositionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PositionBuilder {
+    /// An empty board of the standard size, white to move
+    pub fn new() -> Self {
+        Self {
+            board: ChessBoard::empty(8, 8).unwrap(),
+        }
+    }
+
+    /// Starts from the current position of `board`, forgetting how it was reached
+    pub fn from_board(board: &ChessBoard) -> Self {
+        let mut board = board.clone();
+        board.move_stack.clear();
+        board.redo_stack.clear();
+        board.selected_pos = None;
+        Self { board }
+    }
+
+    /// The position as set up so far, which may not be playable yet
+    pub fn board(&self) -> &ChessBoard {
+        &self.board
+    }
+
+    /// Puts `piece` on `pos`, replacing the one standing there
+    pub fn put(&mut self, pos: ChessVec, piece: ChessPiece) -> Result<&mut Self, ChessError> {
+        self.set_square(pos, Some(piece))?;
+        Ok(self)
+    }
+
+    /// Takes the piece off `pos`, if any
+    pub fn remove(&mut self, pos: ChessVec) -> Result<&mut Self, ChessError> {
+        self.set_square(pos, None)?;
+        Ok(self)
+    }
+
+    fn set_square(&mut self, pos: ChessVec, piece: Option<ChessPiece>) -> Result<(), ChessError> {
+        if !self.board.contains(pos) {
+            return Err(ChessError::InvalidSquare(pos.to_string()));
+        }
+        self.board[pos] = piece;
+        self.board.variant_state.promoted &= !square_bit(pos);
+        if self.board.en_passant == Some(pos) {
+            self.board.en_passant = None;
+        }
+        for color in [WHITE, BLACK] {
+            let king = self
+                .board
+                .pieces()
+                .find(|(_, piece)| piece.piece_type == KING && piece.color == color);
+            if let Some((pos, _)) = king {
+                self.board.kings_pos[color as usize] = pos;
+            }
+        }
+        self.board.rehash();
+        Ok(())
+    }
+
+    /// Takes every piece off the board, along with the castling rights and
+    /// the en passant square
+    pub fn clear(&mut self) -> &mut Self {
+        self.board.grid = [[None; MAX_WIDTH]; MAX_HEIGHT];
+        self.board.castling_rights = [[None; 2]; 2];
+        self.board.en_passant = None;
+        self.board.variant_state.promoted = 0;
+        self.board.rehash();
+        self
+    }
+
+    /// Gives the move to `color`
+    pub fn turn(&mut self, color: ChessColor) -> &mut Self {
+        self.board.turn = color;
+        self.board.rehash();
+        self
+    }
+
+    /// Sets the castling rights from the castling field of FEN, X-FEN or
+    /// Shredder-FEN, `-` taking them all away
+    pub fn castling(&mut self, field: &str) -> Result<&mut Self, ChessError> {
+        let mut board = self.board.clone();
+        board.castling_rights = [[None; 2]; 2];
+        if field != "-" {
+            board.read_castling_fen(field)?;
+        }
+        board.rehash();
+        self.board = board;
+        Ok(self)
+    }
+
+    /// Sets the square a pawn skipped by moving two squares, to be taken
+    /// en passant
+    pub fn en_passant(&mut self, square: Option<ChessVec>) -> Result<&mut Self, ChessError> {
+        if let Some(pos) = square.filter(|&pos| !self.board.contains(pos)) {
+            return Err(ChessError::InvalidSquare(pos.to_string()));
+        }
+        self.board.en_passant = square;
+        self.board.rehash();
+        Ok(self)
+    }
+
+    /// Plays the position with the rules of `variant`
+    pub fn variant(&mut self, variant: Variant) -> &mut Self {
+        self.board.variant = variant;
+        self.board.rehash();
+        self
+    }
+
+    /// Edits the position with a command of the board editors:
+    ///
+    /// - `Qd1` or `put Qd1` puts a white queen on d1, `put qd8` a black one on d8
+    /// - `remove d1` or `x d1` empties d1, `clear` the whole board
+    /// - `start` sets up the starting position of the variant
+    /// - `fen <FEN>` sets up a position from FEN
+    /// - `turn w` or `turn b` gives the move to a side
+    /// - `castling KQkq` sets the castling rights, `castling -` takes them away
+    /// - `ep e3` sets the en passant square, `ep -` clears it
+    /// - `variant <name>` plays the position with other rules
+    pub fn edit(&mut self, command: &str) -> Result<(), ChessError> {
+        let invalid = || ChessError::InvalidCommand(command.trim().to_string());
+        let (name, argument) = match command.trim().split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, Some(argument.trim())),
+            None => (command.trim(), None),
+        };
+        match (name.to_lowercase().as_str(), argument) {
+            ("put", Some(placement)) => self.put_placement(placement)?,
+            ("remove" | "x", Some(square)) => {
+                self.remove(square.parse()?)?;
+            }
+            ("clear", None) => {
+                self.clear();
+            }
+            ("start", None) => self.board = ChessBoard::new_variant(self.board.variant),
+            ("fen", Some(fen)) => {
+                self.board = ChessBoard::from_variant_fen(fen, self.board.variant)?
+            }
+            ("turn", Some("w" | "white")) => {
+                self.turn(WHITE);
+            }
+            ("turn", Some("b" | "black")) => {
+                self.turn(BLACK);
+            }
+            ("castling", Some(field)) => {
+                self.castling(field)?;
+            }
+            ("ep", Some("-")) => {
+                self.en_passant(None)?;
+            }
+            ("ep", Some(square)) => {
+                self.en_passant(Some(square.parse()?))?;
+            }
+            ("variant", Some(variant)) => {
+                self.variant(variant.parse()?);
+            }
+            (_, None) => self.put_placement(name).map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        }
+        Ok(())
+    }
+
+    /// Puts a piece given as its FEN letter followed by its square, as in `Qd1`
+    fn put_placement(&mut self, placement: &str) -> Result<(), ChessError> {
+        let mut chars = placement.chars();
+        let piece = chars
+            .next()
+            .and_then(ChessPiece::from_char)
+            .ok_or_else(|| ChessError::InvalidCommand(placement.to_string()))?;
+        self.put(ChessVec::from_str(chars.as_str())?, piece)?;
+        Ok(())
+    }
+
+    /// Checks the position can be played: each side has one king, no pawn
+    /// stands on a back rank, the side not to move isn't in check, and the
+    /// castling rights and en passant square match the pieces
+    pub fn validate(&self) -> Result<(), ChessError> {
+        let board = &self.board;
+        let rules = board.variant.rules();
+        let invalid = |reason| Err(ChessError::InvalidPosition { reason });
+
+        let mut kings = [0; 2];
+        for (pos, piece) in board.pieces() {
+            match piece.piece_type {
+                KING => kings[piece.color as usize] += 1,
+                PAWN if pos.row == board.promotion_row(piece.color)
+                    || pos.row == board.home_row(piece.color)
+                        && !rules.first_rank_pawns_double_step() =>
+                {
+                    return invalid("pawns can't stand on the first or last rank");
+                }
+                _ => (),
+            }
+        }
+        match rules.kings() {
+            Some([1, 1]) if kings != [1, 1] => return invalid("each side needs exactly one king"),
+            Some(expected) if kings != expected => {
+                return invalid("the kings don't match the variant")
+            }
+            _ => (),
+        }
+
+        if board.in_check(board.turn.opponent()) {
+            return invalid("the side not to move is in check");
+        }
+
+        for color in [WHITE, BLACK] {
+            let home_row = board.home_row(color);
+            let king_pos = board.kings_pos[color as usize];
+            for side in [KINGSIDE, QUEENSIDE] {
+                let Some(col) = board.castling_rights[color as usize][side] else {
+                    continue;
+                };
+                let rook_pos = cvec!(col, home_row);
+                let on_its_side = match side {
+                    KINGSIDE => col > king_pos.col,
+                    _ => col < king_pos.col,
+                };
+                if !board.has_king(color)
+                    || king_pos.row != home_row
+                    || !on_its_side
+                    || !board[rook_pos]
+                        .is_some_and(|rook| rook.piece_type == ROOK && rook.color == color)
+                {
+                    return invalid("castling needs the king and its rook on the first rank");
+                }
+            }
+        }
+
+        if let Some(pos) = board.en_passant {
+            // the side not to move just pushed a pawn two squares past `pos`
+            let mover = board.turn.opponent();
+            let direction = board.pawn_direction[mover as usize];
+            let from = cvec!(pos.col, pos.row - direction);
+            let to = cvec!(pos.col, pos.row + direction);
+            let double_step_row = from.row == board.pawn_row(mover)
+                || rules.first_rank_pawns_double_step() && from.row == board.home_row(mover);
+            if !double_step_row
+                || !board.contains(to)
+                || board[from].is_some()
+                || board[pos].is_some()
+                || !board[to].is_some_and(|pawn| pawn.piece_type == PAWN && pawn.color == mover)
+            {
+                return invalid("no pawn just skipped the en passant square");
+            }
+        }
+        Ok(())
+    }
+
+    /// The board to play from, once the position is checked with
+    /// [`PositionBuilder::validate`]
+    pub fn build(&self) -> Result<ChessBoard, ChessError> {
+        self.validate()?;
+        let mut board = self.board.clone();
+        // pieces may have moved since the rights were given, which decides
+        // whether they read as Chess960 ones
+        let field = board.castling_fen(false);
+        board.castling_rights = [[None; 2]; 2];
+        board.chess960 = false;
+        if field != "-" {
+            board.read_castling_fen(&field)?;
+        }
+        board.set_variant(board.variant);
+        Ok(board)
+    }
+}
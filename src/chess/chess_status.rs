@@ -0,0 +1,89 @@
+use super::*;
+
+/// Whether the game on a board is still going, and how it ended otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate {
+        winner: ChessColor,
+    },
+    Stalemate,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    /// A win the variant played adds to checkmate
+    VariantWin {
+        winner: ChessColor,
+        reason: &'static str,
+    },
+    /// A draw the variant played adds to the usual ones
+    VariantDraw {
+        reason: &'static str,
+    },
+}
+
+impl GameStatus {
+    /// Whether the game ended
+    pub fn is_over(&self) -> bool {
+        !matches!(self, GameStatus::Ongoing)
+    }
+
+    /// The side that won, if any
+    pub fn winner(&self) -> Option<ChessColor> {
+        match *self {
+            GameStatus::Checkmate { winner } | GameStatus::VariantWin { winner, .. } => {
+                Some(winner)
+            }
+            _ => None,
+        }
+    }
+
+    /// The PGN result token, e.g. `1-0` or `1/2-1/2`
+    pub fn result(&self) -> &'static str {
+        match (self, self.winner()) {
+            (GameStatus::Ongoing, _) => "*",
+            (_, Some(WHITE)) => "1-0",
+            (_, Some(BLACK)) => "0-1",
+            (_, None) => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameStatus::Ongoing => write!(f, "ongoing"),
+            GameStatus::Checkmate { winner } => write!(f, "checkmate, {winner} wins"),
+            GameStatus::Stalemate => write!(f, "stalemate"),
+            GameStatus::FiftyMoveRule => write!(f, "draw by the fifty-move rule"),
+            GameStatus::InsufficientMaterial => write!(f, "draw by insufficient material"),
+            GameStatus::VariantWin { winner, reason } => write!(f, "{reason}, {winner} wins"),
+            GameStatus::VariantDraw { reason } => write!(f, "{reason}, draw"),
+        }
+    }
+}
+
+impl ChessBoard {
+    /// Whether the game is over, and how
+    pub fn status(&self) -> GameStatus {
+        let rules = self.variant.rules();
+        if let Some(outcome) = rules.outcome(self) {
+            return outcome;
+        }
+        if self.legal_moves().is_empty() {
+            return rules.outcome_without_moves(self);
+        }
+        self.draw_by_rule().unwrap_or(GameStatus::Ongoing)
+    }
+
+    /// The draw the position is in regardless of the moves available, if any
+    pub fn draw_by_rule(&self) -> Option<GameStatus> {
+        if self.halfmove_clock >= 100 {
+            Some(GameStatus::FiftyMoveRule)
+        } else if self.variant.rules().has_insufficient_material(self) {
+            Some(GameStatus::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+}
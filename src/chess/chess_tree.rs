@@ -0,0 +1,429 @@
+use super::chess_notation::{
+    is_pgn_result, pgn_roster, pgn_start, split_pgn, strip_move_number, write_pgn,
+};
+use super::*;
+
+/// Suffix annotations and the glyph each stands for
+const SUFFIX_NAGS: [(&str, u8); 6] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+];
+
+/// A move of a [`GameTree`] with its annotations and the moves answering it
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TreeNode {
+    mv: Option<Move>,
+    san: String,
+    comment: Option<String>,
+    nags: Vec<u8>,
+    children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    /// The move leading to this node, none for the root
+    pub fn mv(&self) -> Option<Move> {
+        self.mv
+    }
+
+    /// The move in standard algebraic notation, empty for the root
+    pub fn san(&self) -> &str {
+        &self.san
+    }
+
+    /// Text about the position after the move
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Numeric annotation glyphs, e.g. `1` for `!` or `4` for `??`
+    pub fn nags(&self) -> &[u8] {
+        &self.nags
+    }
+
+    /// The moves answering this one, the mainline first
+    pub fn children(&self) -> &[TreeNode] {
+        &self.children
+    }
+
+    fn child_index(&self, mv: Move) -> Option<usize> {
+        self.children.iter().position(|child| child.mv == Some(mv))
+    }
+}
+
+/// A game along with its variations, for analysis. A cursor points at the
+/// node whose position is shown and where moves are added
+#[derive(Clone)]
+pub struct GameTree {
+    start: ChessBoard,
+    root: TreeNode,
+    /// Child indices leading from the root to the cursor
+    path: Vec<usize>,
+    board: ChessBoard,
+}
+
+impl Default for GameTree {
+    fn default() -> Self {
+        Self::new(&ChessBoard::new())
+    }
+}
+
+impl GameTree {
+    /// A tree whose mainline is the game of `board`, with the cursor at its
+    /// current position
+    pub fn new(board: &ChessBoard) -> Self {
+        let start = board.starting_position();
+        let mut tree = Self {
+            start: start.clone(),
+            root: TreeNode::default(),
+            path: Vec::new(),
+            board: start,
+        };
+        for mv in board.moves() {
+            tree.play(mv).unwrap();
+        }
+        tree
+    }
+
+    /// The position at the cursor
+    pub fn board(&self) -> &ChessBoard {
+        &self.board
+    }
+
+    /// The position the game starts from
+    pub fn start(&self) -> &ChessBoard {
+        &self.start
+    }
+
+    /// The node before the first move, holding the whole tree
+    pub fn root(&self) -> &TreeNode {
+        &self.root
+    }
+
+    /// The node at the cursor
+    pub fn current(&self) -> &TreeNode {
+        self.path
+            .iter()
+            .fold(&self.root, |node, &index| &node.children[index])
+    }
+
+    fn current_mut(&mut self) -> &mut TreeNode {
+        self.path
+            .iter()
+            .fold(&mut self.root, |node, &index| &mut node.children[index])
+    }
+
+    /// The child indices leading from the root to the cursor, `0` being the
+    /// mainline at every move
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// The moves of the mainline, from the start to the end of the game
+    pub fn mainline(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let mut node = &self.root;
+        while let Some(child) = node.children.first() {
+            moves.extend(child.mv);
+            node = child;
+        }
+        moves
+    }
+
+    /// Plays `mv` from the cursor: goes to it when the tree has it already,
+    /// adds it as a new variation otherwise
+    pub fn play(&mut self, mv: Move) -> Result<(), ChessError> {
+        self.board.make_move(mv)?;
+        // read the move back, with the promotion filled in
+        let mv = self.board.last_move().unwrap();
+        if let Some(index) = self.current().child_index(mv) {
+            self.path.push(index);
+            return Ok(());
+        }
+
+        self.board.retract_move()?;
+        let san = self.board.to_san(mv);
+        self.board.apply_move(mv);
+
+        let node = self.current_mut();
+        node.children.push(TreeNode {
+            mv: Some(mv),
+            san,
+            ..TreeNode::default()
+        });
+        let index = node.children.len() - 1;
+        self.path.push(index);
+        Ok(())
+    }
+
+    /// Moves the cursor one move back
+    pub fn back(&mut self) -> Result<(), ChessError> {
+        self.path.pop().ok_or(ChessError::NoMoveToUndo)?;
+        self.board.retract_move()?;
+        Ok(())
+    }
+
+    /// Moves the cursor one move forward along the mainline
+    pub fn forward(&mut self) -> Result<(), ChessError> {
+        self.enter_variation(0)
+            .map_err(|_| ChessError::NoMoveToRedo)
+    }
+
+    /// Moves the cursor to the `index`th answer of the current move, `0`
+    /// being the mainline
+    pub fn enter_variation(&mut self, index: usize) -> Result<(), ChessError> {
+        let mv = self
+            .current()
+            .children
+            .get(index)
+            .and_then(|child| child.mv)
+            .ok_or(ChessError::NoSuchVariation(index))?;
+        self.board.apply_move(mv);
+        self.path.push(index);
+        Ok(())
+    }
+
+    /// Moves the cursor to the node at the end of `path`
+    pub fn goto(&mut self, path: &[usize]) -> Result<(), ChessError> {
+        let mut node = &self.root;
+        let mut board = self.start.clone();
+        for &index in path {
+            node = node
+                .children
+                .get(index)
+                .ok_or(ChessError::NoSuchVariation(index))?;
+            board.apply_move(node.mv.unwrap());
+        }
+        self.board = board;
+        self.path = path.to_vec();
+        Ok(())
+    }
+
+    /// Moves the cursor before the first move
+    pub fn goto_start(&mut self) {
+        self.board = self.start.clone();
+        self.path.clear();
+    }
+
+    /// Moves the cursor forward to the end of its line
+    pub fn goto_end(&mut self) {
+        while self.forward().is_ok() {}
+    }
+
+    /// Comments the position at the cursor, or removes its comment
+    pub fn set_comment(&mut self, comment: Option<String>) {
+        self.current_mut().comment = comment;
+    }
+
+    /// Annotates the move at the cursor with a numeric annotation glyph
+    pub fn add_nag(&mut self, nag: u8) {
+        let nags = &mut self.current_mut().nags;
+        if !nags.contains(&nag) {
+            nags.push(nag);
+        }
+    }
+
+    /// Removes the annotation glyphs of the move at the cursor
+    pub fn clear_nags(&mut self) {
+        self.current_mut().nags.clear();
+    }
+
+    /// Where the variation holding the cursor branches off: the length of
+    /// the path to the move that starts it
+    fn branch_point(&self) -> Result<usize, ChessError> {
+        self.path
+            .iter()
+            .rposition(|&index| index != 0)
+            .ok_or(ChessError::NotInVariation)
+    }
+
+    /// Makes the variation holding the cursor the main answer where it
+    /// branches off, the former mainline becoming its first variation
+    pub fn promote_variation(&mut self) -> Result<(), ChessError> {
+        let depth = self.branch_point()?;
+        let index = self.path[depth];
+        let parent = self.path[..depth]
+            .iter()
+            .fold(&mut self.root, |node, &index| &mut node.children[index]);
+        let node = parent.children.remove(index);
+        parent.children.insert(0, node);
+        self.path[depth] = 0;
+        Ok(())
+    }
+
+    /// Removes the variation holding the cursor, from the move it branches
+    /// off with, and moves the cursor to where it started
+    pub fn delete_variation(&mut self) -> Result<(), ChessError> {
+        let depth = self.branch_point()?;
+        let index = self.path[depth];
+        let parent = self.path[..depth]
+            .iter()
+            .fold(&mut self.root, |node, &index| &mut node.children[index]);
+        parent.children.remove(index);
+
+        while self.path.len() > depth {
+            self.back()?;
+        }
+        Ok(())
+    }
+
+    /// Exports the tree in Portable Game Notation, the variations as
+    /// recursive annotation variations. `tags` are handled like in
+    /// [`ChessBoard::to_pgn`], the result being taken from the mainline
+    pub fn to_pgn(&self, tags: &[(&str, String)]) -> String {
+        let mut end = self.start.clone();
+        for mv in self.mainline() {
+            end.apply_move(mv);
+        }
+        let roster = pgn_roster(&self.start, end.status().result(), tags);
+
+        let mut tokens = Vec::new();
+        push_comment(&mut tokens, &self.root);
+        let first_ply = 2 * (self.start.fullmove_number as usize - 1) + self.start.turn as usize;
+        push_line(&mut tokens, &self.root, first_ply, true);
+        write_pgn(&roster, tokens)
+    }
+
+    /// Reads a game in Portable Game Notation along with its comments,
+    /// annotation glyphs and variations, with the cursor at the start
+    pub fn from_pgn(pgn: &str) -> Result<(Self, PgnTags), ChessError> {
+        let (tags, movetext) = split_pgn(pgn)?;
+        let mut tree = Self::new(&pgn_start(&tags)?);
+
+        // where to come back to when each open variation closes
+        let mut variations: Vec<Vec<usize>> = Vec::new();
+        let mut comment: Option<String> = None;
+        let mut line_comment = false;
+        let mut token = String::new();
+        for c in movetext.chars().chain(Some(' ')) {
+            if let Some(text) = comment.as_mut() {
+                if c == '}' && !line_comment || c == '\n' && line_comment {
+                    tree.append_comment(text.trim());
+                    comment = None;
+                } else {
+                    text.push(c);
+                }
+                continue;
+            }
+            if !matches!(c, '{' | ';' | '(' | ')') && !c.is_whitespace() {
+                token.push(c);
+                continue;
+            }
+            tree.play_pgn_token(&token)?;
+            token.clear();
+            match c {
+                '{' | ';' => {
+                    comment = Some(String::new());
+                    line_comment = c == ';';
+                }
+                '(' => {
+                    variations.push(tree.path.clone());
+                    // a variation replaces the move before it
+                    tree.back().map_err(|_| ChessError::InvalidPgn {
+                        reason: "a variation doesn't follow a move",
+                    })?;
+                }
+                ')' => {
+                    let path = variations.pop().ok_or(ChessError::InvalidPgn {
+                        reason: "a variation is closed but not opened",
+                    })?;
+                    tree.goto(&path)?;
+                }
+                _ => (),
+            }
+        }
+        if comment.is_some() && !line_comment {
+            return Err(ChessError::InvalidPgn {
+                reason: "a comment isn't closed",
+            });
+        }
+        if !variations.is_empty() {
+            return Err(ChessError::InvalidPgn {
+                reason: "a variation isn't closed",
+            });
+        }
+        tree.goto_start();
+        Ok((tree, tags))
+    }
+
+    fn append_comment(&mut self, text: &str) {
+        let node = self.current_mut();
+        node.comment = Some(match node.comment.take() {
+            Some(comment) => format!("{comment} {text}"),
+            None => text.to_string(),
+        });
+    }
+
+    fn play_pgn_token(&mut self, token: &str) -> Result<(), ChessError> {
+        let token = strip_move_number(token);
+        if token.is_empty() || is_pgn_result(token) {
+            return Ok(());
+        }
+        if let Some(nag) = token.strip_prefix('$') {
+            let nag = nag.parse().map_err(|_| ChessError::InvalidPgn {
+                reason: "invalid annotation glyph",
+            })?;
+            self.add_nag(nag);
+            return Ok(());
+        }
+
+        let san = token.trim_end_matches(['!', '?']);
+        let suffix = &token[san.len()..];
+        if !san.is_empty() {
+            let mv = self.board.parse_san(san)?;
+            self.play(mv)?;
+        }
+        if let Some(&(_, nag)) = SUFFIX_NAGS.iter().find(|(text, _)| *text == suffix) {
+            self.add_nag(nag);
+        }
+        Ok(())
+    }
+}
+
+fn push_comment(tokens: &mut Vec<String>, node: &TreeNode) {
+    if let Some(comment) = &node.comment {
+        // a closing brace would end the comment early
+        tokens.push(format!("{{{}}}", comment.replace('}', "")));
+    }
+}
+
+/// Pushes `node` with its annotations, preceded by its number on white's
+/// moves or when `numbered`
+fn push_move(tokens: &mut Vec<String>, node: &TreeNode, ply: usize, numbered: bool) {
+    let number = ply / 2 + 1;
+    if ply.is_multiple_of(2) {
+        tokens.push(format!("{number}."));
+    } else if numbered {
+        tokens.push(format!("{number}..."));
+    }
+    tokens.push(node.san.clone());
+    tokens.extend(node.nags.iter().map(|nag| format!("${nag}")));
+    push_comment(tokens, node);
+}
+
+/// Pushes the mainline answering `node`, `ply` being the ply of its first
+/// move, with the variations of every move right after it
+fn push_line(tokens: &mut Vec<String>, node: &TreeNode, ply: usize, numbered: bool) {
+    let Some((main, variations)) = node.children.split_first() else {
+        return;
+    };
+    push_move(tokens, main, ply, numbered);
+    for variation in variations {
+        let mut variation_tokens = Vec::new();
+        push_move(&mut variation_tokens, variation, ply, true);
+        push_line(
+            &mut variation_tokens,
+            variation,
+            ply + 1,
+            variation.comment.is_some(),
+        );
+        variation_tokens[0].insert(0, '(');
+        variation_tokens.last_mut().unwrap().push(')');
+        tokens.extend(variation_tokens);
+    }
+    let numbered = !variations.is_empty() || main.comment.is_some();
+    push_line(tokens, main, ply + 1, numbered);
+}
@@ -0,0 +1,614 @@
+use super::*;
+use std::str::FromStr;
+
+/// The rules of chess a board is played with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    /// Plain chess
+    #[default]
+    Standard,
+    /// Bringing the king to one of the four center squares wins as well
+    KingOfTheHill,
+    /// Checking the opposing king a third time wins as well
+    ThreeCheck,
+    /// Captures are forced, kings are plain pieces, and the side losing all
+    /// of its pieces or left without moves wins
+    Antichess,
+    /// Taken pieces change sides and can be dropped back on the board
+    Crazyhouse,
+    /// Captures blow up the taker and every piece but pawns around, and
+    /// blowing up the opposing king wins
+    Atomic,
+    /// Crazyhouse on two boards, the pieces taken on one going to the
+    /// pocket of the partner on the other
+    Bughouse,
+    /// White has a horde of pawns and no king, and wins by checkmate while
+    /// Black wins by taking every piece of the horde
+    Horde,
+    /// Both kings race to the last rank without ever giving check
+    RacingKings,
+    /// Played on a 10x8 board with an archbishop and a chancellor on each side
+    Capablanca,
+}
+
+/// Every variant, in the order of [`Variant`]
+pub const VARIANTS: [Variant; 10] = [
+    Variant::Standard,
+    Variant::KingOfTheHill,
+    Variant::ThreeCheck,
+    Variant::Antichess,
+    Variant::Crazyhouse,
+    Variant::Atomic,
+    Variant::Bughouse,
+    Variant::Horde,
+    Variant::RacingKings,
+    Variant::Capablanca,
+];
+
+/// State some variants keep besides the position, restored on undo
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct VariantState {
+    /// Checks given by each color, for three-check
+    pub(super) checks: [u8; 2],
+    /// Pieces in hand by color and piece type, for Crazyhouse
+    pub(super) pockets: [[u8; PIECE_TYPES]; 2],
+    /// The squares of pieces that were promoted, going back to the pocket
+    /// as pawns once taken
+    pub(super) promoted: u128,
+}
+
+/// The hooks a variant overrides to change the rules, all of them
+/// defaulting to standard chess
+pub trait VariantRules: Sync {
+    /// The name used in PGN `Variant` tags
+    fn name(&self) -> &'static str;
+
+    /// The position games start from in FEN, when it isn't the standard one
+    fn starting_fen(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// How many kings each side has, when the variant fixes it
+    fn kings(&self) -> Option<[u32; 2]> {
+        self.has_checks().then_some([1, 1])
+    }
+
+    /// Whether pawns on the first rank may move two squares, as from the second
+    fn first_rank_pawns_double_step(&self) -> bool {
+        false
+    }
+
+    /// Whether kings may not be left in check; they are plain pieces otherwise
+    fn has_checks(&self) -> bool {
+        true
+    }
+
+    /// Whether the king of `color` is attacked, as long as kings can be
+    fn in_check(&self, board: &ChessBoard, color: ChessColor) -> bool {
+        board.is_attacked(board.kings_pos[color as usize], color.opponent())
+    }
+
+    /// Whether captures blow up the pieces around them
+    fn has_explosions(&self) -> bool {
+        false
+    }
+
+    /// Whether taken pieces go to the pocket of the taker, to be dropped
+    /// back on the board
+    fn has_pockets(&self) -> bool {
+        false
+    }
+
+    /// Whether kings may castle
+    fn can_castle(&self) -> bool {
+        true
+    }
+
+    /// The pieces a pawn may promote to
+    fn promotions(&self) -> &'static [PieceType] {
+        &[QUEEN, ROOK, BISHOP, KNIGHT]
+    }
+
+    /// Removes the moves the variant forbids from the legal ones; the board
+    /// may play them to find out, as long as it takes them back
+    fn restrict_moves(&self, _board: &mut ChessBoard, _moves: &mut Vec<Move>) {}
+
+    /// Updates the state of the variant once a move was played
+    fn after_move(&self, _board: &mut ChessBoard) {}
+
+    /// How the variant ended the game before the usual rules apply, if it did
+    fn outcome(&self, _board: &ChessBoard) -> Option<GameStatus> {
+        None
+    }
+
+    /// How the game ends when the side to move has no legal move
+    fn outcome_without_moves(&self, board: &ChessBoard) -> GameStatus {
+        if board.in_check(board.turn) {
+            GameStatus::Checkmate {
+                winner: board.turn.opponent(),
+            }
+        } else {
+            GameStatus::Stalemate
+        }
+    }
+
+    /// Whether neither side can win with the pieces left
+    fn has_insufficient_material(&self, board: &ChessBoard) -> bool {
+        let mut minor_pieces = 0;
+        for (_, piece) in board.pieces() {
+            match piece.piece_type {
+                KING => (),
+                BISHOP | KNIGHT => minor_pieces += 1,
+                _ => return false,
+            }
+        }
+        minor_pieces <= 1
+    }
+
+    /// Extra FEN field written after the en passant square, if any
+    fn fen_field(&self, _board: &ChessBoard) -> Option<String> {
+        None
+    }
+}
+
+struct Standard;
+struct KingOfTheHill;
+struct ThreeCheck;
+struct Antichess;
+struct Crazyhouse;
+struct Atomic;
+struct Bughouse;
+struct Horde;
+struct RacingKings;
+struct Capablanca;
+
+impl Variant {
+    /// The rules of the variant
+    pub fn rules(self) -> &'static dyn VariantRules {
+        match self {
+            Variant::Standard => &Standard,
+            Variant::KingOfTheHill => &KingOfTheHill,
+            Variant::ThreeCheck => &ThreeCheck,
+            Variant::Antichess => &Antichess,
+            Variant::Crazyhouse => &Crazyhouse,
+            Variant::Atomic => &Atomic,
+            Variant::Bughouse => &Bughouse,
+            Variant::Horde => &Horde,
+            Variant::RacingKings => &RacingKings,
+            Variant::Capablanca => &Capablanca,
+        }
+    }
+
+    /// Whether games of the variant start from the standard position, so
+    /// they may start from a Chess960 or odds one instead
+    pub fn has_standard_start(self) -> bool {
+        self.rules().starting_fen().is_none()
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.rules().name())
+    }
+}
+
+/// Reads the name of a variant as PGN tags and chess servers write it,
+/// ignoring case, spaces, dashes and underscores
+impl FromStr for Variant {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        match key.as_str() {
+            "standard" | "chess" | "normal" | "" => Ok(Variant::Standard),
+            "kingofthehill" | "koth" => Ok(Variant::KingOfTheHill),
+            "threecheck" | "3check" => Ok(Variant::ThreeCheck),
+            "antichess" | "giveaway" | "suicide" => Ok(Variant::Antichess),
+            "crazyhouse" | "zh" => Ok(Variant::Crazyhouse),
+            "atomic" => Ok(Variant::Atomic),
+            "bughouse" | "bug" => Ok(Variant::Bughouse),
+            "horde" => Ok(Variant::Horde),
+            "racingkings" | "racing" => Ok(Variant::RacingKings),
+            "capablanca" | "capa" => Ok(Variant::Capablanca),
+            _ => Err(ChessError::UnknownVariant(s.to_string())),
+        }
+    }
+}
+
+impl VariantRules for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+}
+
+const HILL: [ChessVec; 4] = [cvec!(3, 3), cvec!(4, 3), cvec!(3, 4), cvec!(4, 4)];
+
+impl VariantRules for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn outcome(&self, board: &ChessBoard) -> Option<GameStatus> {
+        HILL.into_iter().find_map(|pos| {
+            board[pos]
+                .filter(|piece| piece.piece_type == KING)
+                .map(|king| GameStatus::VariantWin {
+                    winner: king.color,
+                    reason: "the king reached the hill",
+                })
+        })
+    }
+
+    fn has_insufficient_material(&self, _board: &ChessBoard) -> bool {
+        false
+    }
+}
+
+/// How many checks win a game of three-check
+const WINNING_CHECKS: u8 = 3;
+
+impl VariantRules for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "Three-check"
+    }
+
+    fn after_move(&self, board: &mut ChessBoard) {
+        if board.in_check(board.turn) {
+            board.variant_state.checks[board.turn.opponent() as usize] += 1;
+        }
+    }
+
+    fn outcome(&self, board: &ChessBoard) -> Option<GameStatus> {
+        [WHITE, BLACK]
+            .into_iter()
+            .find(|&color| board.variant_state.checks[color as usize] >= WINNING_CHECKS)
+            .map(|winner| GameStatus::VariantWin {
+                winner,
+                reason: "the king was checked three times",
+            })
+    }
+
+    /// Any piece besides the kings can still give checks
+    fn has_insufficient_material(&self, board: &ChessBoard) -> bool {
+        board.pieces().all(|(_, piece)| piece.piece_type == KING)
+    }
+
+    /// Checks each side has left to give, as in `3+3`
+    fn fen_field(&self, board: &ChessBoard) -> Option<String> {
+        let [white, black] = board
+            .variant_state
+            .checks
+            .map(|checks| WINNING_CHECKS.saturating_sub(checks));
+        Some(format!("{white}+{black}"))
+    }
+}
+
+impl VariantRules for Antichess {
+    fn name(&self) -> &'static str {
+        "Antichess"
+    }
+
+    fn has_checks(&self) -> bool {
+        false
+    }
+
+    fn can_castle(&self) -> bool {
+        false
+    }
+
+    fn promotions(&self) -> &'static [PieceType] {
+        &[QUEEN, ROOK, BISHOP, KNIGHT, KING]
+    }
+
+    /// Taking is compulsory when possible
+    fn restrict_moves(&self, board: &mut ChessBoard, moves: &mut Vec<Move>) {
+        if moves.iter().any(|&mv| board.is_capture(mv)) {
+            moves.retain(|&mv| board.is_capture(mv));
+        }
+    }
+
+    fn outcome_without_moves(&self, board: &ChessBoard) -> GameStatus {
+        GameStatus::VariantWin {
+            winner: board.turn,
+            reason: "no move is left",
+        }
+    }
+
+    fn has_insufficient_material(&self, _board: &ChessBoard) -> bool {
+        false
+    }
+}
+
+impl VariantRules for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+
+    fn has_pockets(&self) -> bool {
+        true
+    }
+
+    fn after_move(&self, board: &mut ChessBoard) {
+        board.pocket_last_capture(board.turn.opponent());
+        board.track_promoted();
+    }
+
+    /// Taken pieces come back, so no material is ever too little
+    fn has_insufficient_material(&self, _board: &ChessBoard) -> bool {
+        false
+    }
+}
+
+/// The rules of one board; passing taken pieces to the partner board is up
+/// to whoever links the two, with [`ChessBoard::last_capture`] and
+/// [`ChessBoard::add_to_pocket`]
+impl VariantRules for Bughouse {
+    fn name(&self) -> &'static str {
+        "Bughouse"
+    }
+
+    fn has_pockets(&self) -> bool {
+        true
+    }
+
+    fn after_move(&self, board: &mut ChessBoard) {
+        board.track_promoted();
+    }
+
+    /// Pieces may come from the partner board, so no material is ever too little
+    fn has_insufficient_material(&self, _board: &ChessBoard) -> bool {
+        false
+    }
+}
+
+impl VariantRules for Atomic {
+    fn name(&self) -> &'static str {
+        "Atomic"
+    }
+
+    fn has_explosions(&self) -> bool {
+        true
+    }
+
+    /// Losing the king counts as being in check, so no move may blow it up,
+    /// while blowing up the other one is always allowed. Touching kings
+    /// can't take each other, as the taker would blow up as well
+    fn in_check(&self, board: &ChessBoard, color: ChessColor) -> bool {
+        let [king, other_king] = [color, color.opponent()].map(|color| {
+            board
+                .has_king(color)
+                .then_some(board.kings_pos[color as usize])
+        });
+        match (king, other_king) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(king), Some(other_king)) => {
+                let distance = (king - other_king).abs();
+                distance.col.max(distance.row) > 1 && board.is_attacked(king, color.opponent())
+            }
+        }
+    }
+
+    fn outcome(&self, board: &ChessBoard) -> Option<GameStatus> {
+        [WHITE, BLACK]
+            .into_iter()
+            .find(|&color| !board.has_king(color))
+            .map(|loser| GameStatus::VariantWin {
+                winner: loser.opponent(),
+                reason: "the king blew up",
+            })
+    }
+}
+
+/// The Horde starting position, 36 white pawns against the usual black army
+const HORDE_FEN: &str =
+    "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+
+impl VariantRules for Horde {
+    fn name(&self) -> &'static str {
+        "Horde"
+    }
+
+    fn starting_fen(&self) -> Option<&'static str> {
+        Some(HORDE_FEN)
+    }
+
+    /// The horde has no king
+    fn kings(&self) -> Option<[u32; 2]> {
+        Some([0, 1])
+    }
+
+    fn first_rank_pawns_double_step(&self) -> bool {
+        true
+    }
+
+    fn in_check(&self, board: &ChessBoard, color: ChessColor) -> bool {
+        color == BLACK && board.is_attacked(board.kings_pos[BLACK as usize], WHITE)
+    }
+
+    fn outcome_without_moves(&self, board: &ChessBoard) -> GameStatus {
+        let horde_left = board.pieces().any(|(_, piece)| piece.color == WHITE);
+        if board.turn == WHITE && !horde_left {
+            GameStatus::VariantWin {
+                winner: BLACK,
+                reason: "the horde was wiped out",
+            }
+        } else if board.in_check(board.turn) {
+            GameStatus::Checkmate { winner: WHITE }
+        } else {
+            GameStatus::Stalemate
+        }
+    }
+
+    /// Black wins by taking everything, so material never runs out for both
+    fn has_insufficient_material(&self, _board: &ChessBoard) -> bool {
+        false
+    }
+}
+
+/// The Racing Kings starting position, both armies side by side on the
+/// first two ranks
+const RACING_KINGS_FEN: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+
+impl VariantRules for RacingKings {
+    fn name(&self) -> &'static str {
+        "Racing Kings"
+    }
+
+    fn starting_fen(&self) -> Option<&'static str> {
+        Some(RACING_KINGS_FEN)
+    }
+
+    /// Giving check is forbidden as well
+    fn restrict_moves(&self, board: &mut ChessBoard, moves: &mut Vec<Move>) {
+        moves.retain(|&mv| !board.peek_move(mv, |board| board.in_check(board.turn)));
+    }
+
+    /// White reaching the last rank first only wins if Black can't follow
+    /// with its king right away, which draws
+    fn outcome(&self, board: &ChessBoard) -> Option<GameStatus> {
+        let goal = board.height as i32 - 1;
+        let arrived = [WHITE, BLACK].map(|color| board.kings_pos[color as usize].row == goal);
+        let reason = "the king reached the last rank";
+        match arrived {
+            [true, true] => Some(GameStatus::VariantDraw {
+                reason: "both kings reached the last rank",
+            }),
+            [false, true] => Some(GameStatus::VariantWin {
+                winner: BLACK,
+                reason,
+            }),
+            [true, false] => {
+                let black_king = board.kings_pos[BLACK as usize];
+                let black_follows = board.turn == BLACK
+                    && board
+                        .legal_moves_from(black_king)
+                        .iter()
+                        .any(|mv| mv.to.row == goal);
+                (!black_follows).then_some(GameStatus::VariantWin {
+                    winner: WHITE,
+                    reason,
+                })
+            }
+            [false, false] => None,
+        }
+    }
+
+    /// A lone king can still win the race
+    fn has_insufficient_material(&self, _board: &ChessBoard) -> bool {
+        false
+    }
+}
+
+/// The Capablanca starting position, the archbishop between the queenside
+/// knight and bishop and the chancellor between the kingside ones
+const CAPABLANCA_FEN: &str = "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1";
+
+impl VariantRules for Capablanca {
+    fn name(&self) -> &'static str {
+        "Capablanca"
+    }
+
+    fn starting_fen(&self) -> Option<&'static str> {
+        Some(CAPABLANCA_FEN)
+    }
+
+    fn promotions(&self) -> &'static [PieceType] {
+        &[QUEEN, CHANCELLOR, ARCHBISHOP, ROOK, BISHOP, KNIGHT]
+    }
+}
+
+impl ChessBoard {
+    /// Whether the king of `color` is still on the board, as it may blow up
+    pub(super) fn has_king(&self, color: ChessColor) -> bool {
+        self[self.kings_pos[color as usize]]
+            .is_some_and(|piece| piece.piece_type == KING && piece.color == color)
+    }
+
+    /// Blows up the piece that took on `pos` along with every piece around
+    /// but pawns, returning them so the move can be undone
+    pub(super) fn explode(&mut self, pos: ChessVec) -> Vec<(ChessVec, ChessPiece)> {
+        let mut exploded: Vec<_> = self[pos]
+            .take()
+            .map(|taker| (pos, taker))
+            .into_iter()
+            .collect();
+        for offset in KING_OFFSETS {
+            let at = pos + offset;
+            if !self.contains(at) || self[at].is_some_and(|piece| piece.piece_type == PAWN) {
+                continue;
+            }
+            if let Some(piece) = self[at].take() {
+                exploded.push((at, piece));
+            }
+        }
+        exploded
+    }
+
+    /// The variant the game is played in
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// The starting position of `variant`
+    pub fn new_variant(variant: Variant) -> Self {
+        match variant.rules().starting_fen() {
+            Some(fen) => Self::from_variant_fen(fen, variant).unwrap(),
+            None => {
+                let mut board = Self::new();
+                board.set_variant(variant);
+                board
+            }
+        }
+    }
+
+    /// Plays the game with the rules of `variant` from now on, dropping the
+    /// castling rights it doesn't allow
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+        if !variant.rules().can_castle() {
+            self.castling_rights = [[None; 2]; 2];
+        }
+        self.rehash();
+    }
+
+    /// Checks each color gave so far, which three-check counts
+    pub fn checks_given(&self, color: ChessColor) -> u8 {
+        self.variant_state.checks[color as usize]
+    }
+
+    /// Whether `mv` takes a piece, en passant included
+    pub fn is_capture(&self, mv: Move) -> bool {
+        let is_pawn = self[mv.from].is_some_and(|piece| piece.piece_type == PAWN);
+        self[mv.to].is_some_and(|piece| piece.color != self.turn)
+            || (is_pawn && mv.from.col != mv.to.col)
+    }
+
+    /// Reads the three-check field of FEN: checks left to give as in `3+3`,
+    /// or checks given as in `+0+0`
+    pub(super) fn read_checks_fen(&mut self, field: &str) -> Result<(), ChessError> {
+        let invalid = ChessError::InvalidFen {
+            reason: "invalid check counters",
+        };
+        let (given, counters) = match field.strip_prefix('+') {
+            Some(counters) => (true, counters),
+            None => (false, field),
+        };
+        let (white, black) = counters.split_once('+').ok_or(invalid.clone())?;
+        let mut checks = [0; 2];
+        for (color, counter) in [(WHITE, white), (BLACK, black)] {
+            let counter: u8 = counter.parse().map_err(|_| invalid.clone())?;
+            checks[color as usize] = match given {
+                true => counter,
+                false => WINNING_CHECKS.saturating_sub(counter),
+            };
+        }
+        self.variant_state.checks = checks;
+        Ok(())
+    }
+}
@@ -1,52 +1,110 @@
-use std::ops::{Add, Sub};
+use super::{ChessError, MAX_HEIGHT, MAX_WIDTH};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt,
+    ops::{Add, Sub},
+    str::FromStr,
+};
 
+/// Builds a [`ChessVec`](crate::chess::ChessVec) from a column and a row, or
+/// from a square name literal such as `"e4"`
 #[macro_export]
 macro_rules! cvec {
     ($col:expr, $row:expr) => {
-        crate::chess::ChessVec::new($col, $row)
+        $crate::chess::ChessVec::new($col, $row)
     };
     ($pos: literal) => {{
         let mut str = $pos.to_string();
-        crate::chess::ChessVec::try_from(&mut str).unwrap()
+        $crate::chess::ChessVec::try_from(&mut str).unwrap()
     }};
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A square of the board, or the offset between two squares
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ChessVec {
+    /// From the bottom: row 0 is the 1st rank
     pub row: i32,
+    /// From the left: column 0 is the a-file
     pub col: i32,
 }
 
 impl ChessVec {
+    /// The square at column `col` and row `row`
     pub const fn new(col: i32, row: i32) -> Self {
         Self { row, col }
     }
 
+    /// The offset with both coordinates made positive
     pub fn abs(mut self) -> Self {
         self.row = self.row.abs();
         self.col = self.col.abs();
         self
     }
+
+    /// Whether the position lies on the largest board there can be; see
+    /// [`ChessBoard::contains`](super::ChessBoard::contains) for a given one
+    pub fn is_valid(&self) -> bool {
+        (0..MAX_WIDTH as i32).contains(&self.col) && (0..MAX_HEIGHT as i32).contains(&self.row)
+    }
 }
 
 impl TryFrom<&mut String> for ChessVec {
-    type Error = &'static str;
+    type Error = ChessError;
 
     fn try_from(value: &mut String) -> Result<Self, Self::Error> {
-        const ROWS: &'static str = "87654321";
-        const COLS: &'static str = "abcdefgh";
+        const COLS: &str = "abcdefghijkl";
 
-        let Some(row_idx) = value.chars().position(|c| ROWS.contains(c)) else {
-            return Err("[Warning]: no row number was found");
+        // a rank takes two digits only when the largest board has it
+        let Some(row_idx) = value.find(|c: char| ('1'..='9').contains(&c)) else {
+            return Err(ChessError::InvalidSquare(value.trim().to_string()));
+        };
+        let rank = match value.get(row_idx..row_idx + 2).map(str::parse::<usize>) {
+            Some(Ok(rank)) if rank <= MAX_HEIGHT => {
+                value.replace_range(row_idx..row_idx + 2, "");
+                rank
+            }
+            _ => value.remove(row_idx).to_digit(10).unwrap() as usize,
         };
-        let row = ROWS.find(value.remove(row_idx)).unwrap();
 
-        let Some(col_idx) = value.chars().position(|c| COLS.contains(c)) else {
-            return Err("[Warning]: no column character was found");
+        let Some(col_idx) = value.find(|c: char| COLS.contains(c)) else {
+            return Err(ChessError::InvalidSquare(value.trim().to_string()));
         };
         let col = COLS.find(value.remove(col_idx)).unwrap();
 
-        Ok(Self::new(col.try_into().unwrap(), row.try_into().unwrap()))
+        Ok(Self::new(col as i32, rank as i32 - 1))
+    }
+}
+
+/// Reads exactly a square name, such as `e4`
+impl FromStr for ChessVec {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChessVec::try_from(&mut s.to_string())
+            .ok()
+            .filter(|pos| pos.to_string() == s)
+            .ok_or_else(|| ChessError::InvalidSquare(s.to_string()))
+    }
+}
+
+/// Serialized as its square name
+impl Serialize for ChessVec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChessVec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for ChessVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", (b'a' + self.col as u8) as char, self.row + 1)
     }
 }
 
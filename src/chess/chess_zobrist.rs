@@ -0,0 +1,271 @@
+use super::{chess_pocket::square_bit, *};
+
+const SQUARES: usize = MAX_WIDTH * MAX_HEIGHT;
+/// Pocket counts with a key of their own, up to the 32 pawns of the two
+/// boards of Bughouse; larger counts share the last key
+const POCKET_COUNTS: usize = 33;
+
+/// Random numbers XORed together for every feature of a position
+struct ZobristKeys {
+    pieces: [[[u64; SQUARES]; PIECE_TYPES]; 2],
+    /// By color, castling side and column of the rook
+    castling: [[[u64; MAX_WIDTH]; 2]; 2],
+    /// By column of the en passant square
+    en_passant: [u64; MAX_WIDTH],
+    black_to_move: u64,
+    /// By color, piece type and number of pieces of the type in the pocket
+    pockets: [[[u64; POCKET_COUNTS]; PIECE_TYPES]; 2],
+    /// By color and checks given, up to three
+    checks: [[u64; 4]; 2],
+    promoted: [u64; SQUARES],
+    variants: [u64; VARIANTS.len()],
+}
+
+/// The keys drawn from a fixed seed, so they are the same on every run
+static KEYS: ZobristKeys = {
+    let mut state = 0x9e37_79b9_7f4a_7c15;
+    // splitmix64
+    const fn next(state: &mut u64) -> u64 {
+        *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    let mut keys = ZobristKeys {
+        pieces: [[[0; SQUARES]; PIECE_TYPES]; 2],
+        castling: [[[0; MAX_WIDTH]; 2]; 2],
+        en_passant: [0; MAX_WIDTH],
+        black_to_move: 0,
+        pockets: [[[0; POCKET_COUNTS]; PIECE_TYPES]; 2],
+        checks: [[0; 4]; 2],
+        promoted: [0; SQUARES],
+        variants: [0; VARIANTS.len()],
+    };
+    let mut color = 0;
+    while color < 2 {
+        let mut piece_type = 0;
+        while piece_type < PIECE_TYPES {
+            let mut square = 0;
+            while square < SQUARES {
+                keys.pieces[color][piece_type][square] = next(&mut state);
+                square += 1;
+            }
+            let mut count = 0;
+            while count < POCKET_COUNTS {
+                keys.pockets[color][piece_type][count] = next(&mut state);
+                count += 1;
+            }
+            piece_type += 1;
+        }
+        let mut side = 0;
+        while side < 2 {
+            let mut col = 0;
+            while col < MAX_WIDTH {
+                keys.castling[color][side][col] = next(&mut state);
+                col += 1;
+            }
+            side += 1;
+        }
+        let mut checks = 0;
+        while checks < 4 {
+            keys.checks[color][checks] = next(&mut state);
+            checks += 1;
+        }
+        color += 1;
+    }
+    let mut i = 0;
+    while i < SQUARES {
+        keys.promoted[i] = next(&mut state);
+        if i < MAX_WIDTH {
+            keys.en_passant[i] = next(&mut state);
+        }
+        if i < VARIANTS.len() {
+            keys.variants[i] = next(&mut state);
+        }
+        i += 1;
+    }
+    keys.black_to_move = next(&mut state);
+    keys
+};
+
+const fn square(pos: ChessVec) -> usize {
+    pos.row as usize * MAX_WIDTH + pos.col as usize
+}
+
+const fn pocket_key(color: ChessColor, piece_type: usize, count: u8) -> u64 {
+    let count = count as usize;
+    let count = if count < POCKET_COUNTS {
+        count
+    } else {
+        POCKET_COUNTS - 1
+    };
+    KEYS.pockets[color as usize][piece_type][count]
+}
+
+/// The castling rights, the en passant square and the checks given, part
+/// of the key
+const fn rights_key(
+    castling_rights: &CastlingRights,
+    en_passant: Option<ChessVec>,
+    checks: [u8; 2],
+) -> u64 {
+    let mut key = 0;
+    let mut color = 0;
+    while color < 2 {
+        let mut side = 0;
+        while side < 2 {
+            if let Some(col) = castling_rights[color][side] {
+                key ^= KEYS.castling[color][side][col as usize];
+            }
+            side += 1;
+        }
+        let checks = if checks[color] < 3 { checks[color] } else { 3 };
+        key ^= KEYS.checks[color][checks as usize];
+        color += 1;
+    }
+    if let Some(pos) = en_passant {
+        key ^= KEYS.en_passant[pos.col as usize];
+    }
+    key
+}
+
+impl ChessBoard {
+    /// A Zobrist hash of the position: the pieces, the side to move, the
+    /// castling rights, the en passant square and the variant with its
+    /// state, but not the move clocks. Equal positions hash the same
+    /// however they were reached. Kept up to date move after move, unlike
+    /// [`ChessBoard::zobrist_key`]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// The hash [`ChessBoard::key`] holds, computed from the whole position
+    pub const fn zobrist_key(&self) -> u64 {
+        let mut key = KEYS.variants[self.variant as usize];
+        let mut row = 0;
+        while row < self.height {
+            let mut col = 0;
+            while col < self.width {
+                let pos = cvec!(col as i32, row as i32);
+                key ^= self.square_key(pos);
+                col += 1;
+            }
+            row += 1;
+        }
+        if let BLACK = self.turn {
+            key ^= KEYS.black_to_move;
+        }
+        let mut color = 0;
+        while color < 2 {
+            let mut piece_type = 0;
+            while piece_type < PIECE_TYPES {
+                let count = self.variant_state.pockets[color][piece_type];
+                key ^= pocket_key([WHITE, BLACK][color], piece_type, count);
+                piece_type += 1;
+            }
+            color += 1;
+        }
+        key ^ rights_key(
+            &self.castling_rights,
+            self.en_passant,
+            self.variant_state.checks,
+        )
+    }
+
+    /// Brings the key up to date with a position set up otherwise than by
+    /// playing moves
+    pub(super) const fn rehash(&mut self) {
+        self.key = self.zobrist_key();
+    }
+
+    /// What stands on the `touched` squares, part of the key
+    pub(super) fn squares_key(&self, mut touched: u128) -> u64 {
+        let mut key = 0;
+        while touched != 0 {
+            let square = touched.trailing_zeros() as usize;
+            touched &= touched - 1;
+            let pos = cvec!((square % MAX_WIDTH) as i32, (square / MAX_WIDTH) as i32);
+            key ^= self.square_key(pos);
+        }
+        key
+    }
+
+    /// Finishes the key of the move just played, which changed what stands
+    /// on the `touched` squares and whose key was taken out before it; the
+    /// side to move and the pockets update the key themselves
+    pub(super) fn finish_move_key(&mut self, touched: u128) {
+        self.key ^= self.squares_key(touched);
+        let Some(last) = self.move_stack.last() else {
+            return;
+        };
+        let (castling_rights, en_passant, checks) = (
+            self.castling_rights,
+            self.en_passant,
+            self.variant_state.checks,
+        );
+        if (
+            last.castling_rights,
+            last.en_passant,
+            last.variant_state.checks,
+        ) != (castling_rights, en_passant, checks)
+        {
+            self.key ^= rights_key(
+                &last.castling_rights,
+                last.en_passant,
+                last.variant_state.checks,
+            ) ^ rights_key(&castling_rights, en_passant, checks);
+        }
+    }
+
+    const fn square_key(&self, pos: ChessVec) -> u64 {
+        let Some(piece) = self.grid[pos.row as usize][pos.col as usize] else {
+            return 0;
+        };
+        let key = KEYS.pieces[piece.color as usize][piece.piece_type as usize][square(pos)];
+        match self.variant_state.promoted & square_bit(pos) {
+            0 => key,
+            _ => key ^ KEYS.promoted[square(pos)],
+        }
+    }
+
+    /// The squares `mv` may change, as a bit per square: the squares it
+    /// leaves and reaches, the pawn it takes en passant, the row of a king
+    /// that may castle, and around the square of a capture when captures
+    /// explode
+    pub(super) fn touched_squares(&self, mv: Move) -> u128 {
+        if mv.drop.is_some() {
+            return square_bit(mv.to);
+        }
+        let mut touched = square_bit(mv.from) | square_bit(mv.to);
+        let piece_type = self[mv.from].map(|piece| piece.piece_type);
+        let pawn_capture = piece_type == Some(PAWN) && mv.from.col != mv.to.col;
+        match piece_type {
+            Some(KING) => touched |= ((1 << self.width) - 1) << (mv.from.row as usize * MAX_WIDTH),
+            _ if pawn_capture => touched |= square_bit(cvec!(mv.to.col, mv.from.row)),
+            _ => (),
+        }
+        let capture = pawn_capture || self[mv.to].is_some();
+        if capture && self.variant.rules().has_explosions() {
+            for offset in KING_OFFSETS {
+                let pos = mv.to + offset;
+                if self.contains(pos) {
+                    touched |= square_bit(pos);
+                }
+            }
+        }
+        touched
+    }
+
+    pub(super) fn toggle_turn_key(&mut self) {
+        self.key ^= KEYS.black_to_move;
+    }
+
+    /// Toggles the key of the pocket count of `piece_type` for `color`, on
+    /// the way from or to the count it has now
+    pub(super) fn toggle_pocket_key(&mut self, color: ChessColor, piece_type: PieceType) {
+        let count = self.variant_state.pockets[color as usize][piece_type as usize];
+        self.key ^= pocket_key(color, piece_type as usize, count);
+    }
+}
@@ -1,139 +1,263 @@
+//! The rules of chess: the board, its pieces and moves, notation, the
+//! status of a game, and game trees with variations for analysis
+
 pub use crate::cvec;
-use std::fmt::Write;
-use std::mem;
+use serde::{Deserialize, Serialize};
 use std::{
     fmt,
     ops::{Index, IndexMut},
+    sync::Arc,
 };
 
-mod chess_vec;
+mod chess_castling;
+mod chess_error;
+mod chess_move;
+mod chess_notation;
+mod chess_odds;
 mod chess_piece;
-
+mod chess_pocket;
+mod chess_setup;
+mod chess_status;
+mod chess_tree;
+mod chess_variant;
+mod chess_vec;
+mod chess_zobrist;
+
+pub use chess_castling::*;
+pub use chess_error::*;
+pub use chess_move::*;
+pub use chess_notation::*;
+pub use chess_odds::*;
+pub use chess_piece::*;
+pub use chess_setup::*;
+pub use chess_status::*;
+pub use chess_tree::*;
+pub use chess_variant::*;
 pub use chess_vec::*;
-use chess_piece::*;
 
-const WIDTH: usize = 8;
-const HEIGHT: usize = 8;
+/// The most columns a board can have
+pub const MAX_WIDTH: usize = 12;
+/// The most rows a board can have
+pub const MAX_HEIGHT: usize = 10;
+
+const KINGSIDE: usize = 0;
+const QUEENSIDE: usize = 1;
 
+/// A game of chess: the pieces on the board, the side to move, and the
+/// moves played so far so they can be undone and redone
+#[derive(Clone)]
 pub struct ChessBoard {
-    grid: [[Option<ChessPiece>; WIDTH]; HEIGHT],
+    /// Indexed by row then column; only the first `height` rows and
+    /// `width` columns are on the board
+    grid: [[Option<ChessPiece>; MAX_WIDTH]; MAX_HEIGHT],
+    width: usize,
+    height: usize,
     turn: ChessColor,
     move_stack: Vec<ChessMove>,
+    /// Undone moves, the next one to redo last
+    redo_stack: Vec<Move>,
     kings_pos: [ChessVec; 2],
-    pawn_direction : [i32; 2],
+    pawn_direction: [i32; 2],
     selected_pos: Option<ChessVec>,
+    /// The column of the rook each side can still castle with, indexed by
+    /// color, then by [`KINGSIDE`] or [`QUEENSIDE`]
+    castling_rights: CastlingRights,
+    en_passant: Option<ChessVec>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    /// Whether castling moves are written as the king taking its own rook,
+    /// as Chess960 needs
+    chess960: bool,
+    variant: Variant,
+    variant_state: VariantState,
+    /// See [`ChessBoard::key`]
+    key: u64,
+    /// Set by [`ChessBoard::set_movement`], the built-in movements when `None`
+    movements: Option<Arc<PieceMovements>>,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+/// The side a piece or player plays for; white moves first
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ChessColor {
+    #[allow(missing_docs)]
     WHITE = 0,
+    #[allow(missing_docs)]
     BLACK = 1,
 }
 use ChessColor::*;
 
+#[derive(Clone)]
 struct ChessMove {
     from: ChessVec,
     to: ChessVec,
     taken_piece: Option<ChessPiece>,
     move_type: MoveType,
+    /// Pieces blown up by a capture in Atomic chess, the taker first
+    exploded: Vec<(ChessVec, ChessPiece)>,
+    // state overwritten by the move, restored on undo
+    castling_rights: CastlingRights,
+    en_passant: Option<ChessVec>,
+    halfmove_clock: u32,
+    variant_state: VariantState,
+    key: u64,
 }
 
+#[derive(Clone)]
 enum MoveType {
     Normal,
     EnPassant(ChessVec),
-    Casteling(Box<ChessMove>),
+    Casteling {
+        king_to: ChessVec,
+        rook_from: ChessVec,
+        rook_to: ChessVec,
+    },
     Promoting(PieceType),
+    /// A piece of the pocket put on the board
+    Drop(PieceType),
 }
 use MoveType::*;
 
+impl ChessColor {
+    /// The other color
+    pub fn opponent(self) -> Self {
+        match self {
+            WHITE => BLACK,
+            BLACK => WHITE,
+        }
+    }
+}
+
+impl fmt::Display for ChessColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", ["white", "black"][*self as usize])
+    }
+}
+
+impl Default for ChessBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ChessBoard {
+    /// The initial position of a game
     pub const fn new() -> Self {
-        let mut grid = [const { [const { None }; WIDTH] }; HEIGHT];
-
-        grid[0][0] = Some(ChessPiece::new(ROOK, BLACK));
-        grid[0][1] = Some(ChessPiece::new(KNIGHT, BLACK));
-        grid[0][2] = Some(ChessPiece::new(BISHOP, BLACK));
-        grid[0][3] = Some(ChessPiece::new(QUEEN, BLACK));
-        grid[0][4] = Some(ChessPiece::new(KING, BLACK));
-        grid[0][5] = Some(ChessPiece::new(BISHOP, BLACK));
-        grid[0][6] = Some(ChessPiece::new(KNIGHT, BLACK));
-        grid[0][7] = Some(ChessPiece::new(ROOK, BLACK));
-        grid[1] = [const { Some(ChessPiece::new(PAWN, BLACK)) }; 8];
-
-        grid[6] = [const { Some(ChessPiece::new(PAWN, WHITE)) }; 8];
-        grid[7][0] = Some(ChessPiece::new(ROOK, WHITE));
-        grid[7][1] = Some(ChessPiece::new(KNIGHT, WHITE));
-        grid[7][2] = Some(ChessPiece::new(BISHOP, WHITE));
-        grid[7][3] = Some(ChessPiece::new(QUEEN, WHITE));
-        grid[7][4] = Some(ChessPiece::new(KING, WHITE));
-        grid[7][5] = Some(ChessPiece::new(BISHOP, WHITE));
-        grid[7][6] = Some(ChessPiece::new(KNIGHT, WHITE));
-        grid[7][7] = Some(ChessPiece::new(ROOK, WHITE));
-
-        let mut pawn_direction = [0; 2];
-        pawn_direction[WHITE as usize] = -1;
-        pawn_direction[BLACK as usize] = 1;
+        let mut grid = [const { [const { None }; MAX_WIDTH] }; MAX_HEIGHT];
+
+        const BACK_ROW: [PieceType; 8] = [ROOK, KNIGHT, BISHOP, QUEEN, KING, BISHOP, KNIGHT, ROOK];
+        let mut col = 0;
+        while col < 8 {
+            grid[0][col] = Some(ChessPiece::new(BACK_ROW[col], WHITE));
+            grid[1][col] = Some(ChessPiece::new(PAWN, WHITE));
+            grid[6][col] = Some(ChessPiece::new(PAWN, BLACK));
+            grid[7][col] = Some(ChessPiece::new(BACK_ROW[col], BLACK));
+            col += 1;
+        }
 
-        Self {
+        let mut board = Self {
             grid,
+            width: 8,
+            height: 8,
             turn: ChessColor::WHITE,
             move_stack: Vec::new(),
-            kings_pos: [cvec!(4, 7), cvec!(4, 0)],
-            pawn_direction,
+            redo_stack: Vec::new(),
+            kings_pos: [cvec!(4, 0), cvec!(4, 7)],
+            pawn_direction: Self::PAWN_DIRECTION,
             selected_pos: None,
+            castling_rights: [[Some(7), Some(0)]; 2],
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            chess960: false,
+            variant: Variant::Standard,
+            variant_state: VariantState {
+                checks: [0; 2],
+                pockets: [[0; PIECE_TYPES]; 2],
+                promoted: 0,
+            },
+            key: 0,
+            movements: None,
+        };
+        board.rehash();
+        board
+    }
+
+    const PAWN_DIRECTION: [i32; 2] = {
+        let mut pawn_direction = [0; 2];
+        pawn_direction[WHITE as usize] = 1;
+        pawn_direction[BLACK as usize] = -1;
+        pawn_direction
+    };
+
+    /// A board of `width` columns and `height` rows without any piece
+    pub fn empty(width: usize, height: usize) -> Result<Self, ChessError> {
+        if !(1..=MAX_WIDTH).contains(&width) || !(1..=MAX_HEIGHT).contains(&height) {
+            return Err(ChessError::InvalidBoardSize { width, height });
         }
+        let mut board = Self::new();
+        board.grid = [[None; MAX_WIDTH]; MAX_HEIGHT];
+        board.width = width;
+        board.height = height;
+        board.castling_rights = [[None; 2]; 2];
+        board.rehash();
+        Ok(board)
     }
 
-    pub fn to_fen(&self) -> String {
-        let mut result = String::new();
-        // add board state
-        for row in self.grid.iter() {
-            let spaces = row.iter().fold(0, |mut spaces, i| {
-                match i {
-                    Some(piece) => {
-                        if spaces > 0 {
-                            result.push_str(&spaces.to_string());
-                            spaces = 0;
-                        }
-                        result.push_str(&piece.to_string());
-                    }
-                    None => spaces += 1,
-                }
-                spaces
-            });
-            if spaces > 0 {
-                result.push_str(&spaces.to_string());
-            }
-            result.push('/');
+    /// Number of columns of the board
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows of the board
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether `pos` lies on this board
+    pub fn contains(&self, pos: ChessVec) -> bool {
+        (0..self.width as i32).contains(&pos.col) && (0..self.height as i32).contains(&pos.row)
+    }
+
+    /// The row holding the king and rooks of `color` at the start
+    fn home_row(&self, color: ChessColor) -> i32 {
+        match color {
+            WHITE => 0,
+            BLACK => self.height as i32 - 1,
         }
-        let _ = result.pop();
-        // add current turn
-        write!(result, " {}", ['w', 'b'][self.turn as usize]).unwrap();
-        return result;
     }
 
-    fn get_king(&self, color: ChessColor) -> (&ChessPiece, ChessVec) {
-        let king_pos = self.kings_pos[color as usize];
-        return (self[king_pos].as_ref().unwrap(), king_pos);
+    /// The row pawns of `color` start on and may double step from
+    fn pawn_row(&self, color: ChessColor) -> i32 {
+        self.home_row(color) + self.pawn_direction[color as usize]
+    }
+
+    fn promotion_row(&self, color: ChessColor) -> i32 {
+        self.home_row(color.opponent())
     }
 
+    /// The side to move
     pub fn get_turn(&self) -> ChessColor {
         self.turn
     }
 
+    /// Passes the move to the other side, without moving any piece
     pub fn swap_turn(&mut self) {
         self.turn = match self.turn {
             WHITE => BLACK,
             BLACK => WHITE,
         };
+        self.toggle_turn_key();
     }
 
-    pub fn select_piece(&mut self, pos: ChessVec) -> Result<(), &'static str> {
-        match &self[pos] {
+    /// Selects the piece on `pos` to move it next, if it belongs to the side to move
+    pub fn select_piece(&mut self, pos: ChessVec) -> Result<(), ChessError> {
+        match self[pos] {
             Some(piece) if piece.color != self.turn => {
-                Err("[Warning]: this is not your piece to select")
+                Err(ChessError::NotYourPiece { square: pos, piece })
             }
-            None => Err("[Warning]: there is no piece to select"),
+            None => Err(ChessError::NoPieceAt(pos)),
             _ => {
                 self.selected_pos = Some(pos);
                 Ok(())
@@ -141,100 +265,398 @@ impl ChessBoard {
         }
     }
 
+    /// Whether a piece was selected to move
     pub fn is_piece_selected(&self) -> bool {
         self.selected_pos.is_some()
     }
 
-    pub fn deselect_piece(&mut self) -> Result<(), &'static str> {
+    /// The square of the selected piece
+    pub fn get_selected(&self) -> Option<ChessVec> {
+        self.selected_pos
+    }
+
+    /// Forgets the selected piece
+    pub fn deselect_piece(&mut self) -> Result<(), ChessError> {
         if self.selected_pos.is_none() {
-            Err("[Warning]: no piece was selected")
+            Err(ChessError::NoPieceSelected)
         } else {
             self.selected_pos = None;
             Ok(())
         }
     }
 
-    pub fn move_piece(&mut self, from: ChessVec, to: ChessVec) -> Result<(), &'static str> {
-        match &self[from] {
+    /// Moves the piece on `from` to `to` without checking the rules of chess,
+    /// only that the piece belongs to the side to move; see [`ChessBoard::make_move`]
+    pub fn move_piece(&mut self, from: ChessVec, to: ChessVec) -> Result<(), ChessError> {
+        match self[from] {
             Some(piece) if piece.color != self.turn => {
-                return Err("[Warning]: this is not your piece to move");
+                return Err(ChessError::NotYourPiece {
+                    square: from,
+                    piece,
+                });
             }
-            None => return Err("[Warning]: there is no piece to move"),
+            None => return Err(ChessError::NoPieceAt(from)),
             _ => (),
         }
-        let mut piece = self[from].take().unwrap();
+        let mv = Move::new(from, to);
+        self.branch_off(mv);
+        self.apply_move(mv);
+        Ok(())
+    }
 
-        piece.total_moves += 1;
+    /// Moves the selected piece to `to`, unchecked like [`ChessBoard::move_piece`]
+    pub fn move_selected(&mut self, to: ChessVec) -> Result<(), ChessError> {
+        let Some(selected_pos) = self.selected_pos else {
+            return Err(ChessError::NoPieceSelected);
+        };
+        self.move_piece(selected_pos, to)
+    }
 
-        if matches!(piece.piece_type, KING) {
-            self.kings_pos[piece.color as usize] = to;
+    /// Plays `mv` if it is legal in the current position; a pawn reaching the
+    /// last row without a promotion piece becomes a queen
+    pub fn make_move(&mut self, mut mv: Move) -> Result<(), ChessError> {
+        match self[mv.from] {
+            Some(piece) if piece.color != self.turn => {
+                return Err(ChessError::NotYourPiece {
+                    square: mv.from,
+                    piece,
+                });
+            }
+            Some(piece)
+                if piece.piece_type == PAWN
+                    && mv.promotion.is_none()
+                    && mv.to.row == self.promotion_row(piece.color) =>
+            {
+                mv.promotion = Some(QUEEN);
+            }
+            None if mv.drop.is_none() => return Err(ChessError::NoPieceAt(mv.from)),
+            _ => (),
+        }
+        if !self.legal_moves_from(mv.from).contains(&mv) {
+            // castling may be written the way the other notation does
+            mv = self.as_castling(mv).ok_or(ChessError::IllegalMove(mv))?;
         }
+        self.branch_off(mv);
+        self.apply_move(mv);
+        Ok(())
+    }
+
+    /// Plays the selected piece to `to`, following the rules of chess
+    pub fn make_selected(&mut self, to: ChessVec) -> Result<(), ChessError> {
+        let Some(selected_pos) = self.selected_pos else {
+            return Err(ChessError::NoPieceSelected);
+        };
+        self.make_move(Move::new(selected_pos, to))
+    }
+
+    /// Plays `mv` without any check, used once a move is known to be legal
+    pub(crate) fn apply_move(&mut self, mv: Move) {
+        let key = self.key;
+        let touched = self.touched_squares(mv);
+        self.key ^= self.squares_key(touched);
+        self.play_move(mv, key);
+        self.finish_move_key(touched);
+    }
 
-        let taken_piece = mem::replace(&mut self[to], Some(piece));
+    /// Plays `mv` for `look` to see the position it leads to, then takes it
+    /// back; the key isn't kept up to date in between
+    pub(super) fn peek_move<T>(&mut self, mv: Move, look: impl FnOnce(&Self) -> T) -> T {
+        self.play_move(mv, self.key);
+        let seen = look(self);
+        self.retract_move().unwrap();
+        seen
+    }
+
+    /// Moves the pieces for [`ChessBoard::apply_move`], saving `key` to go
+    /// back to on undo
+    fn play_move(
+        &mut self,
+        Move {
+            from,
+            to,
+            promotion,
+            drop,
+        }: Move,
+        key: u64,
+    ) {
+        let castling_rights = self.castling_rights;
+        let en_passant = self.en_passant.take();
+        let halfmove_clock = self.halfmove_clock;
+        let variant_state = self.variant_state;
+
+        let castling_side = self.castling_side(Move::new(from, to));
+        let (mut piece, mut move_type) = match drop {
+            Some(piece_type) => (self.take_from_pocket(piece_type), Drop(piece_type)),
+            None => (self[from].take().unwrap(), Normal),
+        };
+        // in Chess960 a castling king lands on its own rook
+        let mut taken_piece = match castling_side {
+            Some(_) => None,
+            None => self[to].take(),
+        };
+        let mut land = to;
+
+        match piece.piece_type {
+            KING => {
+                if let Some(side) = castling_side {
+                    let (king_col, rook_col) = self.castled_cols(side);
+                    let rook_from = cvec!(
+                        castling_rights[piece.color as usize][side].unwrap(),
+                        from.row
+                    );
+                    let rook_to = cvec!(rook_col, from.row);
+                    land = cvec!(king_col, from.row);
+
+                    let mut rook = self[rook_from].take();
+                    if let Some(rook) = rook.as_mut() {
+                        rook.total_moves += 1;
+                    }
+                    self[rook_to] = rook;
+                    move_type = Casteling {
+                        king_to: land,
+                        rook_from,
+                        rook_to,
+                    };
+                }
+                self.kings_pos[piece.color as usize] = land;
+                self.castling_rights[piece.color as usize] = [None; 2];
+            }
+            PAWN => {
+                if taken_piece.is_none() && from.col != to.col && Some(to) == en_passant {
+                    let target = cvec!(to.col, from.row);
+                    taken_piece = self[target].take();
+                    move_type = EnPassant(target);
+                } else if to.row == self.promotion_row(piece.color) {
+                    let to_type = promotion.unwrap_or(QUEEN);
+                    piece.piece_type = to_type;
+                    move_type = Promoting(to_type);
+                }
+
+                if (to - from).abs() == cvec!(0, 2) {
+                    self.en_passant = Some(cvec!(from.col, (from.row + to.row) / 2));
+                }
+            }
+            _ => (),
+        }
+
+        // moving a castling rook or taking it loses that castling right
+        for color in [WHITE, BLACK] {
+            let home_row = self.home_row(color);
+            for side in [KINGSIDE, QUEENSIDE] {
+                let Some(col) = castling_rights[color as usize][side] else {
+                    continue;
+                };
+                if [from, to].contains(&cvec!(col, home_row)) {
+                    self.castling_rights[color as usize][side] = None;
+                }
+            }
+        }
+
+        if piece.piece_type == PAWN || taken_piece.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.turn == BLACK {
+            self.fullmove_number += 1;
+        }
+
+        piece.total_moves += 1;
+        self[land] = Some(piece);
+        let exploded = match taken_piece {
+            Some(_) if self.variant.rules().has_explosions() => self.explode(land),
+            _ => Vec::new(),
+        };
 
         self.move_stack.push(ChessMove {
             from,
             to,
             taken_piece,
-            move_type: MoveType::Normal,
+            move_type,
+            exploded,
+            castling_rights,
+            en_passant,
+            halfmove_clock,
+            variant_state,
+            key,
         });
 
         self.swap_turn();
         self.selected_pos = None;
+        self.variant.rules().after_move(self);
+    }
+
+    /// Keeps the undone moves when `mv` is the next of them, and forgets
+    /// them when the game takes another line
+    fn branch_off(&mut self, mv: Move) {
+        if self.redo_stack.last() == Some(&mv) {
+            self.redo_stack.pop();
+        } else {
+            self.redo_stack.clear();
+        }
+    }
 
+    /// Takes the last move back, restoring any taken piece and lost right;
+    /// it can be played again with [`ChessBoard::redo_move`]
+    pub fn undo_move(&mut self) -> Result<(), ChessError> {
+        let mv = self.retract_move()?;
+        self.redo_stack.push(mv);
         Ok(())
     }
 
-    pub fn move_selected(&mut self, to: ChessVec) -> Result<(), &'static str> {
-        let Some(selected_pos) = self.selected_pos else {
-            return Err("[Warning]: no piece is selected to move");
-        };
-        self.move_piece(selected_pos, to)
+    /// Plays the last undone move again
+    pub fn redo_move(&mut self) -> Result<(), ChessError> {
+        let mv = self.redo_stack.pop().ok_or(ChessError::NoMoveToRedo)?;
+        self.apply_move(mv);
+        Ok(())
+    }
+
+    /// Whether there are undone moves to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
     }
 
-    pub fn undo_move(&mut self) -> Result<(), &'static str> {
-        let Some(ChessMove {
+    /// Undoes or redoes moves until `ply` moves of the line are played
+    pub fn goto_ply(&mut self, ply: usize) -> Result<(), ChessError> {
+        if ply > self.line_length() {
+            return Err(ChessError::NoSuchPly(ply));
+        }
+        while self.ply_count() > ply {
+            self.undo_move()?;
+        }
+        while self.ply_count() < ply {
+            self.redo_move()?;
+        }
+        Ok(())
+    }
+
+    /// Undoes every move, keeping them to redo
+    pub fn goto_first(&mut self) {
+        while self.undo_move().is_ok() {}
+    }
+
+    /// Redoes every undone move
+    pub fn goto_last(&mut self) {
+        while self.redo_move().is_ok() {}
+    }
+
+    /// Takes the last move back without keeping it to redo, for the move
+    /// generator and the search to try moves
+    pub(crate) fn retract_move(&mut self) -> Result<Move, ChessError> {
+        let chess_move = self.move_stack.pop().ok_or(ChessError::NoMoveToUndo)?;
+        let mv = chess_move.as_move();
+        let ChessMove {
             from,
             to,
             taken_piece,
             move_type,
-        }) = self.move_stack.pop()
-        else {
-            return Err("[Warning]: move stack is empty");
+            exploded,
+            castling_rights,
+            en_passant,
+            halfmove_clock,
+            variant_state,
+            key,
+        } = chess_move;
+
+        for (pos, piece) in exploded {
+            self[pos] = Some(piece);
+        }
+        let land = match move_type {
+            Casteling { king_to, .. } => king_to,
+            _ => to,
         };
-
-        let mut piece = self[to].take();
-        piece.as_mut().map(|piece| piece.total_moves -= 1);
-        self[from] = piece;
-
-        self.swap_turn();
+        let mut piece = self[land].take();
+        if let Some(piece) = piece.as_mut() {
+            piece.total_moves -= 1;
+            if piece.piece_type == KING {
+                self.kings_pos[piece.color as usize] = from;
+            }
+        }
 
         match move_type {
             Normal => {
                 self[to] = taken_piece;
             }
-            EnPassant(target) => todo!(),
-            Casteling(rook_move) => todo!(),
-            Promoting(to_type) => todo!(),
+            EnPassant(target) => {
+                self[target] = taken_piece;
+            }
+            Casteling {
+                rook_from, rook_to, ..
+            } => {
+                let mut rook = self[rook_to].take();
+                if let Some(rook) = rook.as_mut() {
+                    rook.total_moves -= 1;
+                }
+                self[rook_from] = rook;
+            }
+            Promoting(_) => {
+                if let Some(piece) = piece.as_mut() {
+                    piece.piece_type = PAWN;
+                }
+                self[to] = taken_piece;
+            }
+            // the pocket comes back along with the variant state
+            Drop(_) => piece = None,
+        }
+        self[from] = piece;
+
+        self.swap_turn();
+        if self.turn == BLACK {
+            self.fullmove_number -= 1;
         }
+        self.castling_rights = castling_rights;
+        self.en_passant = en_passant;
+        self.halfmove_clock = halfmove_clock;
+        self.variant_state = variant_state;
+        self.key = key;
 
         self.selected_pos = None;
 
-        Ok(())
+        Ok(mv)
+    }
+
+    /// Number of moves played on this board
+    pub fn ply_count(&self) -> usize {
+        self.move_stack.len()
+    }
+
+    /// Number of moves of the whole line, including the undone ones
+    pub fn line_length(&self) -> usize {
+        self.move_stack.len() + self.redo_stack.len()
+    }
+
+    /// Every piece on the board along with its position
+    pub fn pieces(&self) -> impl Iterator<Item = (ChessVec, ChessPiece)> + '_ {
+        self.grid[..self.height]
+            .iter()
+            .enumerate()
+            .flat_map(|(row, pieces)| {
+                pieces[..self.width]
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(col, piece)| {
+                        piece.map(|piece| (cvec!(col as i32, row as i32), piece))
+                    })
+            })
     }
 }
 
 impl fmt::Display for ChessBoard {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "   a b c d e f g h")?;
-        writeln!(f, "  +---------------+")?;
-
-        for (y, row) in self.grid.iter().enumerate() {
-            write!(f, "{} ", (HEIGHT - y))?;
-            for (x, cell) in row.iter().enumerate() {
-                let is_selected = self
-                    .selected_pos
-                    .map_or(false, |pos| pos == cvec!(x as i32, 7 - y as i32));
+        // ranks past the 9th take two digits
+        let margin = self.height.to_string().len();
+        let files: Vec<String> = (0..self.width)
+            .map(|x| ((b'a' + x as u8) as char).to_string())
+            .collect();
+        let files = format!("{:margin$}  {}", "", files.join(" "));
+        let border = format!("{:margin$} +{}+", "", "-".repeat(2 * self.width - 1));
+
+        writeln!(f, "{files}")?;
+        writeln!(f, "{border}")?;
+        for y in (0..self.height).rev() {
+            write!(f, "{:>margin$} ", y + 1)?;
+            for (x, cell) in self.grid[y][..self.width].iter().enumerate() {
+                let is_selected = self.selected_pos == Some(cvec!(x as i32, y as i32));
                 write!(
                     f,
                     "{}{}",
@@ -242,11 +664,10 @@ impl fmt::Display for ChessBoard {
                     cell.as_ref().map_or(" ".to_string(), ChessPiece::to_string)
                 )?;
             }
-            writeln!(f, "| {}", (HEIGHT - y))?;
+            writeln!(f, "| {}", y + 1)?;
         }
-
-        writeln!(f, "  +---------------+")?;
-        write!(f, "   a b c d e f g h")
+        writeln!(f, "{border}")?;
+        write!(f, "{files}")
     }
 }
 
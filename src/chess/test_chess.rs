@@ -1,62 +1,62 @@
 use super::*;
 
 #[test]
-fn test_chess_board_fen() -> Result<(), &'static str> {
+fn test_chess_board_fen() -> Result<(), ChessError> {
     let mut board = ChessBoard::new();
 
     assert_eq!(
         board.to_fen(),
-        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w"
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
     );
 
     board.move_piece(cvec!("b2"), cvec!("b4"))?;
     assert_eq!(
         board.to_fen(),
-        "rnbqkbnr/pppppppp/8/8/1P6/8/P1PPPPPP/RNBQKBNR b"
+        "rnbqkbnr/pppppppp/8/8/1P6/8/P1PPPPPP/RNBQKBNR b KQkq b3 0 1"
     );
 
     board.move_piece(cvec!("c8"), cvec!("a6"))?;
     assert_eq!(
         board.to_fen(),
-        "rn1qkbnr/pppppppp/b7/8/1P6/8/P1PPPPPP/RNBQKBNR w"
+        "rn1qkbnr/pppppppp/b7/8/1P6/8/P1PPPPPP/RNBQKBNR w KQkq - 1 2"
     );
 
     board.select_piece(cvec!("g1"))?;
     board.move_piece(cvec!("e1"), cvec!("h6"))?;
     assert_eq!(
         board.to_fen(),
-        "rn1qkbnr/pppppppp/b6K/8/1P6/8/P1PPPPPP/RNBQ1BNR b"
+        "rn1qkbnr/pppppppp/b6K/8/1P6/8/P1PPPPPP/RNBQ1BNR b kq - 2 2"
     );
 
     board.select_piece(cvec!("g7"))?;
     board.move_selected(cvec!("g3"))?;
     assert_eq!(
         board.to_fen(),
-        "rn1qkbnr/pppppp1p/b6K/8/1P6/6p1/P1PPPPPP/RNBQ1BNR w"
+        "rn1qkbnr/pppppp1p/b6K/8/1P6/6p1/P1PPPPPP/RNBQ1BNR w kq - 0 3"
     );
 
     board.select_piece(cvec!("d1"))?;
     board.move_selected(cvec!("a5"))?;
     assert_eq!(
         board.to_fen(),
-        "rn1qkbnr/pppppp1p/b6K/Q7/1P6/6p1/P1PPPPPP/RNB2BNR b"
+        "rn1qkbnr/pppppp1p/b6K/Q7/1P6/6p1/P1PPPPPP/RNB2BNR b kq - 1 3"
     );
 
     Ok(())
 }
 
 #[test]
-fn test_chess_board_select_piece() -> Result<(), &'static str> {
+fn test_chess_board_select_piece() -> Result<(), ChessError> {
     let mut board = ChessBoard::new();
 
     assert!(matches!(
         board.select_piece(cvec!("h5")),
-        Err("[Warning]: there is no piece to select")
+        Err(ChessError::NoPieceAt(pos)) if pos == cvec!("h5")
     ));
 
     assert!(matches!(
         board.select_piece(cvec!("a8")),
-        Err("[Warning]: this is not your piece to select")
+        Err(ChessError::NotYourPiece { piece, .. }) if piece.color == BLACK
     ));
 
     board.select_piece(cvec!("a1"))?;
@@ -66,7 +66,7 @@ fn test_chess_board_select_piece() -> Result<(), &'static str> {
     assert_eq!(board.selected_pos, None);
     assert_eq!(
         board.to_fen(),
-        "rnbqkbnr/pppppppp/8/8/R7/8/PPPPPPPP/1NBQKBNR b"
+        "rnbqkbnr/pppppppp/8/8/R7/8/PPPPPPPP/1NBQKBNR b Kkq - 1 1"
     );
 
     board.select_piece(cvec!("g7"))?;
@@ -75,8 +75,711 @@ fn test_chess_board_select_piece() -> Result<(), &'static str> {
     board.move_piece(cvec!("h7"), cvec!("h4"))?;
     assert_eq!(
         board.to_fen(),
-        "rnbqkbnr/ppppppp1/8/8/R6p/8/PPPPPPPP/1NBQKBNR w"
+        "rnbqkbnr/ppppppp1/8/8/R6p/8/PPPPPPPP/1NBQKBNR w Kkq - 0 2"
     );
 
     Ok(())
 }
+
+#[test]
+fn test_chess_board_legal_moves() -> Result<(), ChessError> {
+    let mut board = ChessBoard::new();
+    assert_eq!(board.legal_moves().len(), 20);
+
+    assert!(matches!(
+        board.make_move(Move::new(cvec!("e2"), cvec!("e5"))),
+        Err(ChessError::IllegalMove(_))
+    ));
+
+    board.make_move(Move::new(cvec!("f2"), cvec!("f3")))?;
+    board.make_move(Move::new(cvec!("e7"), cvec!("e5")))?;
+    board.make_move(Move::new(cvec!("g2"), cvec!("g4")))?;
+    board.make_move(Move::new(cvec!("d8"), cvec!("h4")))?;
+    assert_eq!(board.status(), GameStatus::Checkmate { winner: BLACK });
+    assert_eq!(board.san_history(), ["f3", "e5", "g4", "Qh4#"]);
+    assert!(board.to_pgn(&[]).ends_with("1. f3 e5 2. g4 Qh4# 0-1\n"));
+
+    while board.undo_move().is_ok() {}
+    assert_eq!(board.to_fen(), ChessBoard::new().to_fen());
+
+    Ok(())
+}
+
+#[test]
+fn test_chess_board_special_moves() -> Result<(), ChessError> {
+    let fen = "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1";
+    let mut board = ChessBoard::from_fen(fen)?;
+    assert_eq!(board.to_fen(), fen);
+
+    let en_passant = Move::new(cvec!("e5"), cvec!("d6"));
+    assert_eq!(board.to_san(en_passant), "exd6");
+    board.make_move(en_passant)?;
+    assert_eq!(board[cvec!("d5")], None);
+
+    board.make_move(Move::new(cvec!("e8"), cvec!("g8")))?;
+    assert_eq!(board.to_fen(), "r4rk1/1P6/3P4/8/8/8/8/R3K2R w KQ - 1 2");
+
+    let promotion = Move::with_promotion(cvec!("b7"), cvec!("b8"), KNIGHT);
+    assert_eq!(board.to_san(promotion), "b8=N");
+    board.make_move(promotion)?;
+    board.make_move(Move::new(cvec!("a8"), cvec!("a1")))?;
+    assert_eq!(board.to_fen(), "1N3rk1/8/3P4/8/8/8/8/r3K2R w K - 0 3");
+
+    for _ in 0..4 {
+        board.undo_move()?;
+    }
+    assert_eq!(board.to_fen(), fen);
+
+    Ok(())
+}
+
+#[test]
+fn test_chess_board_hints() -> Result<(), ChessError> {
+    let mut board = ChessBoard::new();
+    assert_eq!(
+        board.legal_destinations(cvec!("g1")),
+        [cvec!("h3"), cvec!("f3")]
+    );
+    assert!(board.legal_destinations(cvec!("g8")).is_empty());
+
+    board.make_move(Move::new(cvec!("e2"), cvec!("e4")))?;
+    board.make_move(Move::new(cvec!("f7"), cvec!("f6")))?;
+    board.make_move(Move::new(cvec!("d1"), cvec!("h5")))?;
+    assert_eq!(board.last_move(), Some(Move::new(cvec!("d1"), cvec!("h5"))));
+    assert_eq!(board.check_squares(), [cvec!("e8"), cvec!("h5")]);
+    assert_eq!(board.legal_destinations(cvec!("g7")), [cvec!("g6")]);
+
+    Ok(())
+}
+
+#[test]
+fn test_chess_board_perft() -> Result<(), ChessError> {
+    assert_eq!(ChessBoard::new().perft(3), 8902);
+
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    assert_eq!(ChessBoard::from_fen(kiwipete)?.perft(2), 2039);
+
+    Ok(())
+}
+
+#[test]
+fn test_chess_board_notation_parsing() -> Result<(), ChessError> {
+    let board = ChessBoard::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1")?;
+    assert_eq!(
+        board.parse_uci("b7a8n")?,
+        Move::with_promotion(cvec!("b7"), cvec!("a8"), KNIGHT)
+    );
+    assert_eq!(
+        board.parse_san("exd6")?,
+        Move::new(cvec!("e5"), cvec!("d6"))
+    );
+    assert_eq!(board.parse_san("O-O")?, Move::new(cvec!("e1"), cvec!("g1")));
+    assert_eq!(board.parse_san("Rd1")?, Move::new(cvec!("a1"), cvec!("d1")));
+    assert_eq!(
+        board.parse_san("e4"),
+        Err(ChessError::NoMatchingMove("e4".to_string()))
+    );
+    assert_eq!(
+        board.parse_uci("e5e6e7"),
+        Err(ChessError::InvalidMoveNotation("e5e6e7".to_string()))
+    );
+
+    let board = ChessBoard::from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1")?;
+    assert_eq!(
+        board.parse_san("Rd1"),
+        Err(ChessError::AmbiguousMove("Rd1".to_string()))
+    );
+    assert_eq!(
+        board.parse_san("Rhd1")?,
+        Move::new(cvec!("h1"), cvec!("d1"))
+    );
+
+    let pgn = "[Event \"Casual \\\"blitz\\\"\"]\n\n1. f3 {weak} e5 (1... d5) 2.g4 $4 Qh4# 0-1\n";
+    let (board, tags) = ChessBoard::from_pgn(pgn)?;
+    assert_eq!(board.san_history(), ["f3", "e5", "g4", "Qh4#"]);
+    assert_eq!(
+        tags,
+        [("Event".to_string(), "Casual \"blitz\"".to_string())]
+    );
+
+    let tags: Vec<(&str, String)> = tags.iter().map(|(n, v)| (n.as_str(), v.clone())).collect();
+    let (replayed, _) = ChessBoard::from_pgn(&board.to_pgn(&tags))?;
+    assert_eq!(replayed.to_fen(), board.to_fen());
+
+    Ok(())
+}
+
+#[test]
+fn test_chess_error_kinds() {
+    assert_eq!(
+        ChessBoard::from_fen("8/8/8/8/8/8/8/8 w - - 0 1").err(),
+        Some(ChessError::InvalidFen {
+            reason: "each side needs exactly one king"
+        })
+    );
+    let long_run = format!("4k3/{}/8/8/8/8/8/4K3 w - - 0 1", "9".repeat(40));
+    assert_eq!(
+        ChessBoard::from_fen(&long_run).err(),
+        Some(ChessError::InvalidFen {
+            reason: "rows should have the same number of columns, from 1 to 12"
+        })
+    );
+    assert_eq!(
+        ChessBoard::from_pgn("1. e4 {unclosed").err(),
+        Some(ChessError::InvalidPgn {
+            reason: "a comment isn't closed"
+        })
+    );
+    assert_eq!(ChessBoard::new().undo_move(), Err(ChessError::NoMoveToUndo));
+
+    let error = ChessError::IllegalMove(Move::new(cvec!("e2"), cvec!("e5")));
+    assert_eq!(error.code(), "illegal_move");
+    assert_eq!(error.to_string(), "e2e5 is not a legal move");
+}
+
+#[test]
+fn test_chess_board_history() -> Result<(), ChessError> {
+    let mut board = ChessBoard::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1")?;
+    board.make_move("e5d6".parse()?)?;
+    board.make_move(board.parse_san("Kd7")?)?;
+
+    let history = board.history();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].san, "exd6");
+    assert_eq!(history[0].piece.piece_type, PAWN);
+    assert_eq!(
+        history[0]
+            .captured
+            .map(|piece| (piece.piece_type, piece.color)),
+        Some((PAWN, BLACK))
+    );
+    assert_eq!(history[0].fen, "4k3/8/3P4/8/8/8/8/4K3 b - - 0 1");
+    assert_eq!(history[1].captured, None);
+    assert_eq!(history[1].fen, board.to_fen());
+
+    let json = serde_json::to_value(&history[0]).unwrap();
+    assert_eq!(json["uci"], "e5d6");
+    assert_eq!(json["piece"]["color"], "white");
+    let played: PlayedMove = serde_json::from_value(json).unwrap();
+    assert_eq!(played, history[0]);
+
+    let promotion: Move = serde_json::from_str("\"b7b8n\"").unwrap();
+    assert_eq!(
+        promotion,
+        Move::with_promotion(cvec!("b7"), cvec!("b8"), KNIGHT)
+    );
+    assert!("b7b8p".parse::<Move>().is_err());
+    assert!("e11e4".parse::<Move>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_chess_board_navigation() -> Result<(), ChessError> {
+    let mut board = ChessBoard::new();
+    for san in ["e4", "e5", "Nf3", "Nc6"] {
+        board.make_move(board.parse_san(san)?)?;
+    }
+    let end_fen = board.to_fen();
+
+    board.undo_move()?;
+    board.undo_move()?;
+    assert_eq!(board.ply_count(), 2);
+    assert_eq!(board.line_length(), 4);
+    board.redo_move()?;
+    assert_eq!(board.san_history(), ["e4", "e5", "Nf3"]);
+
+    board.goto_first();
+    assert_eq!(board.to_fen(), ChessBoard::new().to_fen());
+    assert_eq!(board.goto_ply(5), Err(ChessError::NoSuchPly(5)));
+    board.goto_last();
+    assert_eq!(board.to_fen(), end_fen);
+
+    // replaying the next move of the line keeps the rest of it
+    board.goto_ply(1)?;
+    board.make_move(board.parse_san("e5")?)?;
+    assert_eq!(board.line_length(), 4);
+
+    // any other move starts a new line
+    board.make_move(board.parse_san("Bc4")?)?;
+    assert!(!board.can_redo());
+    assert_eq!(board.redo_move(), Err(ChessError::NoMoveToRedo));
+    assert_eq!(board.line_length(), 3);
+    Ok(())
+}
+
+#[test]
+fn test_game_tree() -> Result<(), ChessError> {
+    let mut tree = GameTree::default();
+    for san in ["e4", "e5", "Nf3"] {
+        tree.play(tree.board().parse_san(san)?)?;
+    }
+    tree.add_nag(1);
+    tree.back()?;
+    tree.play(tree.board().parse_san("Bc4")?)?;
+    tree.set_comment(Some("the Bishop's Opening".to_string()));
+    tree.play(tree.board().parse_san("Nf6")?)?;
+    assert_eq!(tree.path(), [0, 0, 1, 0]);
+    assert_eq!(tree.promote_variation(), Ok(()));
+    assert_eq!(tree.path(), [0, 0, 0, 0]);
+    assert_eq!(tree.promote_variation(), Err(ChessError::NotInVariation));
+
+    let pgn = tree.to_pgn(&[]);
+    assert!(pgn.contains("1. e4 e5 2. Bc4 {the Bishop's Opening} (2. Nf3 $1) 2... Nf6 *"));
+    let (read, _) = GameTree::from_pgn(&pgn)?;
+    assert_eq!(read.root(), tree.root());
+    assert_eq!(read.to_pgn(&[]), pgn);
+
+    // suffix annotations, nested variations and a variation of the first move
+    let (mut tree, _) = GameTree::from_pgn(
+        "1. e4!? (1. d4 d5 (1... Nf6 2. c4) 2. c4) 1... c5 {Sicilian} 2. Nf3 *",
+    )?;
+    assert_eq!(tree.root().children()[0].nags(), [5]);
+    assert_eq!(tree.mainline().len(), 3);
+    tree.goto(&[1, 1, 0])?;
+    assert_eq!(tree.current().san(), "c4");
+    tree.delete_variation()?;
+    assert_eq!(tree.path(), [1]);
+    assert_eq!(tree.current().children().len(), 1);
+    assert!(tree
+        .to_pgn(&[])
+        .contains("1. e4 $5 (1. d4 d5 2. c4) 1... c5 {Sicilian} 2. Nf3 *"));
+
+    assert_eq!(
+        GameTree::from_pgn("1. e4 (1. d4").err(),
+        Some(ChessError::InvalidPgn {
+            reason: "a variation isn't closed"
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_chess960() -> Result<(), ChessError> {
+    assert_eq!(
+        ChessBoard::chess960(518)?.to_fen(),
+        ChessBoard::new().to_fen()
+    );
+    assert_eq!(
+        ChessBoard::chess960(0)?.to_fen(),
+        "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+    );
+    assert_eq!(
+        ChessBoard::chess960(960).err(),
+        Some(ChessError::InvalidChess960Index(960))
+    );
+
+    let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+    let mut board = ChessBoard::from_fen(fen)?;
+    assert!(board.is_chess960());
+    assert_eq!(board.to_shredder_fen(), fen);
+    assert_eq!(board.perft(3), 12189);
+
+    // the king stays on g1 and the rook goes from h1 to f1, once f1 is free
+    let mut board = ChessBoard::from_fen("4k3/8/8/8/8/8/8/5RKR w H - 0 1")?;
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/5RKR w K - 0 1");
+    board.make_move(board.parse_san("Rf2")?)?;
+    board.make_move(board.parse_san("Kd7")?)?;
+    let castling = board.parse_san("O-O")?;
+    assert_eq!(castling.to_string(), "g1h1");
+    board.make_move(castling)?;
+    assert_eq!(board.to_fen(), "8/3k4/8/8/8/8/5R2/5RK1 b - - 3 2");
+    assert_eq!(board.san_history()[2], "O-O");
+
+    // a king landing on its castling square castles as well
+    let mut board = ChessBoard::chess960(0)?;
+    for san in ["Nc3", "Nc6", "Nf3", "Nf6", "d4", "d5", "Qd2", "Qd7"] {
+        board.make_move(board.parse_san(san)?)?;
+    }
+    board.make_move(Move::new(cvec!("g1"), cvec!("c1")))?;
+    board.make_move(board.parse_san("O-O-O")?)?;
+    assert_eq!(board.moves()[8].to_string(), "g1f1");
+    assert_eq!(
+        board.to_fen(),
+        "bbkr3r/pppqpppp/2n2n2/3p4/3P4/2N2N2/PPPQPPPP/BBKR3R w - - 4 6"
+    );
+    board.set_chess960(false);
+    assert_eq!(board.moves()[9].to_string(), "g8c8");
+    Ok(())
+}
+
+#[test]
+fn test_variants() -> Result<(), ChessError> {
+    assert_eq!("koth".parse::<Variant>()?, Variant::KingOfTheHill);
+    assert_eq!(
+        "crazy".parse::<Variant>().err(),
+        Some(ChessError::UnknownVariant("crazy".to_string()))
+    );
+    assert!("losers".parse::<Variant>().is_err());
+    assert!(Variant::Atomic.has_standard_start());
+    assert!(!Variant::Horde.has_standard_start());
+
+    // antichess: captures are forced, and losing every piece wins
+    let mut board = ChessBoard::new();
+    board.set_variant(Variant::Antichess);
+    assert_eq!(
+        board.to_fen(),
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+    );
+    assert_eq!(board.perft(3), 8067);
+    for san in ["e4", "d5"] {
+        board.make_move(board.parse_san(san)?)?;
+    }
+    assert_eq!(
+        board.legal_moves(),
+        vec![Move::new(cvec!("e4"), cvec!("d5"))]
+    );
+    let board = ChessBoard::from_variant_fen("8/P7/8/8/8/8/8/7k w - - 0 1", Variant::Antichess)?;
+    assert!(board.legal_moves().contains(&"a7a8k".parse()?));
+    let board = ChessBoard::from_variant_fen("8/8/8/8/8/8/8/7k w - - 0 1", Variant::Antichess)?;
+    assert_eq!(board.status().winner(), Some(WHITE));
+
+    // king of the hill
+    let mut board =
+        ChessBoard::from_variant_fen("8/8/8/8/8/4K3/8/7k w - - 0 1", Variant::KingOfTheHill)?;
+    board.make_move(board.parse_san("Ke4")?)?;
+    assert_eq!(board.status().result(), "1-0");
+
+    // three-check counts checks, writes them in FEN, and undoes them
+    let mut board = ChessBoard::new();
+    board.set_variant(Variant::ThreeCheck);
+    for san in ["e4", "e5", "Bc4", "Nc6", "Bxf7+", "Kxf7"] {
+        board.make_move(board.parse_san(san)?)?;
+    }
+    assert_eq!(board.checks_given(WHITE), 1);
+    let fen = "r1bq1bnr/pppp1kpp/2n5/4p3/4P3/8/PPPP1PPP/RNBQK1NR w KQ - 2+3 0 4";
+    assert_eq!(board.to_fen(), fen);
+    assert_eq!(
+        ChessBoard::from_variant_fen(fen, Variant::ThreeCheck)?.checks_given(WHITE),
+        1
+    );
+    for san in ["Qh5+", "g6", "Qxg6+"] {
+        board.make_move(board.parse_san(san)?)?;
+    }
+    assert_eq!(board.status().winner(), Some(WHITE));
+    board.undo_move()?;
+    assert_eq!(board.checks_given(WHITE), 2);
+    assert!(!board.status().is_over());
+
+    let pgn = board.to_pgn(&[]);
+    assert!(pgn.contains("[Variant \"Three-check\"]"));
+    assert!(!pgn.contains("[FEN"));
+    let (read, _) = ChessBoard::from_pgn(&pgn)?;
+    assert_eq!(read.variant(), Variant::ThreeCheck);
+    assert_eq!(read.to_fen(), board.to_fen());
+    Ok(())
+}
+
+#[test]
+fn test_crazyhouse() -> Result<(), ChessError> {
+    let mut board = ChessBoard::new();
+    board.set_variant(Variant::Crazyhouse);
+    assert_eq!(board.perft(3), 8902);
+    for san in ["e4", "d5", "exd5", "Qxd5"] {
+        board.make_move(board.parse_san(san)?)?;
+    }
+    assert_eq!(board.pocket(WHITE), vec![PAWN]);
+    assert_eq!(
+        board.to_fen(),
+        "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3"
+    );
+    let legal = board.legal_moves();
+    assert!(legal.contains(&Move::new_drop(PAWN, cvec!("e4"))));
+    assert!(!legal.contains(&Move::new_drop(PAWN, cvec!("e1"))));
+    assert!(!legal.contains(&Move::new_drop(KNIGHT, cvec!("e4"))));
+
+    let drop = board.parse_san("@e4")?;
+    assert_eq!(drop.to_string(), "P@e4");
+    assert_eq!(board.to_san(drop), "P@e4");
+    board.make_move(drop)?;
+    assert!(board.pocket(WHITE).is_empty());
+    assert_eq!(board.parse_uci("P@e6")?, Move::new_drop(PAWN, cvec!("e6")));
+    board.undo_move()?;
+    assert_eq!(board.pocket(WHITE), vec![PAWN]);
+
+    // a promoted piece goes back to the pocket as a pawn
+    let fen = "3qk3/8/8/8/8/8/8/3Q~K3[] b - - 0 1";
+    let mut board = ChessBoard::from_variant_fen(fen, Variant::Crazyhouse)?;
+    assert!(board.is_promoted(cvec!("d1")));
+    assert_eq!(board.to_fen(), fen);
+    board.make_move(board.parse_san("Qxd1+")?)?;
+    assert_eq!(board.pocket(BLACK), vec![PAWN]);
+    assert!(!board.is_promoted(cvec!("d1")));
+
+    let pgn = board.to_pgn(&[]);
+    assert!(pgn.contains("[Variant \"Crazyhouse\"]"));
+    let (read, _) = ChessBoard::from_pgn(&pgn)?;
+    assert_eq!(read.to_fen(), board.to_fen());
+    Ok(())
+}
+
+#[test]
+fn test_atomic() -> Result<(), ChessError> {
+    let mut board = ChessBoard::new();
+    board.set_variant(Variant::Atomic);
+    assert_eq!(board.perft(3), 8902);
+
+    // taking the queen blows up the rook and the king next to it, not the pawn
+    let fen = "4k3/3pq3/8/8/8/8/8/4R1K1 w - - 0 1";
+    let mut board = ChessBoard::from_variant_fen(fen, Variant::Atomic)?;
+    board.make_move(board.parse_san("Rxe7")?)?;
+    assert_eq!(board.to_fen(), "8/3p4/8/8/8/8/8/6K1 b - - 0 1");
+    assert_eq!(board.status().winner(), Some(WHITE));
+    board.undo_move()?;
+    assert_eq!(board.to_fen(), fen);
+
+    // a king can't take, and touching kings can't give check
+    let board = ChessBoard::from_variant_fen("4k3/8/8/8/8/8/4q3/4K3 w - - 0 1", Variant::Atomic)?;
+    assert_eq!(board.status(), GameStatus::Checkmate { winner: BLACK });
+    let board = ChessBoard::from_variant_fen("8/8/8/3kK3/8/8/8/3R4 b - - 0 1", Variant::Atomic)?;
+    assert!(!board.in_check(BLACK));
+    Ok(())
+}
+
+#[test]
+fn test_board_dimensions() -> Result<(), ChessError> {
+    let mut board = ChessBoard::new_variant(Variant::Capablanca);
+    assert_eq!((board.width(), board.height()), (10, 8));
+    assert_eq!(board.perft(3), 25228);
+    let fen = "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1";
+    assert_eq!(board.to_fen(), fen);
+
+    board.make_move(board.parse_san("Ad3")?)?;
+    assert_eq!(board[cvec!("d3")].unwrap().piece_type, ARCHBISHOP);
+    board.make_move(board.parse_san("j6")?)?;
+    assert_eq!(board.san_history(), ["Ad3", "j6"]);
+    assert_eq!(board.last_move(), Some("j7j6".parse()?));
+
+    // the king castles two squares from the corner, next to its rook
+    let fen = "r4k3r/10/10/10/10/10/10/R4K3R w KQkq - 0 1";
+    let board = ChessBoard::from_variant_fen(fen, Variant::Capablanca)?;
+    assert_eq!(board.parse_san("O-O")?, Move::new(cvec!("f1"), cvec!("i1")));
+    assert_eq!(
+        board.parse_san("O-O-O")?,
+        Move::new(cvec!("f1"), cvec!("c1"))
+    );
+    assert!(!board.is_chess960());
+
+    // a chancellor checks like a knight, an amazon mates alone with the king
+    let board = ChessBoard::from_fen("4k3/8/3C4/8/8/8/8/4K3 b - - 0 1")?;
+    assert!(board.in_check(BLACK));
+    let board = ChessBoard::from_fen("k7/2M5/1K6/8/8/8/8/8 b - - 0 1")?;
+    assert_eq!(board.status(), GameStatus::Checkmate { winner: WHITE });
+
+    let mut board = ChessBoard::empty(10, 10)?;
+    assert!(board.contains(cvec!("j10")) && !board.contains(cvec!("k1")));
+    assert_eq!(cvec!("a10").to_string(), "a10");
+    board[cvec!("a10")] = Some(ChessPiece::new(KING, BLACK));
+    board[cvec!("j1")] = Some(ChessPiece::new(KING, WHITE));
+    assert_eq!(
+        board.to_fen().split(' ').next(),
+        Some("k9/10/10/10/10/10/10/10/10/9K")
+    );
+    assert!(matches!(
+        ChessBoard::empty(13, 8),
+        Err(ChessError::InvalidBoardSize {
+            width: 13,
+            height: 8
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_fairy_movements() -> Result<(), ChessError> {
+    const CAMEL: [ChessVec; 8] = [
+        cvec!(1, 3),
+        cvec!(3, 1),
+        cvec!(3, -1),
+        cvec!(1, -3),
+        cvec!(-1, -3),
+        cvec!(-3, -1),
+        cvec!(-3, 1),
+        cvec!(-1, 3),
+    ];
+    let camel = Movement {
+        leaps: &CAMEL,
+        slides: &[],
+    };
+
+    // the knight on b7 turns into a camel, which leaps onto e8
+    let mut board = ChessBoard::from_fen("4k3/1N6/8/8/8/8/8/4K3 b - - 0 1")?;
+    assert!(!board.in_check(BLACK));
+    board.set_movement(KNIGHT, camel)?;
+    assert_eq!(board.movement(KNIGHT), camel);
+    assert!(board.in_check(BLACK));
+    assert!(board.is_attacked(cvec!("e6"), WHITE));
+    assert!(!board.is_attacked(cvec!("d8"), WHITE));
+    assert_eq!(board.check_squares(), [cvec!("e8"), cvec!("b7")]);
+    // the king can't step onto another square the camel reaches
+    assert_eq!(board.legal_moves().len(), 5);
+    board.make_move(board.parse_san("Kd7")?)?;
+    let mut destinations = board.legal_destinations(cvec!("b7"));
+    destinations.sort_by_key(|pos| (pos.col, pos.row));
+    assert_eq!(
+        destinations,
+        [cvec!("a4"), cvec!("c4"), cvec!("e6"), cvec!("e8")]
+    );
+
+    const ONE_WAY: [ChessVec; 1] = [cvec!(1, 3)];
+    let one_way = Movement {
+        leaps: &ONE_WAY,
+        slides: &[],
+    };
+    assert!(matches!(
+        board.set_movement(QUEEN, one_way),
+        Err(ChessError::InvalidMovement { .. })
+    ));
+    assert!(matches!(
+        board.set_movement(PAWN, camel),
+        Err(ChessError::InvalidMovement { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_bughouse() -> Result<(), ChessError> {
+    // taken pieces stay out of the pockets of the board they were taken on
+    let fen = "4k3/8/8/3p4/4P3/8/8/4K3[] w - - 0 1";
+    let mut board = ChessBoard::from_variant_fen(fen, Variant::Bughouse)?;
+    board.make_move(board.parse_san("exd5")?)?;
+    assert_eq!(board.last_capture(), Some(ChessPiece::new(PAWN, BLACK)));
+    assert!(board.pocket(WHITE).is_empty());
+
+    // the partner board hands them over, promoted pieces as pawns
+    board.add_to_pocket(BLACK, KNIGHT);
+    board.make_move(board.parse_san("N@f3+")?)?;
+    assert_eq!(board.to_fen(), "4k3/8/8/3P4/8/5n2/8/4K3[] w - - 1 2");
+
+    let fen = "q~3k3/8/8/8/8/8/8/R3K3[] w - - 0 1";
+    let mut board = ChessBoard::from_variant_fen(fen, Variant::Bughouse)?;
+    board.make_move(board.parse_san("Rxa8+")?)?;
+    assert_eq!(board.last_capture(), Some(ChessPiece::new(PAWN, BLACK)));
+    assert_eq!(board.status(), GameStatus::Ongoing);
+    Ok(())
+}
+
+#[test]
+fn test_horde_and_racing_kings() -> Result<(), ChessError> {
+    let mut board = ChessBoard::new_variant(Variant::Horde);
+    assert_eq!(board.perft(3), 1274);
+
+    // pawns of the first rank double-step, and taking the last one wins
+    let fen = "4k3/8/8/8/8/8/8/P3q3 w - - 0 1";
+    board = ChessBoard::from_variant_fen(fen, Variant::Horde)?;
+    assert_eq!(
+        board.legal_destinations(cvec!("a1")),
+        [cvec!("a2"), cvec!("a3")]
+    );
+    board.make_move(board.parse_san("a3")?)?;
+    board.make_move(board.parse_san("Qa5")?)?;
+    board.make_move(board.parse_san("a4")?)?;
+    board.make_move(board.parse_san("Qxa4#")?)?;
+    assert_eq!(board.status().winner(), Some(BLACK));
+    assert!(ChessBoard::from_variant_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1", Variant::Horde).is_err());
+
+    let mut board = ChessBoard::new_variant(Variant::RacingKings);
+    assert_eq!(board.perft(3), 11264);
+    assert!(board.parse_san("Nd3").is_ok());
+    // the knight would check the black king
+    assert!(board.parse_san("Nc3").is_err());
+
+    // Black may still draw once the white king arrived, if its king follows
+    let fen = "7K/1k6/8/8/8/8/8/8 b - - 0 1";
+    let mut board = ChessBoard::from_variant_fen(fen, Variant::RacingKings)?;
+    assert_eq!(board.status(), GameStatus::Ongoing);
+    board.make_move(board.parse_san("Kb8")?)?;
+    assert_eq!(board.status().result(), "1/2-1/2");
+    let board = ChessBoard::from_variant_fen("7K/8/1k6/8/8/8/8/8 b - - 0 1", Variant::RacingKings)?;
+    assert_eq!(board.status().winner(), Some(WHITE));
+    Ok(())
+}
+
+#[test]
+fn test_odds() -> Result<(), ChessError> {
+    let fens = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR b KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1",
+    ];
+    for (odds, fen) in ODDS.into_iter().zip(fens) {
+        assert_eq!(ChessBoard::with_odds(odds, WHITE).to_fen(), fen);
+        assert_eq!(odds.to_string().parse::<Odds>()?, odds);
+    }
+    let board = ChessBoard::with_odds(Odds::Rook, BLACK);
+    assert_eq!(
+        board.to_fen(),
+        "1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1"
+    );
+    assert!("bishop".parse::<Odds>().is_err());
+
+    // the PGN starts from the odds position
+    let mut board = ChessBoard::with_odds(Odds::PawnAndMove, WHITE);
+    board.make_move(board.parse_san("e5")?)?;
+    let pgn = board.to_pgn(&[]);
+    assert!(pgn.contains("[SetUp \"1\"]"));
+    assert!(pgn.contains(&format!("[FEN \"{}\"]", fens[0])));
+    assert_eq!(ChessBoard::from_pgn(&pgn)?.0.to_fen(), board.to_fen());
+    Ok(())
+}
+
+#[test]
+fn test_position_builder() -> Result<(), ChessError> {
+    let mut builder = PositionBuilder::new();
+    builder
+        .put(cvec!("e1"), ChessPiece::new(KING, WHITE))?
+        .put(cvec!("h1"), ChessPiece::new(ROOK, WHITE))?
+        .castling("K")?;
+    assert_eq!(
+        builder.validate(),
+        Err(ChessError::InvalidPosition {
+            reason: "each side needs exactly one king"
+        })
+    );
+    builder.edit("ke8")?;
+    let board = builder.build()?;
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+    assert!(board.parse_san("O-O").is_ok());
+    assert!(builder.remove(cvec!("i1")).is_err());
+
+    // each rule in turn, the position playable again after the fix
+    let broken = [
+        ("Pa8", "remove a8"),
+        ("Qe2", "remove e2"),
+        ("castling KQ", "castling K"),
+        ("ep e3", "ep -"),
+        ("x h1", "Rh1"),
+    ];
+    for (breaking, fixing) in broken {
+        builder.edit(breaking)?;
+        assert!(
+            matches!(builder.build(), Err(ChessError::InvalidPosition { .. })),
+            "{breaking}"
+        );
+        builder.edit(fixing)?;
+        builder.build()?;
+    }
+
+    // the side to move may be in check, and take en passant
+    builder.edit("turn b")?;
+    builder.edit("Qe2")?;
+    builder.edit("x e2")?;
+    builder.edit("turn w")?;
+    builder.edit("pd5")?;
+    builder.edit("Pe5")?;
+    builder.edit("ep d6")?;
+    let board = builder.build()?;
+    assert!(board.parse_san("exd6").is_ok());
+    assert_eq!(
+        builder.edit("bogus"),
+        Err(ChessError::InvalidCommand("bogus".to_string()))
+    );
+
+    // Horde keeps white pawns on the first rank
+    let mut builder = PositionBuilder::from_board(&ChessBoard::new_variant(Variant::Horde));
+    builder.build()?;
+    builder.edit("variant standard")?;
+    assert!(builder.build().is_err());
+    Ok(())
+}
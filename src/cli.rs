@@ -1,15 +1,62 @@
 use std::{
-    io::{stdin, stdout, Write},
+    fs,
+    io::{read_to_string, stdin, stdout, Write},
+    path::Path,
     process::exit,
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant},
 };
 
-use crate::chess::{ChessBoard, ChessVec};
+use chess_rs::chess::{
+    ChessBoard, ChessColor, ChessPiece, ChessVec, Move, PgnTags, PositionBuilder, Variant,
+};
+use chess_rs::engine::{
+    evaluate_terms, search_with_table, EvalWeights, Evaluator, Network, SearchLimits, SearchResult,
+    TranspositionTable, Tuner, TuningPosition, EVAL_TERMS, TOTAL_PHASE,
+};
 
 enum ChessInput {
     Move(ChessVec, ChessVec),
     Select(ChessVec),
     Deselect,
     Undo,
+    Hint,
+    /// Drops a piece of the pocket, as in `N@f3`
+    Drop(Move),
+    /// Walks the line of moves without changing it
+    Navigate(Navigation),
+}
+
+enum Navigation {
+    Back,
+    Forward,
+    Goto(usize),
+    First,
+    Last,
+}
+
+/// Reads the navigation commands, which must be checked before squares as
+/// `goto 5` or `forward` would be read as one
+fn parse_navigation(input: &str) -> Option<Result<Navigation, String>> {
+    let mut words = input.split_whitespace();
+    let navigation = match words.next()?.to_lowercase().as_str() {
+        "back" => Navigation::Back,
+        "forward" | "redo" => Navigation::Forward,
+        "first" => Navigation::First,
+        "last" => Navigation::Last,
+        "goto" => match words.next().map(str::parse) {
+            Some(Ok(ply)) => Navigation::Goto(ply),
+            _ => return Some(Err("goto needs a ply number".to_string())),
+        },
+        _ => return None,
+    };
+    Some(Ok(navigation))
+}
+
+/// The built-in engine playing one side of a terminal game
+pub struct EngineOpponent {
+    pub color: ChessColor,
+    pub limits: SearchLimits,
 }
 
 fn get_input() -> ChessInput {
@@ -21,10 +68,31 @@ fn get_input() -> ChessInput {
             .read_line(&mut buf)
             .expect("[Error]: failed to read from stdin");
 
+        match parse_navigation(&buf) {
+            Some(Ok(navigation)) => return ChessInput::Navigate(navigation),
+            Some(Err(e)) => {
+                eprintln!("[Error]: {e}");
+                continue;
+            }
+            None => (),
+        }
+
+        if buf.contains('@') {
+            match buf.trim().parse() {
+                Ok(mv) => return ChessInput::Drop(mv),
+                Err(e) => {
+                    eprintln!("[Error]: {e}");
+                    continue;
+                }
+            }
+        }
+
         let from = match ChessVec::try_from(&mut buf) {
             Ok(from) => from,
             Err(_) => {
-                if buf.contains(['u', 'U']) {
+                if buf.to_lowercase().contains("hint") {
+                    return ChessInput::Hint;
+                } else if buf.contains(['u', 'U']) {
                     return ChessInput::Undo;
                 } else if buf.contains(['d', 'D']) {
                     return ChessInput::Deselect;
@@ -44,25 +112,478 @@ fn get_input() -> ChessInput {
     }
 }
 
-pub fn start_cli_game() {
-    let mut board = ChessBoard::new();
+/// Formats a score of the side to move from the point of view of white,
+/// in pawns or as a number of moves until mate
+fn format_score(result: &SearchResult, turn: ChessColor) -> String {
+    let sign = match turn {
+        ChessColor::WHITE => 1,
+        ChessColor::BLACK => -1,
+    };
+    match result.mate_in() {
+        Some(moves) => format!("#{}", moves * sign),
+        None => format!("{:+.2}", (result.score * sign) as f64 / 100.0),
+    }
+}
+
+/// The pieces `color` holds in Crazyhouse as FEN letters, e.g. `QN`
+fn format_pocket(board: &ChessBoard, color: ChessColor) -> String {
+    let pocket: String = board
+        .pocket(color)
+        .into_iter()
+        .map(|piece_type| ChessPiece::new(piece_type, color).to_string())
+        .collect();
+    if pocket.is_empty() {
+        "-".to_string()
+    } else {
+        pocket
+    }
+}
+
+/// Searches the position and describes the best move found,
+/// e.g. `Nf3 (+0.35, depth 4, 1234 nodes)`
+fn describe_search(
+    board: &ChessBoard,
+    limits: SearchLimits,
+    evaluator: &Evaluator,
+) -> Option<(Move, String)> {
+    let result = search_with_table(
+        board,
+        limits,
+        &AtomicBool::new(false),
+        &TranspositionTable::default(),
+        evaluator,
+        1,
+        |_| (),
+    );
+    let best_move = result.best_move?;
+    let description = format!(
+        "{} ({}, depth {}, {} nodes)",
+        board.to_san(best_move),
+        format_score(&result, board.get_turn()),
+        result.depth,
+        result.nodes
+    );
+    Some((best_move, description))
+}
+
+/// Plays a game in the terminal, the engine and the hints scoring positions
+/// with `evaluator`
+pub fn start_cli_game(
+    mut board: ChessBoard,
+    opponent: Option<EngineOpponent>,
+    evaluator: &Evaluator,
+) {
+    let hint_limits = opponent
+        .as_ref()
+        .map_or(SearchLimits::depth(4), |opponent| opponent.limits);
 
     loop {
+        if let Some(opponent) = &opponent {
+            // while walking back through the game the engine waits for the line to end
+            if board.get_turn() == opponent.color && !board.can_redo() && !board.status().is_over()
+            {
+                if let Some((best_move, description)) =
+                    describe_search(&board, opponent.limits, evaluator)
+                {
+                    board.make_move(best_move).unwrap();
+                    println!("[Engine]: {description}");
+                }
+            }
+        }
+
         println!("{board}");
+        if board.variant().rules().has_pockets() {
+            println!(
+                "[Pockets]: white {}, black {}",
+                format_pocket(&board, ChessColor::WHITE),
+                format_pocket(&board, ChessColor::BLACK)
+            );
+        }
+        if board.can_redo() {
+            println!(
+                "[Info]: at ply {} of {}",
+                board.ply_count(),
+                board.line_length()
+            );
+        }
 
-        while let Err(e) = match get_input() {
-            ChessInput::Move(from, to) => board.move_piece(from, to),
-            ChessInput::Select(pos) => {
-                if board.is_piece_selected() {
-                    board.move_selected(pos)
-                } else {
-                    board.select_piece(pos)
+        let status = board.status();
+        if status.is_over() {
+            println!("[Info]: {status}");
+        }
+
+        loop {
+            let result = match get_input() {
+                ChessInput::Move(from, to) => board.make_move(Move::new(from, to)),
+                ChessInput::Drop(mv) => board.make_move(mv),
+                ChessInput::Select(pos) => {
+                    if board.is_piece_selected() {
+                        board.make_selected(pos)
+                    } else {
+                        board.select_piece(pos)
+                    }
+                }
+                ChessInput::Deselect => board.deselect_piece(),
+                ChessInput::Undo => board.undo_move().map(|_| {
+                    // take the engine reply back as well, so it's our turn again
+                    if opponent
+                        .as_ref()
+                        .is_some_and(|opponent| opponent.color == board.get_turn())
+                    {
+                        board.undo_move().ok();
+                    }
+                }),
+                ChessInput::Navigate(navigation) => match navigation {
+                    Navigation::Back => board.undo_move(),
+                    Navigation::Forward => board.redo_move(),
+                    Navigation::Goto(ply) => board.goto_ply(ply),
+                    Navigation::First => board.goto_ply(0),
+                    Navigation::Last => board.goto_ply(board.line_length()),
+                },
+                ChessInput::Hint => {
+                    match describe_search(&board, hint_limits, evaluator) {
+                        Some((_, description)) => println!("[Hint]: {description}"),
+                        None => println!("[Hint]: there is no move to play"),
+                    }
+                    continue;
+                }
+            };
+            match result {
+                Ok(()) => break,
+                Err(e) => eprintln!("[Warning]: {e}"),
+            }
+        }
+    }
+}
+
+const SETUP_HELP: &str = "\
+Qd1 or put qd8    put a piece, uppercase for white
+remove d1         empty a square, clear for the whole board
+start             set up the starting position
+fen <FEN>         set up a position from FEN
+turn w|b          give the move to a side
+castling KQkq|-   set the castling rights
+ep e3|-           set the en passant square
+variant <name>    play with other rules
+play              start the game once the position is valid
+quit              leave without playing";
+
+/// Lets the position be edited with the commands of
+/// [`PositionBuilder::edit`] until it is valid and `play` is entered
+pub fn run_setup(mut builder: PositionBuilder) -> ChessBoard {
+    println!("{SETUP_HELP}");
+    loop {
+        println!("{}", builder.board());
+        println!("[FEN]: {}", builder.board().to_fen());
+        if let Err(e) = builder.validate() {
+            println!("[Info]: {e}");
+        }
+
+        loop {
+            let mut buf = String::new();
+            print!("[Setup]: ");
+            stdout().flush().unwrap();
+            let read = stdin()
+                .read_line(&mut buf)
+                .expect("[Error]: failed to read from stdin");
+
+            let result = match buf.trim() {
+                _ if read == 0 => exit(0),
+                "" => continue,
+                "help" => {
+                    println!("{SETUP_HELP}");
+                    continue;
+                }
+                "quit" | "q" => {
+                    println!("[Info]: quitting now..");
+                    exit(0);
                 }
+                "play" => match builder.build() {
+                    Ok(board) => return board,
+                    Err(e) => Err(e),
+                },
+                command => builder.edit(command),
+            };
+            match result {
+                Ok(()) => break,
+                Err(e) => eprintln!("[Warning]: {e}"),
             }
-            ChessInput::Deselect => board.deselect_piece(),
-            ChessInput::Undo => board.undo_move(),
-        } {
-            eprintln!("{e}");
+        }
+    }
+}
+
+/// Reads a position given as FEN or PGN, either directly, from a file, or
+/// from stdin when `input` is `-`. FEN is played with `variant`, while PGN
+/// names its own in its `Variant` tag
+pub fn read_position(input: &str, variant: Variant) -> Result<(ChessBoard, PgnTags), String> {
+    let text = if input == "-" {
+        read_to_string(stdin()).map_err(|e| format!("[Error]: failed to read stdin: {e}"))?
+    } else if Path::new(input).is_file() {
+        fs::read_to_string(input).map_err(|e| format!("[Error]: failed to read {input}: {e}"))?
+    } else {
+        input.to_string()
+    };
+
+    match ChessBoard::from_variant_fen(text.trim(), variant) {
+        Ok(board) => Ok((board, Vec::new())),
+        Err(fen_error) => ChessBoard::from_pgn(&text).map_err(|pgn_error| {
+            format!("[Error]: neither FEN ({fen_error}) nor PGN ({pgn_error})")
+        }),
+    }
+}
+
+/// Prints the number of leaf nodes `depth` plies deep, per root move as well
+/// when `divide` is set
+pub fn run_perft(mut board: ChessBoard, depth: u32, divide: bool) {
+    let start = Instant::now();
+    let nodes = if divide {
+        let mut nodes = 0;
+        for (mv, move_nodes) in board.perft_divide(depth) {
+            println!("{mv}: {move_nodes}");
+            nodes += move_nodes;
+        }
+        println!();
+        nodes
+    } else {
+        board.perft(depth)
+    };
+    let elapsed = start.elapsed();
+    println!(
+        "[Perft]: {nodes} nodes at depth {depth} in {:.3}s ({:.0} nodes/s)",
+        elapsed.as_secs_f64(),
+        nodes as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
+}
+
+/// Prints the position with its status and the best move the engine finds
+pub fn run_analyze(board: &ChessBoard, limits: SearchLimits, evaluator: &Evaluator) {
+    println!("{board}");
+    println!("[FEN]: {}", board.to_fen());
+
+    let status = board.status();
+    if status.is_over() {
+        println!("[Info]: {status}");
+        return;
+    }
+    let start = Instant::now();
+    if let Some((_, description)) = describe_search(board, limits, evaluator) {
+        println!(
+            "[Analysis]: {description} in {:.3}s",
+            start.elapsed().as_secs_f64()
+        );
+    }
+}
+
+/// Prints the static evaluation of the position term by term, in pawns
+/// for the middlegame and the endgame, and the blend of both for its phase
+pub fn run_eval(board: &ChessBoard, weights: &EvalWeights, network: Option<&Network>) {
+    println!("{board}");
+    println!("[FEN]: {}", board.to_fen());
+
+    let breakdown = evaluate_terms(board, weights);
+    let pawns = |centipawns: i32| centipawns as f64 / 100.0;
+    println!(
+        "{:<16}{:>16}{:>16}{:>16}",
+        "term", "white mg eg", "black mg eg", "total mg eg"
+    );
+    for (term, [white, black]) in EVAL_TERMS.iter().zip(breakdown.terms) {
+        let total = white - black;
+        println!(
+            "{:<16}{:>8.2}{:>8.2}{:>8.2}{:>8.2}{:>8.2}{:>8.2}",
+            term.name(),
+            pawns(white.mg),
+            pawns(white.eg),
+            pawns(black.mg),
+            pawns(black.eg),
+            pawns(total.mg),
+            pawns(total.eg)
+        );
+    }
+    let total = breakdown.total();
+    println!(
+        "{:<48}{:>8.2}{:>8.2}",
+        "total",
+        pawns(total.mg),
+        pawns(total.eg)
+    );
+    println!(
+        "[Eval]: {:+.2} for white at phase {}/{TOTAL_PHASE}",
+        pawns(breakdown.score()),
+        breakdown.phase
+    );
+    if let Some(network) = network {
+        match network.evaluate(board) {
+            Some(score) => {
+                let score = match board.get_turn() {
+                    ChessColor::WHITE => score,
+                    ChessColor::BLACK => -score,
+                };
+                println!("[NNUE]: {:+.2} for white", pawns(score));
+            }
+            None => println!("[NNUE]: the network can't evaluate this position"),
+        }
+    }
+}
+
+/// Reads a network file for the engine to evaluate with
+pub fn read_network(path: &str) -> Result<Network, String> {
+    let bytes = fs::read(path).map_err(|e| format!("[Error]: failed to read {path}: {e}"))?;
+    Network::from_bytes(&bytes).map_err(|e| format!("[Error]: {path}: {e}"))
+}
+
+/// Reads evaluation weights from a file `tune` wrote
+pub fn read_weights(path: &str) -> Result<EvalWeights, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("[Error]: failed to read {path}: {e}"))?;
+    text.parse().map_err(|e| format!("[Error]: {path}: {e}"))
+}
+
+/// Tunes `weights` to the labelled positions of `data`, a FEN and a game
+/// result per line, and writes them to `output`
+pub fn run_tune(
+    data: &str,
+    variant: Variant,
+    weights: &EvalWeights,
+    epochs: usize,
+    output: &str,
+) -> Result<(), String> {
+    let text = if data == "-" {
+        read_to_string(stdin()).map_err(|e| format!("[Error]: failed to read stdin: {e}"))?
+    } else {
+        fs::read_to_string(data).map_err(|e| format!("[Error]: failed to read {data}: {e}"))?
+    };
+    let mut positions = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let position = TuningPosition::parse(line, variant)
+            .map_err(|e| format!("[Error]: line {}: {e}", index + 1))?;
+        positions.push(position);
+    }
+    if positions.is_empty() {
+        return Err(format!("[Error]: {data} holds no labelled positions"));
+    }
+
+    let mut tuner = Tuner::new(&positions);
+    let scale = tuner.fit_scale(weights);
+    println!(
+        "[Tune]: {} positions, scale {scale:.3}, error {:.6}",
+        positions.len(),
+        tuner.error(weights)
+    );
+    let start = Instant::now();
+    let tuned = tuner.tune(weights, epochs, |epoch, error| {
+        if epoch % 10 == 0 || epoch == epochs {
+            println!(
+                "[Tune]: epoch {epoch}/{epochs}, error {error:.6}, {:.1} s",
+                start.elapsed().as_secs_f64()
+            );
+        }
+    });
+    println!("[Tune]: error {:.6} once rounded", tuner.error(&tuned));
+
+    fs::write(output, tuned.to_string())
+        .map_err(|e| format!("[Error]: failed to write {output}: {e}"))?;
+    println!("[Tune]: wrote the weights to {output}");
+    Ok(())
+}
+
+/// Positions `bench` searches: openings, middlegames and endgames
+const BENCH_FENS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "r2q1rk1/pp2bppp/2n1pn2/2pp4/3P1B2/2PBPN2/PP1N1PPP/R2QK2R w KQ - 0 9",
+    "2r2rk1/1bqnbppp/p2ppn2/1p6/3NP3/P1N1BP2/1PPQ2PP/2KR1B1R w - - 0 13",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "6k1/5pp1/4p2p/8/3P4/5PP1/r4K1P/2R5 b - - 0 35",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+];
+
+/// Searches the bench positions `depth` plies deep with 1, 2, 4... and
+/// `max_threads` threads, printing the time to depth of each thread count
+/// and its speedup over a single thread
+pub fn run_bench(depth: u32, max_threads: usize, hash_mb: usize) {
+    let boards: Vec<ChessBoard> = BENCH_FENS
+        .iter()
+        .map(|fen| ChessBoard::from_fen(fen).unwrap())
+        .collect();
+    let mut thread_counts: Vec<usize> = (0..)
+        .map(|power| 1 << power)
+        .take_while(|&threads| threads < max_threads)
+        .collect();
+    thread_counts.push(max_threads);
+
+    let mut single_thread_time = None;
+    for threads in thread_counts {
+        let mut time = Duration::ZERO;
+        let mut nodes = 0;
+        for board in &boards {
+            // every position starts from an empty table, as in a new game
+            let table = TranspositionTable::new(hash_mb);
+            let start = Instant::now();
+            let result = search_with_table(
+                board,
+                SearchLimits::depth(depth),
+                &AtomicBool::new(false),
+                &table,
+                &Evaluator::default(),
+                threads,
+                |_| (),
+            );
+            time += start.elapsed();
+            nodes += result.nodes;
+        }
+        let single_thread_time = *single_thread_time.get_or_insert(time);
+        println!(
+            "[Bench]: {threads:>3} threads: depth {depth} in {:.3}s, {nodes} nodes \
+             ({:.0} nodes/s), {:.2}x speedup",
+            time.as_secs_f64(),
+            nodes as f64 / time.as_secs_f64().max(1e-9),
+            single_thread_time.as_secs_f64() / time.as_secs_f64().max(1e-9)
+        );
+    }
+}
+
+/// The formats `convert` can write a game in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvertFormat {
+    Fen,
+    Pgn,
+    San,
+    Uci,
+}
+
+impl ConvertFormat {
+    pub const NAMES: [&'static str; 4] = ["fen", "pgn", "san", "uci"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fen" => Some(Self::Fen),
+            "pgn" => Some(Self::Pgn),
+            "san" => Some(Self::San),
+            "uci" => Some(Self::Uci),
+            _ => None,
+        }
+    }
+}
+
+/// Writes a game read by [`read_position`] in another notation
+pub fn run_convert(board: &ChessBoard, tags: &[(String, String)], format: ConvertFormat) {
+    match format {
+        ConvertFormat::Fen => println!("{}", board.to_fen()),
+        ConvertFormat::Pgn => {
+            let tags: Vec<(&str, String)> = tags
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone()))
+                .collect();
+            print!("{}", board.to_pgn(&tags));
+        }
+        ConvertFormat::San => println!("{}", board.san_history().join(" ")),
+        ConvertFormat::Uci => {
+            let moves: Vec<String> = board.moves().iter().map(Move::to_string).collect();
+            println!("{}", moves.join(" "));
         }
     }
 }
@@ -0,0 +1,725 @@
+use std::{
+    fmt,
+    ops::{Add, AddAssign, Mul, Neg, Sub},
+    slice,
+    str::FromStr,
+    sync::Arc,
+};
+
+use super::Network;
+use crate::chess::{
+    ChessBoard, ChessColor, ChessError, ChessVec, PieceType, ALL_PIECE_TYPES, AMAZON, ARCHBISHOP,
+    BISHOP, CHANCELLOR, KING, KNIGHT, PAWN, QUEEN, ROOK,
+};
+
+/// Material value of each piece type in centipawns, in [`PieceType`] order
+pub const PIECE_VALUES: [i32; 9] = [0, 900, 330, 320, 500, 100, 850, 900, 1250];
+
+/// Material value of a piece type in centipawns, as used to order moves
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    PIECE_VALUES[piece_type as usize]
+}
+
+/// A value in the middlegame and in the endgame, blended by how much
+/// material is left on the board
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    #[allow(missing_docs)]
+    pub mg: i32,
+    #[allow(missing_docs)]
+    pub eg: i32,
+}
+
+/// A [`Score`] from its middlegame and endgame values
+const fn s(mg: i32, eg: i32) -> Score {
+    Score { mg, eg }
+}
+
+impl Score {
+    /// The value at `phase`, from [`TOTAL_PHASE`] with every piece on the
+    /// board down to 0 with none but kings and pawns
+    pub fn taper(self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (TOTAL_PHASE - phase)) / TOTAL_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        s(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        s(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Neg for Score {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        s(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Self;
+
+    fn mul(self, factor: i32) -> Self {
+        s(self.mg * factor, self.eg * factor)
+    }
+}
+
+/// The phase of the starting position of standard chess
+pub const TOTAL_PHASE: i32 = 24;
+
+/// How much each piece type counts toward the middlegame, in [`PieceType`] order
+const PHASE_WEIGHTS: [i32; 9] = [0, 4, 1, 1, 2, 0, 3, 4, 5];
+
+/// The weights of every term of the evaluation, in centipawns. Piece-square
+/// tables are laid out as a board seen by white, the 8th rank first, and
+/// stretched over boards of other sizes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalWeights {
+    /// By piece type, in [`PieceType`] order
+    pub material: [Score; PIECE_TYPE_COUNT],
+    /// By piece type up to pawns; the fairy pieces use the table of the
+    /// piece they move the most like
+    pub piece_squares: [[Score; 64]; PIECE_TABLES],
+    /// By piece type, for every square the piece could move to
+    pub mobility: [Score; PIECE_TYPE_COUNT],
+    /// By rank of the pawn from its side's first one
+    pub passed_pawn: [Score; RANKS],
+    /// For each pawn without a friendly pawn on the files next to it
+    pub isolated_pawn: Score,
+    /// For each pawn past the first on a file
+    pub doubled_pawn: Score,
+    #[allow(missing_docs)]
+    pub bishop_pair: Score,
+    /// For each rook on a file without any pawn
+    pub rook_open_file: Score,
+    /// For each rook on a file with only enemy pawns
+    pub rook_semi_open_file: Score,
+    /// For each friendly pawn on the two ranks in front of the king, on
+    /// its file and the ones next to it
+    pub king_shelter: Score,
+}
+
+/// Piece-square tables from their middlegame and endgame halves
+const fn tables(mg: [i32; 64], eg: [i32; 64]) -> [Score; 64] {
+    let mut table = [s(0, 0); 64];
+    let mut i = 0;
+    while i < 64 {
+        table[i] = s(mg[i], eg[i]);
+        i += 1;
+    }
+    table
+}
+
+#[rustfmt::skip]
+const KNIGHT_SQUARES: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP_SQUARES: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_SQUARES: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+      5,  10,  10,  10,  10,  10,  10,   5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+     -5,   0,   0,   0,   0,   0,   0,  -5,
+      0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN_SQUARES: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_SQUARES_MG: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_SQUARES_EG: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+#[rustfmt::skip]
+const PAWN_SQUARES_MG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     50,  50,  50,  50,  50,  50,  50,  50,
+     10,  10,  20,  30,  30,  20,  10,  10,
+      5,   5,  10,  25,  25,  10,   5,   5,
+      0,   0,   0,  20,  20,   0,   0,   0,
+      5,  -5, -10,   0,   0, -10,  -5,   5,
+      5,  10,  10, -20, -20,  10,  10,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_SQUARES_EG: [i32; 64] = [
+      0,   0,   0,   0,   0,   0,   0,   0,
+     40,  40,  40,  40,  40,  40,  40,  40,
+     25,  25,  25,  25,  25,  25,  25,  25,
+     15,  15,  15,  15,  15,  15,  15,  15,
+      5,   5,   5,   5,   5,   5,   5,   5,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+      0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+/// The weights the engine plays with
+pub const DEFAULT_WEIGHTS: EvalWeights = EvalWeights {
+    material: [
+        s(0, 0),
+        s(900, 940),
+        s(330, 340),
+        s(320, 300),
+        s(500, 520),
+        s(100, 120),
+        s(850, 860),
+        s(900, 920),
+        s(1250, 1290),
+    ],
+    piece_squares: [
+        tables(KING_SQUARES_MG, KING_SQUARES_EG),
+        tables(QUEEN_SQUARES, QUEEN_SQUARES),
+        tables(BISHOP_SQUARES, BISHOP_SQUARES),
+        tables(KNIGHT_SQUARES, KNIGHT_SQUARES),
+        tables(ROOK_SQUARES, ROOK_SQUARES),
+        tables(PAWN_SQUARES_MG, PAWN_SQUARES_EG),
+    ],
+    mobility: [
+        s(0, 0),
+        s(1, 2),
+        s(5, 5),
+        s(4, 4),
+        s(2, 4),
+        s(0, 0),
+        s(3, 3),
+        s(2, 3),
+        s(1, 2),
+    ],
+    passed_pawn: [
+        s(0, 0),
+        s(5, 10),
+        s(5, 15),
+        s(10, 25),
+        s(20, 45),
+        s(35, 75),
+        s(60, 120),
+        s(0, 0),
+    ],
+    isolated_pawn: s(-10, -15),
+    doubled_pawn: s(-10, -20),
+    bishop_pair: s(30, 50),
+    rook_open_file: s(25, 10),
+    rook_semi_open_file: s(10, 5),
+    king_shelter: s(10, 0),
+};
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        DEFAULT_WEIGHTS
+    }
+}
+
+const PIECE_TYPE_COUNT: usize = ALL_PIECE_TYPES.len();
+/// Piece types up to pawns, which have their own piece-square table
+const PIECE_TABLES: usize = PAWN as usize + 1;
+const RANKS: usize = 8;
+
+// where each field starts among the scores, in declaration order
+const MATERIAL_OFFSET: usize = 0;
+const PIECE_SQUARES_OFFSET: usize = MATERIAL_OFFSET + PIECE_TYPE_COUNT;
+const MOBILITY_OFFSET: usize = PIECE_SQUARES_OFFSET + PIECE_TABLES * 64;
+const PASSED_PAWN_OFFSET: usize = MOBILITY_OFFSET + PIECE_TYPE_COUNT;
+const ISOLATED_PAWN_OFFSET: usize = PASSED_PAWN_OFFSET + RANKS;
+const DOUBLED_PAWN_OFFSET: usize = ISOLATED_PAWN_OFFSET + 1;
+const BISHOP_PAIR_OFFSET: usize = DOUBLED_PAWN_OFFSET + 1;
+const ROOK_OPEN_FILE_OFFSET: usize = BISHOP_PAIR_OFFSET + 1;
+const ROOK_SEMI_OPEN_FILE_OFFSET: usize = ROOK_OPEN_FILE_OFFSET + 1;
+const KING_SHELTER_OFFSET: usize = ROOK_SEMI_OPEN_FILE_OFFSET + 1;
+
+/// How many [`Score`]s [`EvalWeights`] holds
+pub const WEIGHT_COUNT: usize = KING_SHELTER_OFFSET + 1;
+
+/// One [`Score`] of [`EvalWeights`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Weight {
+    Material(PieceType),
+    /// By table and square
+    PieceSquare(usize, usize),
+    Mobility(PieceType),
+    /// By rank
+    PassedPawn(usize),
+    IsolatedPawn,
+    DoubledPawn,
+    BishopPair,
+    RookOpenFile,
+    RookSemiOpenFile,
+    KingShelter,
+}
+
+impl Weight {
+    /// Where the weight is in [`EvalWeights::scores_mut`]
+    pub(super) fn index(self) -> usize {
+        match self {
+            Weight::Material(piece_type) => MATERIAL_OFFSET + piece_type as usize,
+            Weight::PieceSquare(table, square) => PIECE_SQUARES_OFFSET + table * 64 + square,
+            Weight::Mobility(piece_type) => MOBILITY_OFFSET + piece_type as usize,
+            Weight::PassedPawn(rank) => PASSED_PAWN_OFFSET + rank,
+            Weight::IsolatedPawn => ISOLATED_PAWN_OFFSET,
+            Weight::DoubledPawn => DOUBLED_PAWN_OFFSET,
+            Weight::BishopPair => BISHOP_PAIR_OFFSET,
+            Weight::RookOpenFile => ROOK_OPEN_FILE_OFFSET,
+            Weight::RookSemiOpenFile => ROOK_SEMI_OPEN_FILE_OFFSET,
+            Weight::KingShelter => KING_SHELTER_OFFSET,
+        }
+    }
+}
+
+impl EvalWeights {
+    pub(super) fn get(&self, weight: Weight) -> Score {
+        match weight {
+            Weight::Material(piece_type) => self.material[piece_type as usize],
+            Weight::PieceSquare(table, square) => self.piece_squares[table][square],
+            Weight::Mobility(piece_type) => self.mobility[piece_type as usize],
+            Weight::PassedPawn(rank) => self.passed_pawn[rank],
+            Weight::IsolatedPawn => self.isolated_pawn,
+            Weight::DoubledPawn => self.doubled_pawn,
+            Weight::BishopPair => self.bishop_pair,
+            Weight::RookOpenFile => self.rook_open_file,
+            Weight::RookSemiOpenFile => self.rook_semi_open_file,
+            Weight::KingShelter => self.king_shelter,
+        }
+    }
+
+    /// Every field by its name in a weights file
+    fn fields_mut(&mut self) -> Vec<(String, &mut [Score])> {
+        let mut fields = vec![("material".to_string(), &mut self.material[..])];
+        for (piece_type, table) in ALL_PIECE_TYPES.iter().zip(&mut self.piece_squares) {
+            let name = format!("{piece_type:?}").to_lowercase();
+            fields.push((format!("piece_squares.{name}"), &mut table[..]));
+        }
+        fields.extend([
+            ("mobility".to_string(), &mut self.mobility[..]),
+            ("passed_pawn".to_string(), &mut self.passed_pawn[..]),
+            (
+                "isolated_pawn".to_string(),
+                slice::from_mut(&mut self.isolated_pawn),
+            ),
+            (
+                "doubled_pawn".to_string(),
+                slice::from_mut(&mut self.doubled_pawn),
+            ),
+            (
+                "bishop_pair".to_string(),
+                slice::from_mut(&mut self.bishop_pair),
+            ),
+            (
+                "rook_open_file".to_string(),
+                slice::from_mut(&mut self.rook_open_file),
+            ),
+            (
+                "rook_semi_open_file".to_string(),
+                slice::from_mut(&mut self.rook_semi_open_file),
+            ),
+            (
+                "king_shelter".to_string(),
+                slice::from_mut(&mut self.king_shelter),
+            ),
+        ]);
+        fields
+    }
+
+    /// All [`WEIGHT_COUNT`] scores, field after field
+    pub fn scores_mut(&mut self) -> Vec<&mut Score> {
+        self.fields_mut()
+            .into_iter()
+            .flat_map(|(_, scores)| scores.iter_mut())
+            .collect()
+    }
+}
+
+/// Writes the weights a field per line, as its name followed by `mg/eg` pairs
+impl fmt::Display for EvalWeights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, scores) in self.clone().fields_mut() {
+            write!(f, "{name}")?;
+            for score in scores.iter() {
+                write!(f, " {}/{}", score.mg, score.eg)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads weights as they are written, fields left out keeping their default
+/// and lines starting with `#` skipped
+impl FromStr for EvalWeights {
+    type Err = ChessError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut weights = DEFAULT_WEIGHTS;
+        let mut fields = weights.fields_mut();
+        for (index, line) in text.lines().enumerate() {
+            let error = |reason| ChessError::InvalidWeights {
+                line: index + 1,
+                reason,
+            };
+            let mut words = line.split_whitespace();
+            let Some(name) = words.next().filter(|name| !name.starts_with('#')) else {
+                continue;
+            };
+            let (_, scores) = fields
+                .iter_mut()
+                .find(|(field, _)| field == name)
+                .ok_or(error("no weight goes by the name"))?;
+            let values: Vec<Score> = words
+                .map(|pair| {
+                    let (mg, eg) = pair.split_once('/')?;
+                    Some(s(mg.parse().ok()?, eg.parse().ok()?))
+                })
+                .collect::<Option<_>>()
+                .ok_or(error("expected mg/eg pairs of whole centipawns"))?;
+            if values.len() != scores.len() {
+                return Err(error("wrong number of mg/eg pairs"));
+            }
+            scores.copy_from_slice(&values);
+        }
+        drop(fields);
+        Ok(weights)
+    }
+}
+
+/// The terms of the evaluation, as [`evaluate_terms`] breaks it down
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalTerm {
+    /// Pieces on the board and in pockets
+    Material,
+    PieceSquares,
+    Mobility,
+    PassedPawns,
+    IsolatedPawns,
+    DoubledPawns,
+    BishopPair,
+    RookFiles,
+    KingShelter,
+}
+
+/// Every term of the evaluation, in the order they are printed
+pub const EVAL_TERMS: [EvalTerm; 9] = [
+    EvalTerm::Material,
+    EvalTerm::PieceSquares,
+    EvalTerm::Mobility,
+    EvalTerm::PassedPawns,
+    EvalTerm::IsolatedPawns,
+    EvalTerm::DoubledPawns,
+    EvalTerm::BishopPair,
+    EvalTerm::RookFiles,
+    EvalTerm::KingShelter,
+];
+
+impl EvalTerm {
+    /// The name of the term for people
+    pub fn name(self) -> &'static str {
+        match self {
+            EvalTerm::Material => "material",
+            EvalTerm::PieceSquares => "piece squares",
+            EvalTerm::Mobility => "mobility",
+            EvalTerm::PassedPawns => "passed pawns",
+            EvalTerm::IsolatedPawns => "isolated pawns",
+            EvalTerm::DoubledPawns => "doubled pawns",
+            EvalTerm::BishopPair => "bishop pair",
+            EvalTerm::RookFiles => "rook files",
+            EvalTerm::KingShelter => "king shelter",
+        }
+    }
+}
+
+/// The evaluation of a position term by term, for each color
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalBreakdown {
+    /// By [`EvalTerm`], then by color
+    pub terms: [[Score; 2]; EVAL_TERMS.len()],
+    /// From [`TOTAL_PHASE`] in the opening down to 0 in pawn endgames
+    pub phase: i32,
+}
+
+impl EvalBreakdown {
+    /// The sum of every term, white's minus black's
+    pub fn total(&self) -> Score {
+        self.terms
+            .iter()
+            .fold(Score::default(), |total, [white, black]| {
+                total + *white - *black
+            })
+    }
+
+    /// The tapered score in centipawns from the point of view of white
+    pub fn score(&self) -> i32 {
+        self.total().taper(self.phase)
+    }
+}
+
+/// What the search scores positions with, shared by its threads and by the
+/// searches of a game
+#[derive(Debug, Clone, Default)]
+pub struct Evaluator {
+    /// The weights of the handcrafted evaluation
+    pub weights: Arc<EvalWeights>,
+    /// The network scoring the positions it can play instead, if any
+    pub network: Option<Arc<Network>>,
+}
+
+/// Scores the position in centipawns from the point of view of the side to
+/// move, pieces in a pocket counting as much as the ones on the board
+pub fn evaluate(board: &ChessBoard) -> i32 {
+    evaluate_with(board, &DEFAULT_WEIGHTS)
+}
+
+/// Like [`evaluate`], with other weights
+pub fn evaluate_with(board: &ChessBoard, weights: &EvalWeights) -> i32 {
+    let score = evaluate_terms(board, weights).score();
+    match board.get_turn() {
+        ChessColor::WHITE => score,
+        ChessColor::BLACK => -score,
+    }
+}
+
+/// The square of the piece-square tables `pos` stands for, seen by `color`
+fn table_square(board: &ChessBoard, pos: ChessVec, color: ChessColor) -> usize {
+    let col = pos.col as usize * 8 / board.width();
+    let row = pos.row as usize * 8 / board.height();
+    let rank = match color {
+        ChessColor::WHITE => row,
+        ChessColor::BLACK => 7 - row,
+    };
+    (7 - rank) * 8 + col
+}
+
+/// The table of the standard piece moving the most like `piece_type`
+fn table_piece(piece_type: PieceType) -> usize {
+    match piece_type {
+        ARCHBISHOP => KNIGHT as usize,
+        CHANCELLOR => ROOK as usize,
+        AMAZON => QUEEN as usize,
+        piece_type => piece_type as usize,
+    }
+}
+
+/// Squares the piece on `pos` could move to, empty or holding an enemy piece
+fn mobility(board: &ChessBoard, pos: ChessVec, piece_type: PieceType, color: ChessColor) -> i32 {
+    let reachable = |to: ChessVec| board.contains(to) && board[to].is_none_or(|p| p.color != color);
+    let movement = board.movement(piece_type);
+    let leaps = movement
+        .leaps
+        .iter()
+        .filter(|&&offset| reachable(pos + offset))
+        .count();
+    let mut slides = 0;
+    for &direction in movement.slides {
+        let mut to = pos + direction;
+        while reachable(to) {
+            slides += 1;
+            if board[to].is_some() {
+                break;
+            }
+            to = to + direction;
+        }
+    }
+    (leaps + slides) as i32
+}
+
+/// Breaks the evaluation of the position down into its terms, with `weights`
+pub fn evaluate_terms(board: &ChessBoard, weights: &EvalWeights) -> EvalBreakdown {
+    let mut terms = [[Score::default(); 2]; EVAL_TERMS.len()];
+    let phase = visit_terms(board, |term, color, weight, count| {
+        terms[term as usize][color as usize] += weights.get(weight) * count;
+    });
+    EvalBreakdown { terms, phase }
+}
+
+/// Calls `add` with every weight the position scores for each color, and how
+/// many times, returning the phase of the position
+pub(super) fn visit_terms(
+    board: &ChessBoard,
+    mut add: impl FnMut(EvalTerm, ChessColor, Weight, i32),
+) -> i32 {
+    // pawns by file and color, and the rows they stand on
+    let width = board.width();
+    let mut pawns: [Vec<Vec<i32>>; 2] = [vec![Vec::new(); width], vec![Vec::new(); width]];
+    let mut bishops = [0; 2];
+    let mut phase = 0;
+    for (pos, piece) in board.pieces() {
+        if piece.piece_type == PAWN {
+            pawns[piece.color as usize][pos.col as usize].push(pos.row);
+        }
+    }
+
+    for (pos, piece) in board.pieces() {
+        let (piece_type, color) = (piece.piece_type, piece.color);
+        phase += PHASE_WEIGHTS[piece_type as usize];
+        add(EvalTerm::Material, color, Weight::Material(piece_type), 1);
+        let square = table_square(board, pos, color);
+        add(
+            EvalTerm::PieceSquares,
+            color,
+            Weight::PieceSquare(table_piece(piece_type), square),
+            1,
+        );
+        if piece_type != PAWN {
+            let squares = mobility(board, pos, piece_type, color);
+            add(
+                EvalTerm::Mobility,
+                color,
+                Weight::Mobility(piece_type),
+                squares,
+            );
+        }
+
+        let [own_pawns, enemy_pawns] = match color {
+            ChessColor::WHITE => [&pawns[0], &pawns[1]],
+            ChessColor::BLACK => [&pawns[1], &pawns[0]],
+        };
+        let col = pos.col as usize;
+        let files = col.saturating_sub(1)..(col + 2).min(width);
+        // rows ahead of `row` from the point of view of `color`
+        let ahead = |row: i32, of: i32| match color {
+            ChessColor::WHITE => of > row,
+            ChessColor::BLACK => of < row,
+        };
+        match piece_type {
+            PAWN => {
+                let blocked = files
+                    .clone()
+                    .any(|file| enemy_pawns[file].iter().any(|&row| ahead(pos.row, row)));
+                if !blocked {
+                    let rank = match color {
+                        ChessColor::WHITE => pos.row,
+                        ChessColor::BLACK => board.height() as i32 - 1 - pos.row,
+                    };
+                    let rank = rank as usize * 8 / board.height();
+                    add(EvalTerm::PassedPawns, color, Weight::PassedPawn(rank), 1);
+                }
+                let isolated = files
+                    .filter(|&file| file != col)
+                    .all(|file| own_pawns[file].is_empty());
+                if isolated {
+                    add(EvalTerm::IsolatedPawns, color, Weight::IsolatedPawn, 1);
+                }
+            }
+            BISHOP => bishops[color as usize] += 1,
+            ROOK => {
+                if own_pawns[col].is_empty() && enemy_pawns[col].is_empty() {
+                    add(EvalTerm::RookFiles, color, Weight::RookOpenFile, 1);
+                } else if own_pawns[col].is_empty() {
+                    add(EvalTerm::RookFiles, color, Weight::RookSemiOpenFile, 1);
+                }
+            }
+            KING => {
+                let direction = match color {
+                    ChessColor::WHITE => 1,
+                    ChessColor::BLACK => -1,
+                };
+                let shelter: usize = files
+                    .map(|file| {
+                        own_pawns[file]
+                            .iter()
+                            .filter(|&&row| {
+                                row == pos.row + direction || row == pos.row + 2 * direction
+                            })
+                            .count()
+                    })
+                    .sum();
+                add(
+                    EvalTerm::KingShelter,
+                    color,
+                    Weight::KingShelter,
+                    shelter as i32,
+                );
+            }
+            _ => (),
+        }
+    }
+
+    for color in [ChessColor::WHITE, ChessColor::BLACK] {
+        for piece_type in board.pocket(color) {
+            phase += PHASE_WEIGHTS[piece_type as usize];
+            add(EvalTerm::Material, color, Weight::Material(piece_type), 1);
+        }
+        if bishops[color as usize] >= 2 {
+            add(EvalTerm::BishopPair, color, Weight::BishopPair, 1);
+        }
+        let doubled: usize = pawns[color as usize]
+            .iter()
+            .map(|file| file.len().saturating_sub(1))
+            .sum();
+        add(
+            EvalTerm::DoubledPawns,
+            color,
+            Weight::DoubledPawn,
+            doubled as i32,
+        );
+    }
+
+    phase.min(TOTAL_PHASE)
+}
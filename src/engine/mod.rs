@@ -0,0 +1,16 @@
+//! A negamax alpha-beta search looking for the best move of a position
+
+mod eval;
+mod nnue;
+mod search;
+mod table;
+mod tuner;
+
+pub use eval::*;
+pub use nnue::*;
+pub use search::*;
+pub use table::*;
+pub use tuner::*;
+
+#[cfg(test)]
+mod test_engine;
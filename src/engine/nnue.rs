@@ -0,0 +1,477 @@
+use std::sync::Arc;
+
+use crate::chess::{
+    ChessBoard, ChessColor, ChessError, ChessVec, Move, PieceType, ALL_PIECE_TYPES, KING, PAWN,
+};
+
+/// Squares of the boards the network plays on, 8 by 8
+const SQUARES: usize = 64;
+/// Pieces other than kings, by type and by whether they belong to the side
+/// the accumulator sees the board for
+const PIECE_KINDS: usize = 10;
+/// Inputs of the network for each side: the square of its king, then a
+/// piece and the square it stands on
+pub const HALFKP_FEATURES: usize = SQUARES * PIECE_KINDS * SQUARES;
+/// Accumulator values are clipped to 0..=QA before the output layer
+const QA: i32 = 255;
+/// Output weights are scaled by QB
+const QB: i32 = 64;
+/// Centipawns an output of QA * QB stands for
+const SCALE: i64 = 400;
+
+const MAGIC: &[u8; 4] = b"CRNN";
+const VERSION: u32 = 1;
+
+/// A HalfKP network: a layer from the features of each side to `hidden`
+/// int16 values, clipped and weighed into a score for the side to move.
+///
+/// Network files are little-endian: `CRNN`, the version 1 and the hidden
+/// size as u32s, then as i16s the weights of every feature
+/// (`(king * 10 + piece) * 64 + square`, squares from a1 to h8 and flipped
+/// for black, pieces queen, bishop, knight, rook and pawn of the side then
+/// of its opponent), the hidden biases and the output weights for the side
+/// to move then the other, and last the output bias as an i32
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+/// Reads the fields of a network file in turn
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ChessError> {
+        if self.0.len() < len {
+            return Err(ChessError::InvalidNetwork {
+                reason: "the file ends too early",
+            });
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, ChessError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i16s(&mut self, len: usize) -> Result<Vec<i16>, ChessError> {
+        let bytes = self.take(len * 2)?;
+        Ok(bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect())
+    }
+}
+
+impl Network {
+    /// Reads a network file, as [`Network::to_bytes`] writes it
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ChessError> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC || reader.u32()? != VERSION {
+            return Err(ChessError::InvalidNetwork {
+                reason: "not a version 1 chess-rs network",
+            });
+        }
+        let hidden = reader.u32()? as usize;
+        if hidden == 0 || !hidden.is_multiple_of(16) || hidden > 4096 {
+            return Err(ChessError::InvalidNetwork {
+                reason: "the hidden size must be a multiple of 16 up to 4096",
+            });
+        }
+        let network = Self {
+            hidden,
+            feature_weights: reader.i16s(HALFKP_FEATURES * hidden)?,
+            feature_biases: reader.i16s(hidden)?,
+            output_weights: reader.i16s(2 * hidden)?,
+            output_bias: reader.u32()? as i32,
+        };
+        if !reader.0.is_empty() {
+            return Err(ChessError::InvalidNetwork {
+                reason: "the file goes on past the output bias",
+            });
+        }
+        Ok(network)
+    }
+
+    /// Writes the network in its file format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12 + 2 * self.feature_weights.len());
+        bytes.extend(MAGIC);
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend((self.hidden as u32).to_le_bytes());
+        for weights in [
+            &self.feature_weights,
+            &self.feature_biases,
+            &self.output_weights,
+        ] {
+            bytes.extend(weights.iter().flat_map(|weight| weight.to_le_bytes()));
+        }
+        bytes.extend(self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    /// Number of values of each side's accumulator
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    fn feature_weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+
+    /// The accumulator of `side` from scratch
+    fn refresh(&self, placement: &Placement, side: ChessColor, values: &mut Vec<i16>) {
+        values.clone_from(&self.feature_biases);
+        let king = king_square(placement, side).unwrap();
+        for (square, &code) in placement.iter().enumerate() {
+            if let Some(feature) = feature(side, king, code, square) {
+                simd::add(values, self.feature_weights(feature));
+            }
+        }
+    }
+
+    /// The score in centipawns for `turn`, from the accumulators of both sides
+    fn output(&self, values: &[Vec<i16>; 2], turn: ChessColor) -> i32 {
+        let (own, other) = self.output_weights.split_at(self.hidden);
+        let sum = self.output_bias as i64
+            + simd::clipped_dot(&values[turn as usize], own)
+            + simd::clipped_dot(&values[turn.opponent() as usize], other);
+        (sum * SCALE / (QA * QB) as i64) as i32
+    }
+
+    /// Scores the board for the side to move from scratch, when the
+    /// network can play it: an 8x8 board of standard pieces with a king a
+    /// side and nothing in the pockets
+    pub fn evaluate(&self, board: &ChessBoard) -> Option<i32> {
+        let placement = placement(board)?;
+        let mut values = [Vec::new(), Vec::new()];
+        for side in [ChessColor::WHITE, ChessColor::BLACK] {
+            self.refresh(&placement, side, &mut values[side as usize]);
+        }
+        Some(self.output(&values, board.get_turn()))
+    }
+}
+
+/// What stands on each square: 0 for nothing, otherwise 1 + 6 * color + type
+type Placement = [u8; SQUARES];
+
+/// The placement of the board, when the network can play it
+fn placement(board: &ChessBoard) -> Option<Placement> {
+    if board.width() != 8 || board.height() != 8 {
+        return None;
+    }
+    let mut placement = [0; SQUARES];
+    let mut kings = [0; 2];
+    for (pos, piece) in board.pieces() {
+        if piece.piece_type == KING {
+            kings[piece.color as usize] += 1;
+        }
+        placement[pos.row as usize * 8 + pos.col as usize] = code_at(board, pos)?;
+    }
+    let pockets_empty = [ChessColor::WHITE, ChessColor::BLACK]
+        .into_iter()
+        .all(|color| board.pocket(color).is_empty());
+    (kings == [1, 1] && pockets_empty).then_some(placement)
+}
+
+fn decode(code: u8) -> (ChessColor, PieceType) {
+    let color = match (code - 1) / 6 {
+        0 => ChessColor::WHITE,
+        _ => ChessColor::BLACK,
+    };
+    (color, ALL_PIECE_TYPES[(code as usize - 1) % 6])
+}
+
+/// The square of the king of `side`, flipped for black
+fn king_square(placement: &Placement, side: ChessColor) -> Option<usize> {
+    let code = 1 + 6 * side as u8 + KING as u8;
+    let square = placement.iter().position(|&c| c == code)?;
+    Some(orient(side, square))
+}
+
+fn orient(side: ChessColor, square: usize) -> usize {
+    match side {
+        ChessColor::WHITE => square,
+        ChessColor::BLACK => square ^ 56,
+    }
+}
+
+/// The input of `side` for the piece `code` on `square`, kings having none
+fn feature(side: ChessColor, king: usize, code: u8, square: usize) -> Option<usize> {
+    if code == 0 {
+        return None;
+    }
+    let (color, piece_type) = decode(code);
+    if piece_type == KING {
+        return None;
+    }
+    let kind = (color != side) as usize * 5 + piece_type as usize - 1;
+    Some((king * PIECE_KINDS + kind) * SQUARES + orient(side, square))
+}
+
+/// The accumulators of a position of the line the search is on
+#[derive(Clone)]
+struct Accumulator {
+    /// What `values` were worked out for, none when the network can't
+    /// play the position
+    placement: Option<Placement>,
+    /// By side
+    values: [Vec<i16>; 2],
+}
+
+/// The accumulators of the positions a search went through to reach the
+/// one it is on, kept along with its moves: a move adds and takes off the
+/// features of the pieces it moved, took, promoted or blew up, and only a
+/// side whose king moved gets its accumulator worked out from scratch.
+///
+/// Pieces in a pocket aren't inputs of the network, so positions of
+/// variants with pockets are left to the handcrafted evaluation, as are the
+/// positions where a king was taken or a piece promoted to one and the
+/// lines going on from them
+#[derive(Clone)]
+pub struct Accumulators {
+    network: Arc<Network>,
+    /// Reused as moves are played and taken back
+    stack: Vec<Accumulator>,
+    /// Entries of the stack in use, the last one for the board as it is
+    len: usize,
+}
+
+impl Accumulators {
+    /// The accumulators of `board`, for `network`
+    pub fn new(network: Arc<Network>, board: &ChessBoard) -> Self {
+        let placement = placement(board).filter(|_| !board.variant().rules().has_pockets());
+        let mut values = [Vec::new(), Vec::new()];
+        if let Some(placement) = &placement {
+            for side in [ChessColor::WHITE, ChessColor::BLACK] {
+                network.refresh(placement, side, &mut values[side as usize]);
+            }
+        }
+        Self {
+            network,
+            stack: vec![Accumulator { placement, values }],
+            len: 1,
+        }
+    }
+
+    /// Follows `mv`, just played on `board`, from the accumulators of the
+    /// position before it
+    pub fn push(&mut self, mv: Move, board: &ChessBoard) {
+        if self.len == self.stack.len() {
+            self.stack.push(Accumulator {
+                placement: None,
+                values: [Vec::new(), Vec::new()],
+            });
+        }
+        let network = &*self.network;
+        let (done, rest) = self.stack.split_at_mut(self.len);
+        let (previous, accumulator) = (&done[self.len - 1], &mut rest[0]);
+        self.len += 1;
+
+        accumulator.placement = None;
+        let Some(before) = previous.placement.filter(|_| mv.drop.is_none()) else {
+            return;
+        };
+        let touched = touched_squares(&before, mv, board);
+        let mut placement = before;
+        let mut kings = [0; 2];
+        for square in squares(touched) {
+            let pos = ChessVec::new((square % 8) as i32, (square / 8) as i32);
+            let Some(code) = code_at(board, pos) else {
+                return;
+            };
+            for (code, count) in [(before[square], -1), (code, 1)] {
+                if code != 0 && decode(code).1 == KING {
+                    kings[decode(code).0 as usize] += count;
+                }
+            }
+            placement[square] = code;
+        }
+        // a king was taken, blown up or promoted to
+        if kings != [0, 0] {
+            return;
+        }
+
+        for side in [ChessColor::WHITE, ChessColor::BLACK] {
+            let values = &mut accumulator.values[side as usize];
+            let king = king_square(&placement, side).unwrap();
+            if king_square(&before, side) != Some(king) {
+                network.refresh(&placement, side, values);
+                continue;
+            }
+            values.clone_from(&previous.values[side as usize]);
+            for square in squares(touched).filter(|&square| before[square] != placement[square]) {
+                if let Some(feature) = feature(side, king, before[square], square) {
+                    simd::sub(values, network.feature_weights(feature));
+                }
+                if let Some(feature) = feature(side, king, placement[square], square) {
+                    simd::add(values, network.feature_weights(feature));
+                }
+            }
+        }
+        accumulator.placement = Some(placement);
+    }
+
+    /// Goes back to the position before the last move
+    pub fn pop(&mut self) {
+        debug_assert!(self.len > 1, "no move to take back");
+        self.len -= 1;
+    }
+
+    /// Scores `board`, the position of the last move pushed, for the side to
+    /// move, when the network can play it
+    pub fn evaluate(&self, board: &ChessBoard) -> Option<i32> {
+        let top = &self.stack[self.len - 1];
+        debug_assert!(
+            top.placement.is_none() || top.placement == placement(board),
+            "the accumulators are out of step with the board"
+        );
+        top.placement
+            .map(|_| self.network.output(&top.values, board.get_turn()))
+    }
+}
+
+/// The code of what stands on `pos`, none for a piece the network doesn't know
+fn code_at(board: &ChessBoard, pos: ChessVec) -> Option<u8> {
+    match board[pos] {
+        None => Some(0),
+        Some(piece) if (piece.piece_type as usize) < 6 => {
+            Some(1 + 6 * piece.color as u8 + piece.piece_type as u8)
+        }
+        Some(_) => None,
+    }
+}
+
+/// The squares `mv` may have changed, as a bit per square: the squares it
+/// left and reached, the pawn it took en passant, the rank of a king that
+/// may have castled, and around the square of a capture when captures
+/// explode
+fn touched_squares(before: &Placement, mv: Move, board: &ChessBoard) -> u64 {
+    let bit = |pos: ChessVec| 1u64 << (pos.row * 8 + pos.col);
+    let mut touched = bit(mv.from) | bit(mv.to);
+    let (_, piece_type) = decode(before[(mv.from.row * 8 + mv.from.col) as usize]);
+    let pawn_capture = piece_type == PAWN && mv.from.col != mv.to.col;
+    match piece_type {
+        KING => touched |= 0xff << (mv.from.row * 8),
+        _ if pawn_capture => touched |= bit(ChessVec::new(mv.to.col, mv.from.row)),
+        _ => (),
+    }
+    let capture = pawn_capture || before[(mv.to.row * 8 + mv.to.col) as usize] != 0;
+    if capture && board.variant().rules().has_explosions() {
+        for (col, row) in (-1..=1).flat_map(|col| (-1..=1).map(move |row| (col, row))) {
+            let pos = mv.to + ChessVec::new(col, row);
+            if board.contains(pos) {
+                touched |= bit(pos);
+            }
+        }
+    }
+    touched
+}
+
+/// The squares of the bits set in `mask`
+fn squares(mut mask: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let square = mask.trailing_zeros() as usize;
+        mask &= mask.wrapping_sub(1);
+        (square < SQUARES).then_some(square)
+    })
+}
+
+/// The vector operations of the network, with AVX2 when the CPU has it;
+/// the fallbacks are written for the compiler to vectorize
+mod simd {
+    use super::QA;
+
+    /// `values += weights`
+    pub fn add(values: &mut [i16], weights: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU was just found to support AVX2
+            return unsafe { avx2::add(values, weights) };
+        }
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_add(*weight);
+        }
+    }
+
+    /// `values -= weights`
+    pub fn sub(values: &mut [i16], weights: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU was just found to support AVX2
+            return unsafe { avx2::sub(values, weights) };
+        }
+        for (value, weight) in values.iter_mut().zip(weights) {
+            *value = value.wrapping_sub(*weight);
+        }
+    }
+
+    /// The sum of `values` clipped to 0..=QA times `weights`
+    pub fn clipped_dot(values: &[i16], weights: &[i16]) -> i64 {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU was just found to support AVX2
+            return unsafe { avx2::clipped_dot(values, weights) };
+        }
+        values
+            .iter()
+            .zip(weights)
+            .map(|(&value, &weight)| (value as i32).clamp(0, QA) * weight as i32)
+            .map(i64::from)
+            .sum()
+    }
+
+    /// Slices are as long as the hidden layer, a multiple of 16 values
+    #[cfg(target_arch = "x86_64")]
+    mod avx2 {
+        use std::arch::x86_64::*;
+
+        use super::QA;
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn add(values: &mut [i16], weights: &[i16]) {
+            for (values, weights) in values.chunks_exact_mut(16).zip(weights.chunks_exact(16)) {
+                let value = _mm256_loadu_si256(values.as_ptr().cast());
+                let weight = _mm256_loadu_si256(weights.as_ptr().cast());
+                let sum = _mm256_add_epi16(value, weight);
+                _mm256_storeu_si256(values.as_mut_ptr().cast(), sum);
+            }
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn sub(values: &mut [i16], weights: &[i16]) {
+            for (values, weights) in values.chunks_exact_mut(16).zip(weights.chunks_exact(16)) {
+                let value = _mm256_loadu_si256(values.as_ptr().cast());
+                let weight = _mm256_loadu_si256(weights.as_ptr().cast());
+                let difference = _mm256_sub_epi16(value, weight);
+                _mm256_storeu_si256(values.as_mut_ptr().cast(), difference);
+            }
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn clipped_dot(values: &[i16], weights: &[i16]) -> i64 {
+            let zero = _mm256_setzero_si256();
+            let max = _mm256_set1_epi16(QA as i16);
+            let mut sum = 0;
+            // i32 lanes hold up to 64 pairs of products of 255 * 32767
+            for (values, weights) in values.chunks(64 * 16).zip(weights.chunks(64 * 16)) {
+                let mut lanes = _mm256_setzero_si256();
+                for (values, weights) in values.chunks_exact(16).zip(weights.chunks_exact(16)) {
+                    let value = _mm256_loadu_si256(values.as_ptr().cast());
+                    let weight = _mm256_loadu_si256(weights.as_ptr().cast());
+                    let clipped = _mm256_min_epi16(_mm256_max_epi16(value, zero), max);
+                    lanes = _mm256_add_epi32(lanes, _mm256_madd_epi16(clipped, weight));
+                }
+                let mut stored = [0i32; 8];
+                _mm256_storeu_si256(stored.as_mut_ptr().cast(), lanes);
+                sum += stored.iter().map(|&lane| lane as i64).sum::<i64>();
+            }
+            sum
+        }
+    }
+}
@@ -0,0 +1,456 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    evaluate_with, piece_value, Accumulators, Bound, Evaluator, TableEntry, TranspositionTable,
+};
+use crate::chess::{ChessBoard, GameStatus, Move};
+
+/// Score of a mate on the board; mates further away score a ply less each
+pub const MATE_SCORE: i32 = 100_000;
+/// The deepest the search ever goes, in plies
+pub const MAX_DEPTH: u32 = 64;
+/// The most threads a search can run on
+pub const MAX_THREADS: usize = 256;
+
+/// When to stop searching; a search without any limit runs to [`MAX_DEPTH`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchLimits {
+    /// Plies to search at most
+    pub depth: Option<u32>,
+    /// Positions to visit at most
+    pub nodes: Option<u64>,
+    /// Thinking time in milliseconds
+    pub time_ms: Option<u64>,
+}
+
+/// What a search found
+#[derive(Debug, Clone, Copy)]
+pub struct SearchResult {
+    /// `None` only when the side to move has no legal move
+    pub best_move: Option<Move>,
+    /// Centipawns from the point of view of the side to move
+    pub score: i32,
+    /// The last fully searched depth
+    pub depth: u32,
+    /// Positions visited
+    pub nodes: u64,
+    /// How long the search took
+    pub time: Duration,
+    /// How full the transposition table got, in permille
+    pub hashfull: u32,
+}
+
+impl SearchLimits {
+    /// Searching `depth` plies deep
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+}
+
+impl SearchResult {
+    /// Moves until mate, negative when the side to move gets mated
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score.abs() < MATE_SCORE - MAX_DEPTH as i32 {
+            return None;
+        }
+        let plies = MATE_SCORE - self.score.abs();
+        Some((plies + 1) / 2 * self.score.signum())
+    }
+}
+
+/// How long to think with `remaining` time on the clock, in milliseconds
+pub fn time_budget(remaining: Duration, increment: Duration) -> u64 {
+    (remaining / 20 + increment / 2).as_millis() as u64
+}
+
+/// Finds the best move for the side to move within `limits`
+pub fn search(board: &ChessBoard, limits: SearchLimits) -> SearchResult {
+    search_with_stop(board, limits, &AtomicBool::new(false))
+}
+
+/// Like [`search`], also stopping early once `stop` gets set
+pub fn search_with_stop(
+    board: &ChessBoard,
+    limits: SearchLimits,
+    stop: &AtomicBool,
+) -> SearchResult {
+    search_with_table(
+        board,
+        limits,
+        stop,
+        &TranspositionTable::default(),
+        &Evaluator::default(),
+        1,
+        |_| (),
+    )
+}
+
+/// Like [`search_with_stop`], scoring positions with `evaluator` and keeping
+/// what it learns in `table` for the next searches of the game, and calling
+/// `progress` with the result so far after every depth the main thread
+/// completes.
+///
+/// With more than one thread, helpers search the same position on their own
+/// copy of the board until the main thread is done, sharing what they find
+/// through the table (Lazy SMP). Every other helper starts a ply deeper so
+/// they don't all follow the main thread; the move played and the depth
+/// reported are the main thread's, the nodes those of every thread
+pub fn search_with_table(
+    board: &ChessBoard,
+    limits: SearchLimits,
+    stop: &AtomicBool,
+    table: &TranspositionTable,
+    evaluator: &Evaluator,
+    threads: usize,
+    progress: impl FnMut(&SearchResult),
+) -> SearchResult {
+    table.new_search();
+    let main_done = AtomicBool::new(false);
+    let nodes = AtomicU64::new(0);
+    thread::scope(|scope| {
+        let helpers: Vec<_> = (1..threads.clamp(1, MAX_THREADS))
+            .map(|id| {
+                let helper_limits = SearchLimits {
+                    depth: limits.depth,
+                    ..Default::default()
+                };
+                let mut helper = Searcher::new(
+                    board.clone(),
+                    helper_limits,
+                    &main_done,
+                    table,
+                    evaluator,
+                    &nodes,
+                );
+                helper.first_depth = 1 + id as u32 % 2;
+                scope.spawn(move || helper.iterative_deepening(|_| ()))
+            })
+            .collect();
+
+        let mut result = Searcher::new(board.clone(), limits, stop, table, evaluator, &nodes)
+            .iterative_deepening(progress);
+        main_done.store(true, Ordering::Relaxed);
+        for helper in helpers {
+            result.nodes += helper.join().unwrap().nodes;
+        }
+        result.hashfull = table.hashfull();
+        result
+    })
+}
+
+/// Nodes a thread counts before adding them to the count of every thread
+const NODE_BATCH: u64 = 1024;
+
+struct Searcher<'a> {
+    board: ChessBoard,
+    limits: SearchLimits,
+    stop: &'a AtomicBool,
+    table: &'a TranspositionTable,
+    evaluator: &'a Evaluator,
+    /// The accumulators of the network of the evaluator, if it has one
+    nnue: Option<Accumulators>,
+    start: Instant,
+    nodes: u64,
+    /// The nodes of every thread, counted in batches of [`NODE_BATCH`]
+    shared_nodes: &'a AtomicU64,
+    stopped: bool,
+    completed_depth: u32,
+    /// The depth iterative deepening starts from, past 1 for some helpers
+    first_depth: u32,
+    root_best: Option<Move>,
+}
+
+/// The score of a game that ended `ply` moves from the root, for the side to move
+fn outcome_score(board: &ChessBoard, outcome: GameStatus, ply: u32) -> i32 {
+    match outcome.winner() {
+        Some(winner) if winner == board.get_turn() => MATE_SCORE - ply as i32,
+        Some(_) => -MATE_SCORE + ply as i32,
+        None => 0,
+    }
+}
+
+/// Mate scores count from the root in the search but from the position in
+/// the table, so they stay right wherever the position comes up again
+fn score_to_table(score: i32, ply: u32) -> i32 {
+    match score {
+        score if score >= MATE_SCORE - MAX_DEPTH as i32 => score + ply as i32,
+        score if score <= -MATE_SCORE + MAX_DEPTH as i32 => score - ply as i32,
+        score => score,
+    }
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+    match score {
+        score if score >= MATE_SCORE - MAX_DEPTH as i32 => score - ply as i32,
+        score if score <= -MATE_SCORE + MAX_DEPTH as i32 => score + ply as i32,
+        score => score,
+    }
+}
+
+impl<'a> Searcher<'a> {
+    fn new(
+        board: ChessBoard,
+        limits: SearchLimits,
+        stop: &'a AtomicBool,
+        table: &'a TranspositionTable,
+        evaluator: &'a Evaluator,
+        shared_nodes: &'a AtomicU64,
+    ) -> Self {
+        let nnue = evaluator
+            .network
+            .clone()
+            .map(|network| Accumulators::new(network, &board));
+        Self {
+            board,
+            limits,
+            stop,
+            table,
+            evaluator,
+            nnue,
+            start: Instant::now(),
+            nodes: 0,
+            shared_nodes,
+            stopped: false,
+            completed_depth: 0,
+            first_depth: 1,
+            root_best: None,
+        }
+    }
+
+    fn iterative_deepening(&mut self, mut progress: impl FnMut(&SearchResult)) -> SearchResult {
+        let mut result = SearchResult {
+            best_move: self.board.generate_moves(None).first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+            hashfull: 0,
+        };
+
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).min(MAX_DEPTH);
+        for depth in self.first_depth..=max_depth {
+            let score = self.negamax(depth, 0, -MATE_SCORE, MATE_SCORE);
+            if self.stopped {
+                break;
+            }
+            result.best_move = self.root_best.or(result.best_move);
+            result.score = score;
+            result.depth = depth;
+            self.completed_depth = depth;
+            result.nodes = self.shared_nodes.load(Ordering::Relaxed) + self.nodes % NODE_BATCH;
+            result.time = self.start.elapsed();
+            result.hashfull = self.table.hashfull();
+            progress(&result);
+
+            if result.mate_in().is_some() {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result.time = self.start.elapsed();
+        result.hashfull = self.table.hashfull();
+        result
+    }
+
+    fn should_stop(&mut self) -> bool {
+        // never stop before finishing depth 1, so there is always a move to
+        // play; helpers starting deeper have no move to provide
+        if self.stopped || self.completed_depth == 0 && self.first_depth == 1 {
+            return self.stopped;
+        }
+        let out_of_nodes = self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes);
+        let out_of_time = self.nodes & 1023 == 0
+            && self
+                .limits
+                .time_ms
+                .is_some_and(|time| self.start.elapsed() >= Duration::from_millis(time));
+        self.stopped = out_of_nodes || out_of_time || self.stop.load(Ordering::Relaxed);
+        self.stopped
+    }
+
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(NODE_BATCH) {
+            self.shared_nodes.fetch_add(NODE_BATCH, Ordering::Relaxed);
+        }
+    }
+
+    /// Sorts captures first, most valuable victim and least valuable attacker first
+    fn order_moves(&self, moves: &mut [Move], first: Option<Move>) {
+        moves.sort_by_cached_key(|&mv| {
+            if Some(mv) == first {
+                return i32::MIN;
+            }
+            let victim = self.board[mv.to].map_or(0, |piece| piece_value(piece.piece_type));
+            let attacker = self.board[mv.from].map_or(0, |piece| piece_value(piece.piece_type));
+            let promotion = mv.promotion.map_or(0, piece_value);
+            let capture = if self.board.is_capture(mv) {
+                10 * victim.max(100) - attacker
+            } else {
+                0
+            };
+            -(capture + promotion)
+        });
+    }
+
+    fn negamax(&mut self, depth: u32, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.count_node();
+
+        if ply > 0 {
+            if let Some(outcome) = self.board.variant().rules().outcome(&self.board) {
+                return outcome_score(&self.board, outcome, ply);
+            }
+            if self.board.draw_by_rule().is_some() {
+                return 0;
+            }
+        }
+        if depth == 0 {
+            return self.quiescence(alpha, beta);
+        }
+
+        let mut moves = self.board.generate_moves(None);
+        if moves.is_empty() {
+            let outcome = self
+                .board
+                .variant()
+                .rules()
+                .outcome_without_moves(&self.board);
+            return outcome_score(&self.board, outcome, ply);
+        }
+
+        // a search of the position at least as deep may settle it, and
+        // otherwise tells which move to try first
+        let key = self.board.key();
+        let entry = self.table.probe(key);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => return score.clamp(alpha, beta),
+                Bound::Lower if score >= beta => return beta,
+                Bound::Upper if score <= alpha => return alpha,
+                _ => (),
+            }
+        }
+        let table_move = entry.and_then(|entry| entry.best_move);
+        let first = match ply {
+            0 => self.root_best.or(table_move),
+            _ => table_move,
+        };
+        self.order_moves(&mut moves, first);
+
+        let mut bound = Bound::Upper;
+        let mut best_move = None;
+        for mv in moves {
+            self.apply_move(mv);
+            let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha);
+            self.retract_move();
+
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                self.store(key, Some(mv), beta, depth, ply, Bound::Lower);
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                bound = Bound::Exact;
+                best_move = Some(mv);
+                if ply == 0 {
+                    self.root_best = Some(mv);
+                }
+            }
+        }
+        self.store(key, best_move, alpha, depth, ply, bound);
+        alpha
+    }
+
+    fn store(
+        &self,
+        key: u64,
+        best_move: Option<Move>,
+        score: i32,
+        depth: u32,
+        ply: u32,
+        bound: Bound,
+    ) {
+        self.table.store(
+            key,
+            TableEntry {
+                best_move,
+                score: score_to_table(score, ply),
+                depth,
+                bound,
+            },
+        );
+    }
+
+    /// Plays `mv`, the accumulators of the network following it
+    fn apply_move(&mut self, mv: Move) {
+        self.board.apply_move(mv);
+        if let Some(nnue) = &mut self.nnue {
+            nnue.push(mv, &self.board);
+        }
+    }
+
+    fn retract_move(&mut self) {
+        self.board.retract_move().unwrap();
+        if let Some(nnue) = &mut self.nnue {
+            nnue.pop();
+        }
+    }
+
+    /// Scores the position for the side to move, with the network of the
+    /// evaluator when it can play it
+    fn evaluate(&self) -> i32 {
+        self.nnue
+            .as_ref()
+            .and_then(|nnue| nnue.evaluate(&self.board))
+            .unwrap_or_else(|| evaluate_with(&self.board, &self.evaluator.weights))
+    }
+
+    /// Keeps searching captures so the evaluation isn't taken in the middle of a trade
+    fn quiescence(&mut self, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.count_node();
+
+        let stand_pat = self.evaluate();
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves = self.board.generate_moves(None);
+        moves.retain(|&mv| self.board.is_capture(mv) || mv.promotion.is_some());
+        self.order_moves(&mut moves, None);
+
+        for mv in moves {
+            self.apply_move(mv);
+            let score = -self.quiescence(-beta, -alpha);
+            self.retract_move();
+
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
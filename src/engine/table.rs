@@ -0,0 +1,241 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use super::{MATE_SCORE, MAX_DEPTH};
+use crate::chess::{ChessVec, Move, PieceType, ALL_PIECE_TYPES, MAX_WIDTH};
+
+/// Size of the table when none is asked for, in megabytes
+pub const DEFAULT_HASH_MB: usize = 16;
+/// The largest table that can be asked for, in megabytes
+pub const MAX_HASH_MB: usize = 4096;
+
+/// How the stored score relates to the true score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The true score is at least this high, a move having failed high
+    Lower,
+    /// The true score is at most this high, no move having reached alpha
+    Upper,
+}
+
+/// What a search learned about a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry {
+    /// The move that was best, or refuted the position
+    pub best_move: Option<Move>,
+    /// Centipawns from the point of view of the side to move; mates count
+    /// from the position itself rather than the root
+    pub score: i32,
+    /// Plies searched below the position
+    pub depth: u32,
+    #[allow(missing_docs)]
+    pub bound: Bound,
+}
+
+/// A slot of the table. The key is stored XORed with the data, so a slot
+/// torn by two threads writing at once reads as a different position
+/// instead of mixing both entries, without any lock
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// The slots a key may be stored in, filling a cache line
+#[derive(Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; BUCKET_SLOTS],
+}
+
+const BUCKET_SLOTS: usize = 4;
+
+/// A fixed-size hash table of searched positions, keyed by
+/// [`ChessBoard::key`](crate::chess::ChessBoard::key) and
+/// shared by the searches of a game
+pub struct TranspositionTable {
+    buckets: Box<[Bucket]>,
+    /// Bumped by every search, so entries of older ones get replaced first
+    generation: AtomicU8,
+}
+
+// bit layout of the data of a slot, from the lowest bit
+const MOVE_BITS: u32 = 22;
+const SCORE_BITS: u32 = 20;
+const DEPTH_BITS: u32 = 7;
+const BOUND_BITS: u32 = 2;
+const GENERATION_BITS: u32 = 6;
+const SCORE_SHIFT: u32 = MOVE_BITS;
+const DEPTH_SHIFT: u32 = SCORE_SHIFT + SCORE_BITS;
+const BOUND_SHIFT: u32 = DEPTH_SHIFT + DEPTH_BITS;
+const GENERATION_SHIFT: u32 = BOUND_SHIFT + BOUND_BITS;
+const SCORE_OFFSET: i32 = 1 << (SCORE_BITS - 1);
+
+const _: () = assert!(GENERATION_SHIFT + GENERATION_BITS <= 64);
+const _: () = assert!(MATE_SCORE < SCORE_OFFSET && MAX_DEPTH < 1 << DEPTH_BITS);
+
+fn mask(bits: u32) -> u64 {
+    (1 << bits) - 1
+}
+
+fn square_index(pos: ChessVec) -> u64 {
+    (pos.row as usize * MAX_WIDTH + pos.col as usize) as u64
+}
+
+fn index_square(index: u64) -> ChessVec {
+    let index = index as usize;
+    ChessVec::new((index % MAX_WIDTH) as i32, (index / MAX_WIDTH) as i32)
+}
+
+/// Packs a move in 22 bits: 7 for each square, 4 for the promotion and 4
+/// for the dropped piece, their type plus one; no move packs to 0
+fn pack_move(mv: Option<Move>) -> u64 {
+    let Some(mv) = mv else {
+        return 0;
+    };
+    let piece_code = |piece_type: Option<PieceType>| piece_type.map_or(0, |t| t as u64 + 1);
+    square_index(mv.from)
+        | square_index(mv.to) << 7
+        | piece_code(mv.promotion) << 14
+        | piece_code(mv.drop) << 18
+}
+
+fn unpack_move(code: u64) -> Option<Move> {
+    if code == 0 {
+        return None;
+    }
+    let piece_type = |code: u64| match code & mask(4) {
+        0 => None,
+        code => ALL_PIECE_TYPES.get(code as usize - 1).copied(),
+    };
+    Some(Move {
+        from: index_square(code & mask(7)),
+        to: index_square(code >> 7 & mask(7)),
+        promotion: piece_type(code >> 14),
+        drop: piece_type(code >> 18),
+    })
+}
+
+impl TranspositionTable {
+    /// A table taking about `megabytes` of memory, at least one bucket
+    pub fn new(megabytes: usize) -> Self {
+        let buckets = (megabytes.min(MAX_HASH_MB) << 20) / size_of::<Bucket>();
+        Self {
+            buckets: (0..buckets.max(1)).map(|_| Bucket::default()).collect(),
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Forgets every entry, as between two games
+    pub fn clear(&self) {
+        for slot in self.slots() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Starts a new search, whose entries take precedence over the older ones
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed) as u64 & mask(GENERATION_BITS)
+    }
+
+    fn slots(&self) -> impl Iterator<Item = &Slot> {
+        self.buckets.iter().flat_map(|bucket| &bucket.slots)
+    }
+
+    fn bucket(&self, key: u64) -> &Bucket {
+        &self.buckets[(key % self.buckets.len() as u64) as usize]
+    }
+
+    /// The entry of the position with Zobrist `key`, if it is stored
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        let data = self.bucket(key).slots.iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            (data != 0 && slot.key.load(Ordering::Relaxed) ^ data == key).then_some(data)
+        })?;
+        let bound = match data >> BOUND_SHIFT & mask(BOUND_BITS) {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        Some(TableEntry {
+            best_move: unpack_move(data & mask(MOVE_BITS)),
+            score: (data >> SCORE_SHIFT & mask(SCORE_BITS)) as i32 - SCORE_OFFSET,
+            depth: (data >> DEPTH_SHIFT & mask(DEPTH_BITS)) as u32,
+            bound,
+        })
+    }
+
+    /// Stores `entry` for the position with Zobrist `key` in its bucket,
+    /// over the entry of the same position, else an empty slot, else the
+    /// shallowest entry of an older search, else the shallowest entry of
+    /// this search unless it is deeper than `entry`
+    pub fn store(&self, key: u64, entry: TableEntry) {
+        let generation = self.generation();
+        let slots = self.bucket(key).slots.iter().map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            (slot, data, slot.key.load(Ordering::Relaxed) ^ data)
+        });
+        let (slot, old, old_key) = slots
+            .clone()
+            .find(|&(_, old, old_key)| old != 0 && old_key == key)
+            .or_else(|| {
+                slots.min_by_key(|&(_, old, _)| {
+                    let old_depth = old >> DEPTH_SHIFT & mask(DEPTH_BITS);
+                    let old_generation = old >> GENERATION_SHIFT & mask(GENERATION_BITS);
+                    (old != 0, old_generation == generation, old_depth)
+                })
+            })
+            .unwrap();
+        let old_depth = (old >> DEPTH_SHIFT & mask(DEPTH_BITS)) as u32;
+        let old_generation = old >> GENERATION_SHIFT & mask(GENERATION_BITS);
+        if old != 0 && old_key != key && old_generation == generation && old_depth > entry.depth {
+            return;
+        }
+
+        // keep the best move known when the new entry has none
+        let best_move = match entry.best_move {
+            None if old_key == key => old & mask(MOVE_BITS),
+            best_move => pack_move(best_move),
+        };
+        let bound = match entry.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        let score = (entry.score.clamp(-MATE_SCORE, MATE_SCORE) + SCORE_OFFSET) as u64;
+        let data = best_move
+            | score << SCORE_SHIFT
+            | (entry.depth.min(MAX_DEPTH) as u64) << DEPTH_SHIFT
+            | bound << BOUND_SHIFT
+            | generation << GENERATION_SHIFT;
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    /// How full the table is in permille, from the entries of the current
+    /// search among the first thousand slots, as UCI reports it
+    pub fn hashfull(&self) -> u32 {
+        let size = (self.buckets.len() * BUCKET_SLOTS).min(1000);
+        let used = self
+            .slots()
+            .take(size)
+            .filter(|slot| {
+                let data = slot.data.load(Ordering::Relaxed);
+                data != 0 && data >> GENERATION_SHIFT & mask(GENERATION_BITS) == self.generation()
+            })
+            .count();
+        (used * 1000 / size) as u32
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
+}
@@ -0,0 +1,504 @@
+use std::sync::Arc;
+
+use super::*;
+use crate::chess::{
+    ChessBoard, ChessColor, ChessError, Move, Variant, ALL_PIECE_TYPES, PAWN, QUEEN,
+};
+use crate::cvec;
+
+#[test]
+fn test_search_finds_mate() -> Result<(), ChessError> {
+    let board = ChessBoard::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")?;
+    let result = search(&board, SearchLimits::depth(3));
+
+    assert_eq!(result.best_move, Some(Move::new(cvec!("a1"), cvec!("a8"))));
+    assert_eq!(result.mate_in(), Some(1));
+
+    Ok(())
+}
+
+#[test]
+fn test_search_takes_hanging_piece() -> Result<(), ChessError> {
+    let board = ChessBoard::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1")?;
+    let result = search(&board, SearchLimits::depth(2));
+
+    assert_eq!(result.best_move, Some(Move::new(cvec!("d2"), cvec!("d5"))));
+    assert!(result.score > 0);
+
+    let limited = search(
+        &board,
+        SearchLimits {
+            nodes: Some(1),
+            ..Default::default()
+        },
+    );
+    assert_eq!(limited.depth, 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_transposition_table() -> Result<(), ChessError> {
+    // the same position reached by other move orders hashes the same
+    let mut board = ChessBoard::new();
+    let start_key = board.key();
+    for san in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+        board.make_move(board.parse_san(san)?)?;
+    }
+    assert_eq!(board.key(), start_key);
+    board.make_move(board.parse_san("e4")?)?;
+    let mut other =
+        ChessBoard::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")?;
+    other.make_move(other.parse_san("e4")?)?;
+    assert_eq!(board.key(), other.key());
+    other.swap_turn();
+    assert_ne!(board.key(), other.key());
+
+    // every number of pieces in a pocket hashes apart
+    let keys = ["[]", "[PP]", "[PPPP]", "[NN]", "[PPNN]", "[PPPPNN]"]
+        .iter()
+        .map(|pocket| {
+            let fen = format!("4k3/8/8/8/8/8/8/4K3{pocket} w - - 0 1");
+            Ok(ChessBoard::from_variant_fen(&fen, Variant::Crazyhouse)?.key())
+        })
+        .collect::<Result<Vec<_>, ChessError>>()?;
+    for (i, key) in keys.iter().enumerate() {
+        assert!(!keys[..i].contains(key));
+    }
+
+    // the key kept move after move is the one of the whole position, and
+    // comes back with the moves taken back
+    let positions = [
+        (
+            Variant::Standard,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ),
+        (
+            Variant::Standard,
+            "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1",
+        ),
+        (
+            Variant::Standard,
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1",
+        ),
+        (
+            Variant::Atomic,
+            "rnbqkb1r/ppp1pnpp/8/3pPp2/8/2N5/PPPP1PPP/R1BQKBNR w KQkq f6 0 4",
+        ),
+        (Variant::ThreeCheck, "4k3/8/8/8/8/8/3Q4/4K3 w - - 2+1 0 1"),
+        (
+            Variant::Crazyhouse,
+            "4k3/1P6/8/3n~4/8/8/8/4K3[Np] w - - 0 1",
+        ),
+        (Variant::Antichess, "4k3/P7/8/8/8/8/3q4/4K3 b - - 0 1"),
+    ];
+    for (variant, fen) in positions {
+        let mut board = ChessBoard::from_variant_fen(fen, variant)?;
+        let key = board.key();
+        check_keys(&mut board, 2);
+        assert_eq!(board.key(), key);
+    }
+    let mut board = ChessBoard::new_variant(Variant::Bughouse);
+    board.add_to_pocket(ChessColor::WHITE, QUEEN);
+    board.swap_turn();
+    assert_eq!(board.key(), board.zobrist_key());
+
+    let table = TranspositionTable::new(1);
+    let entry = TableEntry {
+        best_move: Some(Move::with_promotion(
+            cvec!("l9"),
+            cvec!("k10"),
+            crate::chess::CHANCELLOR,
+        )),
+        score: -MATE_SCORE + 3,
+        depth: 7,
+        bound: Bound::Lower,
+    };
+    table.store(start_key, entry);
+    assert_eq!(table.probe(start_key), Some(entry));
+    assert_eq!(table.probe(start_key ^ 1), None);
+    assert!(table.hashfull() <= 1);
+
+    // a drop without a best move keeps the one stored before
+    let drop = Move::new_drop(crate::chess::KNIGHT, cvec!("f3"));
+    table.store(
+        start_key ^ 1,
+        TableEntry {
+            best_move: Some(drop),
+            ..entry
+        },
+    );
+    table.store(
+        start_key ^ 1,
+        TableEntry {
+            best_move: None,
+            depth: 9,
+            ..entry
+        },
+    );
+    assert_eq!(table.probe(start_key ^ 1).unwrap().best_move, Some(drop));
+
+    // shallower entries sharing the bucket don't push out a deep one of the
+    // same search, but do push out the entries of older searches
+    let table = TranspositionTable::new(0);
+    table.store(1, entry);
+    let shallow = TableEntry { depth: 2, ..entry };
+    for key in 2..10 {
+        table.store(key, shallow);
+    }
+    assert_eq!(table.probe(1), Some(entry));
+    assert!(table.probe(9).is_some());
+    table.new_search();
+    for key in 10..14 {
+        table.store(key, shallow);
+    }
+    assert_eq!(table.probe(1), None);
+
+    // the table carries the search over from one move to the next
+    let board = ChessBoard::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")?;
+    let stop = std::sync::atomic::AtomicBool::new(false);
+    let table = TranspositionTable::new(1);
+    let first = search_with_table(
+        &board,
+        SearchLimits::depth(4),
+        &stop,
+        &table,
+        &Evaluator::default(),
+        1,
+        |_| (),
+    );
+    let again = search_with_table(
+        &board,
+        SearchLimits::depth(4),
+        &stop,
+        &table,
+        &Evaluator::default(),
+        1,
+        |_| (),
+    );
+    assert_eq!(first.best_move, again.best_move);
+    assert_eq!(again.mate_in(), Some(1));
+    assert!(again.nodes <= first.nodes);
+    let result = search_with_table(
+        &ChessBoard::new(),
+        SearchLimits::depth(4),
+        &stop,
+        &table,
+        &Evaluator::default(),
+        1,
+        |_| (),
+    );
+    assert!(result.hashfull > 0);
+    table.clear();
+    assert_eq!(table.hashfull(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_lazy_smp() -> Result<(), ChessError> {
+    let board = ChessBoard::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")?;
+    let table = TranspositionTable::new(1);
+    let stop = std::sync::atomic::AtomicBool::new(false);
+    let result = search_with_table(
+        &board,
+        SearchLimits::depth(3),
+        &stop,
+        &table,
+        &Evaluator::default(),
+        4,
+        |_| (),
+    );
+    assert_eq!(result.best_move, Some(Move::new(cvec!("a1"), cvec!("a8"))));
+    assert_eq!(result.mate_in(), Some(1));
+
+    // the main thread reports every depth it completes, with the nodes of
+    // every thread so far
+    let mut reports = Vec::new();
+    let result = search_with_table(
+        &ChessBoard::new(),
+        SearchLimits::depth(4),
+        &stop,
+        &table,
+        &Evaluator::default(),
+        2,
+        |result| reports.push((result.depth, result.nodes)),
+    );
+    assert_eq!(
+        reports.iter().map(|&(depth, _)| depth).collect::<Vec<_>>(),
+        [1, 2, 3, 4]
+    );
+    assert!(reports.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+    assert!(reports[3].1 <= result.nodes);
+
+    // helpers don't keep the main thread from stopping at its limits
+    let limits = SearchLimits {
+        time_ms: Some(50),
+        ..Default::default()
+    };
+    let result = search_with_table(
+        &ChessBoard::new(),
+        limits,
+        &stop,
+        &table,
+        &Evaluator::default(),
+        3,
+        |_| (),
+    );
+    assert!(result.best_move.is_some());
+    assert!(result.time.as_millis() < 1000);
+    Ok(())
+}
+
+#[test]
+fn test_tapered_eval() -> Result<(), ChessError> {
+    assert_eq!(evaluate(&ChessBoard::new()), 0);
+
+    // the same position with the colors swapped scores the same for the side to move
+    let board = ChessBoard::from_fen(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    )?;
+    let mirrored = ChessBoard::from_fen(
+        "r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1",
+    )?;
+    assert_eq!(evaluate(&board), evaluate(&mirrored));
+
+    let board = ChessBoard::from_fen("4k3/8/8/3P4/8/8/P4PP1/4K3 b - - 0 1")?;
+    let breakdown = evaluate_terms(&board, &DEFAULT_WEIGHTS);
+    assert_eq!(breakdown.phase, 0);
+    let [white, black] = breakdown.terms[EvalTerm::PassedPawns as usize];
+    assert!(white.eg > 0 && black == Score::default());
+    let [white, _] = breakdown.terms[EvalTerm::IsolatedPawns as usize];
+    assert_eq!(white, DEFAULT_WEIGHTS.isolated_pawn * 2);
+    assert_eq!(evaluate(&board), -breakdown.score());
+
+    // weights are plain data, for a tuner to change
+    let weights = EvalWeights {
+        bishop_pair: Score::default(),
+        ..EvalWeights::default()
+    };
+    let board = ChessBoard::from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1")?;
+    let [white, _] = evaluate_terms(&board, &weights).terms[EvalTerm::BishopPair as usize];
+    assert_eq!(white, Score::default());
+    assert!(evaluate(&board) > evaluate_with(&board, &weights));
+    Ok(())
+}
+
+#[test]
+fn test_tuner() -> Result<(), ChessError> {
+    // weights survive a trip through their file format
+    let mut weights = EvalWeights::default();
+    weights.piece_squares[PAWN as usize][20] = Score { mg: 7, eg: -3 };
+    assert_eq!(weights.to_string().parse::<EvalWeights>()?, weights);
+    assert_eq!(weights.scores_mut().len(), WEIGHT_COUNT);
+
+    // every weight has its own index, the one of its score in the file order
+    let mut all = Vec::new();
+    for piece_type in ALL_PIECE_TYPES {
+        all.extend([Weight::Material(piece_type), Weight::Mobility(piece_type)]);
+    }
+    for table in 0..weights.piece_squares.len() {
+        all.extend((0..64).map(|square| Weight::PieceSquare(table, square)));
+    }
+    all.extend((0..weights.passed_pawn.len()).map(Weight::PassedPawn));
+    all.extend([
+        Weight::IsolatedPawn,
+        Weight::DoubledPawn,
+        Weight::BishopPair,
+        Weight::RookOpenFile,
+        Weight::RookSemiOpenFile,
+        Weight::KingShelter,
+    ]);
+    let mut indices: Vec<usize> = all.iter().map(|weight| weight.index()).collect();
+    indices.sort_unstable();
+    indices.dedup();
+    assert_eq!(indices.len(), WEIGHT_COUNT);
+    assert!(indices.iter().all(|&index| index < WEIGHT_COUNT));
+    let mut numbered = EvalWeights::default();
+    for (i, score) in numbered.scores_mut().into_iter().enumerate() {
+        score.mg = i as i32;
+    }
+    assert!(all
+        .iter()
+        .all(|&weight| numbered.get(weight).mg == weight.index() as i32));
+    assert_eq!(
+        "# partial\nbishop_pair 40/60\n"
+            .parse::<EvalWeights>()?
+            .bishop_pair,
+        Score { mg: 40, eg: 60 }
+    );
+    assert_eq!(
+        "bishop_pair 40/60 1/1".parse::<EvalWeights>(),
+        Err(ChessError::InvalidWeights {
+            line: 1,
+            reason: "wrong number of mg/eg pairs"
+        })
+    );
+
+    let lines = [
+        "4k3/8/8/3P4/8/8/P4PP1/4K3 b - - 0 1 1-0",
+        "4k3/pp3pp1/8/8/8/8/6P1/4K3 w - - c9 \"0-1\";",
+        "8/8/4k3/8/8/3K4/8/8 w - - 0 1 [0.5]",
+        "4k3/8/8/3P4/8/8/P4PP1/4K3 b - - 0 1 1/2-1/2",
+        "4k3/pp3pp1/8/8/8/8/6P1/4K3 w - - 0 1 0.5",
+    ];
+    let positions = lines
+        .iter()
+        .map(|line| TuningPosition::parse(line, Variant::Standard))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(positions[1].result, 0.0);
+    assert!(TuningPosition::parse("8/8/4k3/8/8/3K4/8/8 w - - 0 1 2-0", Variant::Standard).is_err());
+
+    let mut tuner = Tuner::new(&positions);
+    tuner.fit_scale(&weights);
+    let tuned = tuner.tune(&weights, 20, |_, _| ());
+    assert!(tuner.error(&tuned) < tuner.error(&weights));
+
+    // the search scores positions with the weights it is given
+    let board = &positions[0].board;
+    let mut weights = EvalWeights::default();
+    weights.material[PAWN as usize] = Score { mg: 500, eg: 500 };
+    let evaluator = Evaluator {
+        weights: Arc::new(weights),
+        ..Default::default()
+    };
+    let stop = std::sync::atomic::AtomicBool::new(false);
+    let table = TranspositionTable::new(1);
+    let result = search_with_table(
+        board,
+        SearchLimits::depth(1),
+        &stop,
+        &table,
+        &evaluator,
+        1,
+        |_| (),
+    );
+    assert_ne!(result.score, search(board, SearchLimits::depth(1)).score);
+    Ok(())
+}
+
+/// Plays every line `depth` plies deep from `board`, checking that the
+/// accumulators score every position as a fresh evaluation does, as long as
+/// the network could play every position of the line
+fn check_accumulators(
+    board: &mut ChessBoard,
+    nnue: &mut Accumulators,
+    network: &Network,
+    depth: u32,
+    playable: bool,
+) {
+    let fresh = network.evaluate(board);
+    let playable = playable && fresh.is_some();
+    let expected = if playable { fresh } else { None };
+    assert_eq!(nnue.evaluate(board), expected, "{}", board.to_fen());
+    if depth == 0 {
+        return;
+    }
+    for mv in board.generate_moves(None) {
+        board.apply_move(mv);
+        nnue.push(mv, board);
+        check_accumulators(board, nnue, network, depth - 1, playable);
+        board.retract_move().unwrap();
+        nnue.pop();
+    }
+}
+
+/// Plays every line `depth` moves deep, checking the key of every position
+/// reached against the one computed from scratch
+fn check_keys(board: &mut ChessBoard, depth: u32) {
+    assert_eq!(board.key(), board.zobrist_key(), "{}", board.to_fen());
+    if depth == 0 {
+        return;
+    }
+    for mv in board.generate_moves(None) {
+        board.apply_move(mv);
+        check_keys(board, depth - 1);
+        board.retract_move().unwrap();
+    }
+}
+
+/// A network file of small pseudo-random weights
+fn random_network_bytes(hidden: u32) -> Vec<u8> {
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % 129) as i16 - 64
+    };
+    let mut bytes = b"CRNN".to_vec();
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(hidden.to_le_bytes());
+    let weights = (HALFKP_FEATURES + 1 + 2) * hidden as usize;
+    for _ in 0..weights {
+        bytes.extend(next().to_le_bytes());
+    }
+    bytes.extend(25i32.to_le_bytes());
+    bytes
+}
+
+#[test]
+fn test_nnue() -> Result<(), ChessError> {
+    let bytes = random_network_bytes(32);
+    let network = Network::from_bytes(&bytes)?;
+    assert_eq!(network.hidden(), 32);
+    assert_eq!(network.to_bytes(), bytes);
+    assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Network::from_bytes(b"NNUE").is_err());
+    let network = Arc::new(network);
+
+    // the accumulators follow every move and take-back the way a fresh
+    // evaluation sees the board: castling, en passant, promotions, king
+    // moves, explosions and kings taken
+    for (fen, variant) in [
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            Variant::Standard,
+        ),
+        ("n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1", Variant::Standard),
+        (
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            Variant::Standard,
+        ),
+        (
+            "1r2k1r1/pppppppp/8/8/8/8/PPPPPPPP/1R2K1R1 w GBgb - 0 1",
+            Variant::Standard,
+        ),
+        (
+            "rnbqkb1r/ppp1pnpp/8/3pPp2/8/2N5/PPPP1PPP/R1BQKBNR w KQkq f6 0 4",
+            Variant::Atomic,
+        ),
+        ("4k3/P7/8/8/8/8/3q4/4K3 b - - 0 1", Variant::Antichess),
+    ] {
+        let mut board = ChessBoard::from_variant_fen(fen, variant)?;
+        let mut nnue = Accumulators::new(Arc::clone(&network), &board);
+        check_accumulators(&mut board, &mut nnue, &network, 2, true);
+    }
+
+    // the search evaluates with the network, and boards it can't play fall
+    // back on the handcrafted evaluation
+    let evaluator = Evaluator {
+        network: Some(Arc::clone(&network)),
+        ..Default::default()
+    };
+    let stop = std::sync::atomic::AtomicBool::new(false);
+    let table = TranspositionTable::new(1);
+    let board = ChessBoard::new();
+    let result = search_with_table(
+        &board,
+        SearchLimits::depth(3),
+        &stop,
+        &table,
+        &evaluator,
+        2,
+        |_| (),
+    );
+    assert!(result.best_move.is_some());
+    for variant in [Variant::Capablanca, Variant::Crazyhouse, Variant::Horde] {
+        let board = ChessBoard::new_variant(variant);
+        assert_eq!(
+            Accumulators::new(Arc::clone(&network), &board).evaluate(&board),
+            None
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use std::f64::consts::LN_10;
+
+use super::{visit_terms, EvalWeights, Score, TOTAL_PHASE, WEIGHT_COUNT};
+use crate::chess::{ChessBoard, ChessColor, ChessError, Variant};
+
+/// A position labelled with the result of the game it was played in
+#[derive(Clone)]
+pub struct TuningPosition {
+    #[allow(missing_docs)]
+    pub board: ChessBoard,
+    /// 1 when white won, 0.5 for a draw and 0 when black won
+    pub result: f64,
+}
+
+impl TuningPosition {
+    /// Reads a FEN followed by a result, as `1-0`, `1/2-1/2` or `0-1` or as
+    /// `1.0`, `0.5` or `0.0`, possibly quoted or in brackets. An EPD `c9`
+    /// opcode before the result is skipped
+    pub fn parse(line: &str, variant: Variant) -> Result<Self, ChessError> {
+        let line = line.trim().trim_end_matches(';');
+        let (fen, result) = line.rsplit_once(char::is_whitespace).unwrap_or(("", line));
+        let result = match result.trim_matches(['"', '[', ']', '(', ')']) {
+            "1-0" | "1.0" | "1" => 1.0,
+            "1/2-1/2" | "0.5" => 0.5,
+            "0-1" | "0.0" | "0" => 0.0,
+            _ => return Err(ChessError::InvalidResult(result.to_string())),
+        };
+        let fen = fen.trim_end();
+        let fen = fen.strip_suffix(" c9").unwrap_or(fen);
+        Ok(Self {
+            board: ChessBoard::from_variant_fen(fen, variant)?,
+            result,
+        })
+    }
+}
+
+/// The share of the games a score in centipawns from the point of view of
+/// white should win for white, `scale` stretching it
+fn win_rate(score: f64, scale: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scale * score / 400.0))
+}
+
+/// A position as the weights the evaluation sums up, white's counted up and
+/// black's down
+struct Features {
+    weights: Vec<(usize, i32)>,
+    phase: f64,
+    result: f64,
+}
+
+impl Features {
+    fn new(position: &TuningPosition) -> Self {
+        let mut counts = vec![0; WEIGHT_COUNT];
+        let phase = visit_terms(&position.board, |_, color, weight, count| {
+            counts[weight.index()] += match color {
+                ChessColor::WHITE => count,
+                ChessColor::BLACK => -count,
+            };
+        });
+        Self {
+            weights: counts
+                .into_iter()
+                .enumerate()
+                .filter(|&(_, count)| count != 0)
+                .collect(),
+            phase: phase as f64 / TOTAL_PHASE as f64,
+            result: position.result,
+        }
+    }
+
+    /// The evaluation from the point of view of white, untruncated
+    fn evaluate(&self, params: &[[f64; 2]]) -> f64 {
+        let (mg, eg) = self
+            .weights
+            .iter()
+            .fold((0.0, 0.0), |(mg, eg), &(index, count)| {
+                let [weight_mg, weight_eg] = params[index];
+                (mg + weight_mg * count as f64, eg + weight_eg * count as f64)
+            });
+        mg * self.phase + eg * (1.0 - self.phase)
+    }
+}
+
+/// Tunes evaluation weights to the results of the games a set of positions
+/// were played in, the Texel way: by minimising the mean squared difference
+/// between the results and the win rates the evaluations predict
+pub struct Tuner {
+    positions: Vec<Features>,
+    /// The stretch of the curve from scores to win rates
+    pub scale: f64,
+}
+
+impl Tuner {
+    #[allow(missing_docs)]
+    pub fn new(positions: &[TuningPosition]) -> Self {
+        Self {
+            positions: positions.iter().map(Features::new).collect(),
+            scale: 1.0,
+        }
+    }
+
+    /// The mean squared error of the weights over the positions
+    pub fn error(&self, weights: &EvalWeights) -> f64 {
+        self.error_of(&params(weights))
+    }
+
+    fn error_of(&self, params: &[[f64; 2]]) -> f64 {
+        let total: f64 = self
+            .positions
+            .iter()
+            .map(|position| {
+                let predicted = win_rate(position.evaluate(params), self.scale);
+                (position.result - predicted).powi(2)
+            })
+            .sum();
+        total / self.positions.len().max(1) as f64
+    }
+
+    /// Sets the scale with which `weights` have the least error, so tuning
+    /// doesn't just rescale them
+    pub fn fit_scale(&mut self, weights: &EvalWeights) -> f64 {
+        let params = params(weights);
+        let (mut low, mut high) = (0.0, 10.0);
+        for _ in 0..100 {
+            let third = (high - low) / 3.0;
+            self.scale = low + third;
+            let left = self.error_of(&params);
+            self.scale = high - third;
+            if left < self.error_of(&params) {
+                high -= third;
+            } else {
+                low += third;
+            }
+        }
+        self.scale = (low + high) / 2.0;
+        self.scale
+    }
+
+    /// Runs `epochs` steps of gradient descent from `weights`, with Adam
+    /// step sizes, calling `progress` with the error after each one
+    pub fn tune(
+        &self,
+        weights: &EvalWeights,
+        epochs: usize,
+        mut progress: impl FnMut(usize, f64),
+    ) -> EvalWeights {
+        const LEARNING_RATE: f64 = 1.0;
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+
+        let mut params = params(weights);
+        let mut momentum = vec![[0.0; 2]; WEIGHT_COUNT];
+        let mut velocity = vec![[0.0; 2]; WEIGHT_COUNT];
+        for epoch in 1..=epochs {
+            let mut gradient = vec![[0.0; 2]; WEIGHT_COUNT];
+            for position in &self.positions {
+                let predicted = win_rate(position.evaluate(&params), self.scale);
+                let slope = -2.0
+                    * (position.result - predicted)
+                    * predicted
+                    * (1.0 - predicted)
+                    * self.scale
+                    * LN_10
+                    / 400.0;
+                for &(index, count) in &position.weights {
+                    let count = count as f64 * slope;
+                    gradient[index][0] += count * position.phase;
+                    gradient[index][1] += count * (1.0 - position.phase);
+                }
+            }
+
+            let samples = self.positions.len().max(1) as f64;
+            for (index, param) in params.iter_mut().enumerate() {
+                for phase in 0..2 {
+                    let gradient = gradient[index][phase] / samples;
+                    let m = &mut momentum[index][phase];
+                    let v = &mut velocity[index][phase];
+                    *m = BETA1 * *m + (1.0 - BETA1) * gradient;
+                    *v = BETA2 * *v + (1.0 - BETA2) * gradient * gradient;
+                    let m = *m / (1.0 - BETA1.powi(epoch as i32));
+                    let v = *v / (1.0 - BETA2.powi(epoch as i32));
+                    param[phase] -= LEARNING_RATE * m / (v.sqrt() + 1e-8);
+                }
+            }
+            progress(epoch, self.error_of(&params));
+        }
+
+        let mut tuned = weights.clone();
+        for (score, [mg, eg]) in tuned.scores_mut().into_iter().zip(params) {
+            *score = Score {
+                mg: mg.round() as i32,
+                eg: eg.round() as i32,
+            };
+        }
+        tuned
+    }
+}
+
+/// The weights as floats, to take fractional steps
+fn params(weights: &EvalWeights) -> Vec<[f64; 2]> {
+    weights
+        .clone()
+        .scores_mut()
+        .into_iter()
+        .map(|score| [score.mg as f64, score.eg as f64])
+        .collect()
+}
@@ -0,0 +1,30 @@
+//! Chess rules, notation and an engine, with a terminal and web front end.
+//!
+//! The [`chess`] module holds the rules: a [`chess::ChessBoard`] plays and
+//! undoes [`chess::Move`]s, reads and writes FEN, SAN and PGN, and tells the
+//! [`chess::GameStatus`] of the game. The [`engine`] searches a board for its
+//! best move, and [`uci`] exposes it to chess GUIs.
+//!
+//! ```
+//! use chess_rs::chess::{ChessBoard, GameStatus};
+//!
+//! let mut board = ChessBoard::new();
+//! for san in ["f3", "e5", "g4", "Qh4#"] {
+//!     let mv = board.parse_san(san)?;
+//!     board.make_move(mv)?;
+//! }
+//! assert!(matches!(board.status(), GameStatus::Checkmate { .. }));
+//! # Ok::<(), chess_rs::chess::ChessError>(())
+//! ```
+//!
+//! The `web` feature adds the game server, and the `cli` feature the
+//! `chess-rs` command; both are enabled by default.
+
+#![allow(clippy::needless_return)]
+#![warn(missing_docs)]
+
+pub mod chess;
+pub mod engine;
+pub mod uci;
+#[cfg(feature = "web")]
+pub mod web;
@@ -1,9 +1,545 @@
-mod chess;
+use clap::{builder::PossibleValuesParser, value_parser, Arg, ArgAction, ArgMatches, Command};
+use std::{process::exit, sync::Arc, thread::available_parallelism};
+
+use chess_rs::{
+    chess::{
+        ChessBoard, ChessColor, ChessError, Odds, PgnTags, PositionBuilder, Variant,
+        CHESS960_POSITIONS, ODDS, VARIANTS,
+    },
+    engine::{
+        EvalWeights, Evaluator, Network, SearchLimits, DEFAULT_HASH_MB, MAX_DEPTH, MAX_HASH_MB,
+        MAX_THREADS,
+    },
+    uci,
+};
+
 mod cli;
-mod web;
 
-#[tokio::main]
-async fn main() {
-    // web::start_web_server().await;
-    cli::start_cli_game();
+fn depth_arg() -> Arg {
+    Arg::new("depth")
+        .long("depth")
+        .value_name("PLIES")
+        .value_parser(value_parser!(u32).range(1..))
+        .help("Search depth of the engine")
+}
+
+fn time_arg() -> Arg {
+    Arg::new("time")
+        .long("time")
+        .value_name("MS")
+        .value_parser(value_parser!(u64))
+        .help("Thinking time of the engine in milliseconds")
+}
+
+fn play_as_arg() -> Arg {
+    Arg::new("play-as")
+        .long("play-as")
+        .value_name("COLOR")
+        .value_parser(PossibleValuesParser::new(["white", "black"]))
+        .help("Play against the engine with this color")
+}
+
+fn weights_arg() -> Arg {
+    Arg::new("weights")
+        .long("weights")
+        .value_name("FILE")
+        .help("Evaluation weights written by `tune`, the built-in ones by default")
+}
+
+fn nnue_arg() -> Arg {
+    Arg::new("nnue")
+        .long("nnue")
+        .value_name("FILE")
+        .help("Network for the engine to evaluate with instead of the handcrafted evaluation")
+}
+
+fn variant_arg() -> Arg {
+    let names: Vec<String> = VARIANTS.iter().map(Variant::to_string).collect();
+    Arg::new("variant")
+        .long("variant")
+        .value_parser(|name: &str| name.parse::<Variant>())
+        .default_value("standard")
+        .help(format!("Rules to play with: {}", names.join(", ")))
+}
+
+/// Reads a Chess960 position number, `random` giving none
+fn chess960_index(text: &str) -> Result<Option<u16>, String> {
+    if text == "random" {
+        return Ok(None);
+    }
+    match text.parse() {
+        Ok(index) if index < CHESS960_POSITIONS => Ok(Some(index)),
+        _ => Err(format!(
+            "expected a number below {CHESS960_POSITIONS} or random"
+        )),
+    }
+}
+
+fn command() -> Command {
+    let command = Command::new("chess-rs")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Chess in the terminal, in the browser, and for chess GUIs")
+        .subcommand(
+            Command::new("play")
+                .about("Play a game in the terminal (the default)")
+                .arg(play_as_arg())
+                .arg(
+                    Arg::new("chess960")
+                        .long("chess960")
+                        .value_name("INDEX")
+                        .num_args(0..=1)
+                        .default_missing_value("random")
+                        .value_parser(chess960_index)
+                        .help("Start from a Chess960 position, picked at random without INDEX"),
+                )
+                .arg({
+                    let names: Vec<String> = ODDS.iter().map(Odds::to_string).collect();
+                    Arg::new("odds")
+                        .long("odds")
+                        .value_parser(|name: &str| name.parse::<Odds>())
+                        .conflicts_with("chess960")
+                        .help(format!("Start a handicap game: {}", names.join(", ")))
+                })
+                .arg(
+                    Arg::new("odds-by")
+                        .long("odds-by")
+                        .value_name("COLOR")
+                        .value_parser(PossibleValuesParser::new(["white", "black"]))
+                        .requires("odds")
+                        .help("Side giving the odds, the engine or else white by default"),
+                )
+                .arg(variant_arg())
+                .arg(depth_arg())
+                .arg(time_arg())
+                .arg(weights_arg())
+                .arg(nnue_arg()),
+        )
+        .subcommand(
+            Command::new("setup")
+                .about("Set up a position piece by piece, then play from it")
+                .arg(
+                    Arg::new("fen")
+                        .long("fen")
+                        .help("Position to start editing instead of an empty board"),
+                )
+                .arg(variant_arg())
+                .arg(play_as_arg())
+                .arg(depth_arg())
+                .arg(time_arg()),
+        )
+        .subcommand(
+            Command::new("perft")
+                .about("Count the leaf nodes of the move tree")
+                .arg(
+                    Arg::new("depth")
+                        .required(true)
+                        .value_parser(value_parser!(u32))
+                        .help("Number of plies"),
+                )
+                .arg(
+                    Arg::new("fen")
+                        .long("fen")
+                        .help("Position to start from instead of the initial one"),
+                )
+                .arg(
+                    Arg::new("divide")
+                        .long("divide")
+                        .action(ArgAction::SetTrue)
+                        .help("Also print the count of every root move"),
+                )
+                .arg(variant_arg()),
+        )
+        .subcommand(
+            Command::new("analyze")
+                .about("Let the engine evaluate a position")
+                .arg(
+                    Arg::new("position")
+                        .required(true)
+                        .help("FEN or PGN, a file containing either, or - for stdin"),
+                )
+                .arg(variant_arg())
+                .arg(depth_arg())
+                .arg(time_arg())
+                .arg(weights_arg())
+                .arg(nnue_arg()),
+        )
+        .subcommand(
+            Command::new("eval")
+                .about("Break the static evaluation of a position down by term")
+                .arg(
+                    Arg::new("position")
+                        .required(true)
+                        .help("FEN or PGN, a file containing either, or - for stdin"),
+                )
+                .arg(variant_arg())
+                .arg(weights_arg())
+                .arg(nnue_arg()),
+        )
+        .subcommand(
+            Command::new("tune")
+                .about("Tune the evaluation weights to the results of games")
+                .arg(
+                    Arg::new("data")
+                        .required(true)
+                        .help("File of a FEN and a game result per line, or - for stdin"),
+                )
+                .arg(variant_arg())
+                .arg(weights_arg().help("Weights to start from, the built-in ones by default"))
+                .arg(
+                    Arg::new("epochs")
+                        .long("epochs")
+                        .value_parser(value_parser!(u32).range(1..))
+                        .default_value("200")
+                        .help("Steps of gradient descent to take"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .default_value("weights.txt")
+                        .help("File to write the tuned weights to"),
+                ),
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Convert a game between notations")
+                .arg(
+                    Arg::new("position")
+                        .required(true)
+                        .help("FEN or PGN, a file containing either, or - for stdin"),
+                )
+                .arg(variant_arg())
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_parser(PossibleValuesParser::new(cli::ConvertFormat::NAMES))
+                        .default_value("pgn")
+                        .help("Notation to write"),
+                ),
+        )
+        .subcommand(
+            Command::new("bench")
+                .about(
+                    "Time the engine to a depth on a set of positions, with more and more threads",
+                )
+                .arg(
+                    Arg::new("depth")
+                        .long("depth")
+                        .value_name("PLIES")
+                        .value_parser(value_parser!(u32).range(1..=MAX_DEPTH as i64))
+                        .default_value("6")
+                        .help("Depth to search every position to"),
+                )
+                .arg(
+                    Arg::new("threads")
+                        .long("threads")
+                        .value_parser(value_parser!(u32).range(1..=MAX_THREADS as i64))
+                        .help("Most threads to search with, every core by default"),
+                )
+                .arg(
+                    Arg::new("hash")
+                        .long("hash")
+                        .value_name("MB")
+                        .value_parser(value_parser!(u32).range(1..=MAX_HASH_MB as i64))
+                        .help(format!(
+                            "Size of the transposition table, {DEFAULT_HASH_MB} MB by default"
+                        )),
+                ),
+        )
+        .subcommand(Command::new("uci").about("Talk the Universal Chess Interface on stdin"));
+
+    #[cfg(feature = "web")]
+    let command = command.subcommand(serve::command());
+    command
+}
+
+/// The engine limits given with `--depth` and `--time`, `default_depth` deep otherwise
+fn search_limits(matches: &ArgMatches, default_depth: u32) -> SearchLimits {
+    let mut limits = SearchLimits {
+        depth: matches.get_one::<u32>("depth").copied(),
+        nodes: None,
+        time_ms: matches.get_one::<u64>("time").copied(),
+    };
+    if limits == SearchLimits::default() {
+        limits.depth = Some(default_depth);
+    }
+    limits
+}
+
+/// The engine playing against the color given with `--play-as`, if any
+fn engine_opponent(matches: &ArgMatches) -> Option<cli::EngineOpponent> {
+    matches
+        .get_one::<String>("play-as")
+        .map(|color| cli::EngineOpponent {
+            color: match color.as_str() {
+                "white" => ChessColor::BLACK,
+                _ => ChessColor::WHITE,
+            },
+            limits: search_limits(matches, 4),
+        })
+}
+
+fn read_position_or_exit(matches: &ArgMatches) -> (ChessBoard, PgnTags) {
+    let input = matches.get_one::<String>("position").unwrap();
+    let variant = *matches.get_one::<Variant>("variant").unwrap();
+    cli::read_position(input, variant).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    })
+}
+
+/// The weights given with `--weights`, the built-in ones otherwise
+fn read_weights_or_exit(matches: &ArgMatches) -> EvalWeights {
+    match matches.get_one::<String>("weights") {
+        Some(path) => cli::read_weights(path).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            exit(1);
+        }),
+        None => EvalWeights::default(),
+    }
+}
+
+/// What the engine evaluates with, given `--weights` and `--nnue`
+fn evaluator_or_exit(matches: &ArgMatches) -> Evaluator {
+    Evaluator {
+        weights: Arc::new(read_weights_or_exit(matches)),
+        network: read_network_or_exit(matches),
+    }
+}
+
+/// The network given with `--nnue`, if any
+fn read_network_or_exit(matches: &ArgMatches) -> Option<Arc<Network>> {
+    let path = matches.get_one::<String>("nnue")?;
+    let network = cli::read_network(path).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    });
+    Some(Arc::new(network))
+}
+
+fn main() {
+    match command().get_matches().subcommand() {
+        #[cfg(feature = "web")]
+        Some(("serve", matches)) => serve::run(matches),
+        Some(("perft", matches)) => {
+            let variant = *matches.get_one::<Variant>("variant").unwrap();
+            let board = match matches.get_one::<String>("fen") {
+                Some(fen) => ChessBoard::from_variant_fen(fen, variant).unwrap_or_else(|e| {
+                    eprintln!("[Error]: {e}");
+                    exit(1);
+                }),
+                None => ChessBoard::new_variant(variant),
+            };
+            let depth = *matches.get_one::<u32>("depth").unwrap();
+            cli::run_perft(board, depth, matches.get_flag("divide"));
+        }
+        Some(("analyze", matches)) => {
+            let (board, _) = read_position_or_exit(matches);
+            cli::run_analyze(
+                &board,
+                search_limits(matches, 6),
+                &evaluator_or_exit(matches),
+            );
+        }
+        Some(("eval", matches)) => {
+            let (board, _) = read_position_or_exit(matches);
+            cli::run_eval(
+                &board,
+                &read_weights_or_exit(matches),
+                read_network_or_exit(matches).as_deref(),
+            );
+        }
+        Some(("tune", matches)) => {
+            let result = cli::run_tune(
+                matches.get_one::<String>("data").unwrap(),
+                *matches.get_one::<Variant>("variant").unwrap(),
+                &read_weights_or_exit(matches),
+                *matches.get_one::<u32>("epochs").unwrap() as usize,
+                matches.get_one::<String>("output").unwrap(),
+            );
+            if let Err(e) = result {
+                eprintln!("{e}");
+                exit(1);
+            }
+        }
+        Some(("convert", matches)) => {
+            let (board, tags) = read_position_or_exit(matches);
+            let format = matches.get_one::<String>("to").unwrap();
+            cli::run_convert(
+                &board,
+                &tags,
+                cli::ConvertFormat::from_name(format).unwrap(),
+            );
+        }
+        Some(("uci", _)) => uci::start_uci(),
+        Some(("bench", matches)) => {
+            let threads = matches.get_one::<u32>("threads").map_or_else(
+                || available_parallelism().map_or(1, usize::from),
+                |&threads| threads as usize,
+            );
+            cli::run_bench(
+                *matches.get_one::<u32>("depth").unwrap(),
+                threads,
+                matches
+                    .get_one::<u32>("hash")
+                    .map_or(DEFAULT_HASH_MB, |&hash| hash as usize),
+            );
+        }
+        Some(("setup", matches)) => {
+            let variant = *matches.get_one::<Variant>("variant").unwrap();
+            let mut builder = PositionBuilder::new();
+            builder.variant(variant);
+            if let Some(fen) = matches.get_one::<String>("fen") {
+                if let Err(e) = builder.edit(&format!("fen {fen}")) {
+                    eprintln!("[Error]: {e}");
+                    exit(1);
+                }
+            }
+            let board = cli::run_setup(builder);
+            cli::start_cli_game(board, engine_opponent(matches), &Evaluator::default());
+        }
+        Some(("play", matches)) => {
+            let opponent = engine_opponent(matches);
+            let variant = *matches.get_one::<Variant>("variant").unwrap();
+            let odds_by = match matches.get_one::<String>("odds-by") {
+                Some(color) if color == "black" => ChessColor::BLACK,
+                Some(_) => ChessColor::WHITE,
+                None => opponent.as_ref().map_or(ChessColor::WHITE, |o| o.color),
+            };
+            let chess960 = matches.get_one::<Option<u16>>("chess960");
+            let odds = matches.get_one::<Odds>("odds");
+            if !variant.has_standard_start() && (chess960.is_some() || odds.is_some()) {
+                let setup = if chess960.is_some() {
+                    "Chess960"
+                } else {
+                    "odds"
+                };
+                eprintln!(
+                    "[Error]: {}",
+                    ChessError::IncompatibleVariant { variant, setup }
+                );
+                exit(1);
+            }
+            let mut board = match (chess960, odds) {
+                (Some(Some(index)), _) => ChessBoard::chess960(*index).unwrap(),
+                (Some(None), _) => ChessBoard::chess960_random(),
+                (None, Some(odds)) => ChessBoard::with_odds(*odds, odds_by),
+                (None, None) => ChessBoard::new_variant(variant),
+            };
+            board.set_variant(variant);
+            cli::start_cli_game(board, opponent, &evaluator_or_exit(matches));
+        }
+        _ => cli::start_cli_game(ChessBoard::new(), None, &Evaluator::default()),
+    }
+}
+
+/// The `serve` subcommand, when built with the web server
+#[cfg(feature = "web")]
+mod serve {
+    use clap::{value_parser, Arg, ArgMatches, Command};
+    use std::{
+        net::{IpAddr, SocketAddr},
+        path::PathBuf,
+        process::exit,
+    };
+    use tracing::Level;
+
+    use chess_rs::web::{self, GameLimits, ServerConfig};
+
+    pub fn command() -> Command {
+        Command::new("serve")
+            .about("Serve games over HTTP and websockets")
+            .arg(
+                Arg::new("addr")
+                    .long("addr")
+                    .env("CHESS_ADDR")
+                    .value_parser(value_parser!(IpAddr))
+                    .default_value("127.0.0.1")
+                    .help("Address to listen on"),
+            )
+            .arg(
+                Arg::new("port")
+                    .long("port")
+                    .env("CHESS_PORT")
+                    .value_parser(value_parser!(u16))
+                    .default_value("8080")
+                    .help("Port to listen on"),
+            )
+            .arg(
+                Arg::new("static-dir")
+                    .long("static-dir")
+                    .env("CHESS_STATIC_DIR")
+                    .value_name("DIR")
+                    .value_parser(value_parser!(PathBuf))
+                    .default_value("frontend/dist/")
+                    .help("Directory of the built frontend"),
+            )
+            .arg(
+                Arg::new("log-level")
+                    .long("log-level")
+                    .env("CHESS_LOG_LEVEL")
+                    .value_parser(value_parser!(Level))
+                    .default_value("info")
+                    .help("Most verbose level logged: error, warn, info, debug or trace"),
+            )
+            .arg(
+                Arg::new("max-games")
+                    .long("max-games")
+                    .env("CHESS_MAX_GAMES")
+                    .value_parser(value_parser!(usize))
+                    .default_value("1000")
+                    .help("Open and active games at once"),
+            )
+            .arg(
+                Arg::new("max-engine-depth")
+                    .long("max-engine-depth")
+                    .env("CHESS_MAX_ENGINE_DEPTH")
+                    .value_name("PLIES")
+                    .value_parser(value_parser!(u32).range(1..))
+                    .default_value("8")
+                    .help("Deepest search of the engine"),
+            )
+            .arg(
+                Arg::new("max-engine-time")
+                    .long("max-engine-time")
+                    .env("CHESS_MAX_ENGINE_TIME")
+                    .value_name("MS")
+                    .value_parser(value_parser!(u64))
+                    .default_value("10000")
+                    .help("Longest thinking time of the engine per move"),
+            )
+            .arg(
+                Arg::new("pgn-dir")
+                    .long("pgn-dir")
+                    .env("CHESS_PGN_DIR")
+                    .value_name("DIR")
+                    .value_parser(value_parser!(PathBuf))
+                    .help("Save the PGN of every game there on shutdown"),
+            )
+    }
+
+    fn server_config(matches: &ArgMatches) -> ServerConfig {
+        ServerConfig {
+            addr: SocketAddr::new(
+                *matches.get_one::<IpAddr>("addr").unwrap(),
+                *matches.get_one::<u16>("port").unwrap(),
+            ),
+            static_dir: matches.get_one::<PathBuf>("static-dir").unwrap().clone(),
+            log_level: *matches.get_one::<Level>("log-level").unwrap(),
+            limits: GameLimits {
+                max_games: *matches.get_one::<usize>("max-games").unwrap(),
+                max_engine_depth: *matches.get_one::<u32>("max-engine-depth").unwrap(),
+                max_engine_time_ms: *matches.get_one::<u64>("max-engine-time").unwrap(),
+            },
+            pgn_dir: matches.get_one::<PathBuf>("pgn-dir").cloned(),
+        }
+    }
+
+    pub fn run(matches: &ArgMatches) {
+        if let Err(e) = web::run_web_server(server_config(matches)) {
+            eprintln!("[Error]: the server stopped: {e}");
+            exit(1);
+        }
+    }
 }
@@ -0,0 +1,298 @@
+//! The Universal Chess Interface, spoken by chess GUIs to drive engines
+
+use std::{
+    fs,
+    io::{stdin, stdout, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::chess::{ChessBoard, ChessColor, ChessError, Variant};
+use crate::engine::{
+    search_with_table, time_budget, EvalWeights, Evaluator, Network, SearchLimits, SearchResult,
+    TranspositionTable, DEFAULT_HASH_MB, MAX_HASH_MB, MAX_THREADS,
+};
+
+/// The search running in the background, until `stop` or its limits end it
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl RunningSearch {
+    /// Waits for the search to end by itself and report its move
+    fn wait(self) {
+        self.handle.join().ok();
+    }
+
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+}
+
+/// Talks the Universal Chess Interface on stdin and stdout, so the engine can
+/// be used from chess GUIs
+pub fn start_uci() {
+    let mut board = ChessBoard::new();
+    let mut chess960 = false;
+    let mut variant = Variant::Standard;
+    let mut running: Option<RunningSearch> = None;
+    // searches running in the background hold on to the table until they end
+    let mut table = Arc::new(TranspositionTable::default());
+    let mut threads = 1;
+    let mut evaluator = Evaluator::default();
+
+    for line in stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let mut tokens = line.split_whitespace();
+        let command = tokens.next();
+        match (command, running.take()) {
+            (Some("stop" | "quit"), Some(search)) => search.stop(),
+            (Some("position" | "go" | "ucinewgame"), Some(search)) => search.wait(),
+            (_, search) => running = search,
+        }
+        match command {
+            Some("uci") => {
+                println!("id name chess-rs {}", env!("CARGO_PKG_VERSION"));
+                println!("id author chess-rs developers");
+                println!(
+                    "option name Hash type spin default {DEFAULT_HASH_MB} min 1 max {MAX_HASH_MB}"
+                );
+                println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
+                println!("option name EvalFile type string default <empty>");
+                println!("option name WeightsFile type string default <empty>");
+                println!("option name UCI_Chess960 type check default false");
+                println!(
+                    "option name UCI_Variant type combo default chess \
+                     var chess var kingofthehill var 3check var antichess var crazyhouse var atomic \
+                     var bughouse var horde var racingkings var capablanca"
+                );
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                board = ChessBoard::new();
+                table.clear();
+            }
+            Some("setoption") => match parse_setoption(tokens.collect()) {
+                Some((name, value)) if name.eq_ignore_ascii_case("Hash") => {
+                    match value.parse::<usize>() {
+                        Ok(megabytes) if (1..=MAX_HASH_MB).contains(&megabytes) => {
+                            table = Arc::new(TranspositionTable::new(megabytes));
+                        }
+                        _ => println!("info string Hash goes from 1 to {MAX_HASH_MB} MB"),
+                    }
+                }
+                Some((name, value)) if name.eq_ignore_ascii_case("Threads") => {
+                    match value.parse::<usize>() {
+                        Ok(count) if (1..=MAX_THREADS).contains(&count) => threads = count,
+                        _ => println!("info string Threads goes from 1 to {MAX_THREADS}"),
+                    }
+                }
+                Some((name, value)) if name.eq_ignore_ascii_case("EvalFile") => {
+                    evaluator.network = match value.as_str() {
+                        "" | "<empty>" => None,
+                        path => match read_network(path) {
+                            Ok(loaded) => Some(Arc::new(loaded)),
+                            Err(e) => {
+                                println!("info string {e}");
+                                None
+                            }
+                        },
+                    };
+                }
+                Some((name, value)) if name.eq_ignore_ascii_case("WeightsFile") => {
+                    let weights = match value.as_str() {
+                        "" | "<empty>" => EvalWeights::default(),
+                        path => read_weights(path).unwrap_or_else(|e| {
+                            println!("info string {e}");
+                            EvalWeights::default()
+                        }),
+                    };
+                    evaluator.weights = Arc::new(weights);
+                }
+                Some((name, value)) if name.eq_ignore_ascii_case("UCI_Chess960") => {
+                    chess960 = value == "true";
+                }
+                Some((name, value)) if name.eq_ignore_ascii_case("UCI_Variant") => {
+                    match value.parse() {
+                        Ok(new_variant) => variant = new_variant,
+                        Err(e) => println!("info string {e}"),
+                    }
+                }
+                _ => println!("info string unknown option"),
+            },
+            Some("position") => match parse_position(tokens.collect(), chess960, variant) {
+                Ok(new_board) => board = new_board,
+                Err(e) => println!("info string {e}"),
+            },
+            Some("go") => {
+                let limits = parse_go(tokens.collect(), board.get_turn());
+                running = Some(spawn_search(
+                    board.clone(),
+                    limits,
+                    Arc::clone(&table),
+                    evaluator.clone(),
+                    threads,
+                ));
+            }
+            Some("quit") => break,
+            _ => (),
+        }
+        stdout().flush().unwrap();
+    }
+
+    if let Some(search) = running {
+        search.stop();
+    }
+}
+
+/// Reads the network file of the `EvalFile` option
+fn read_network(path: &str) -> Result<Network, String> {
+    let bytes = fs::read(path).map_err(|e| format!("failed to read {path}: {e}"))?;
+    Network::from_bytes(&bytes).map_err(|e| format!("{path}: {e}"))
+}
+
+/// Reads the evaluation weights file of the `WeightsFile` option
+fn read_weights(path: &str) -> Result<EvalWeights, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("failed to read {path}: {e}"))?;
+    text.parse().map_err(|e| format!("{path}: {e}"))
+}
+
+/// Reads `name <id> [value <x>]`, the name possibly made of several words
+fn parse_setoption(tokens: Vec<&str>) -> Option<(String, String)> {
+    let value_idx = tokens
+        .iter()
+        .position(|&token| token == "value")
+        .unwrap_or(tokens.len());
+    if tokens.first() != Some(&"name") || value_idx < 2 {
+        return None;
+    }
+    let name = tokens[1..value_idx].join(" ");
+    let value = tokens.get(value_idx + 1..).unwrap_or_default().join(" ");
+    Some((name, value))
+}
+
+/// Reads `startpos|fen <fen> [moves <move>...]` played with `variant`,
+/// castling being written as the king taking its rook in `chess960`
+fn parse_position(
+    tokens: Vec<&str>,
+    chess960: bool,
+    variant: Variant,
+) -> Result<ChessBoard, ChessError> {
+    let moves_idx = tokens
+        .iter()
+        .position(|&token| token == "moves")
+        .unwrap_or(tokens.len());
+    let mut board = match tokens.first() {
+        Some(&"startpos") => ChessBoard::new_variant(variant),
+        Some(&"fen") => ChessBoard::from_variant_fen(&tokens[1..moves_idx].join(" "), variant)?,
+        _ => return Err(ChessError::InvalidCommand(tokens.join(" "))),
+    };
+    board.set_variant(variant);
+    if chess960 {
+        board.set_chess960(true);
+    }
+    for token in tokens.iter().skip(moves_idx + 1) {
+        let mv = board.parse_uci(token)?;
+        board.apply_move(mv);
+    }
+    Ok(board)
+}
+
+/// Reads the limits of `go`; a clock gets turned into a time to think
+fn parse_go(tokens: Vec<&str>, turn: ChessColor) -> SearchLimits {
+    let value_of = |name: &str| {
+        tokens
+            .iter()
+            .position(|&token| token == name)
+            .and_then(|idx| tokens.get(idx + 1))
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+
+    let mut limits = SearchLimits {
+        depth: value_of("depth").map(|depth| depth as u32),
+        nodes: value_of("nodes"),
+        time_ms: value_of("movetime"),
+    };
+    if tokens.contains(&"infinite") {
+        return limits;
+    }
+
+    let (time, increment) = match turn {
+        ChessColor::WHITE => ("wtime", "winc"),
+        ChessColor::BLACK => ("btime", "binc"),
+    };
+    if let Some(remaining) = value_of(time) {
+        let increment = value_of(increment).unwrap_or(0);
+        let budget = time_budget(
+            Duration::from_millis(remaining),
+            Duration::from_millis(increment),
+        );
+        limits.time_ms = Some(limits.time_ms.map_or(budget, |time| time.min(budget)));
+    }
+    limits
+}
+
+fn spawn_search(
+    board: ChessBoard,
+    limits: SearchLimits,
+    table: Arc<TranspositionTable>,
+    evaluator: Evaluator,
+    threads: usize,
+) -> RunningSearch {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = thread::spawn({
+        let stop = Arc::clone(&stop);
+        move || {
+            let result = search_with_table(
+                &board,
+                limits,
+                &stop,
+                &table,
+                &evaluator,
+                threads,
+                |result| {
+                    println!("{}", format_info(result));
+                    stdout().flush().unwrap();
+                },
+            );
+            // completed depths were reported as they finished
+            if result.depth == 0 {
+                println!("{}", format_info(&result));
+            }
+            match result.best_move {
+                Some(best_move) => println!("bestmove {best_move}"),
+                None => println!("bestmove 0000"),
+            }
+            stdout().flush().unwrap();
+        }
+    });
+    RunningSearch { stop, handle }
+}
+
+fn format_info(result: &SearchResult) -> String {
+    let score = match result.mate_in() {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {}", result.score),
+    };
+    let millis = result.time.as_millis().max(1);
+    let mut info = format!(
+        "info depth {} score {score} nodes {} nps {} hashfull {} time {millis}",
+        result.depth,
+        result.nodes,
+        result.nodes as u128 * 1000 / millis,
+        result.hashfull
+    );
+    if let Some(best_move) = result.best_move {
+        info.push_str(&format!(" pv {best_move}"));
+    }
+    info
+}
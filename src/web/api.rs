@@ -0,0 +1,209 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::chess::{ChessBoard, ChessError, ChessVec, PositionBuilder, Variant};
+
+use super::{
+    game::{GameHandle, GameId, GameOptions, GamePhase},
+    AppState,
+};
+
+/// JSON endpoints to manage games without a websocket
+pub fn api_routes() -> Router<AppState> {
+    Router::new()
+        .route("/games", get(list_games).post(create_game))
+        .route("/games/finished", get(list_finished_games))
+        .route("/games/:id", get(get_game))
+        .route("/games/:id/moves", get(get_moves))
+        .route("/games/:id/pgn", get(get_pgn))
+        .route("/games/:id/hints", get(get_hints))
+        .route("/positions", post(edit_position))
+}
+
+/// The message sent to clients for `error`, over HTTP or a websocket
+pub fn error_json(error: &ChessError) -> Value {
+    json!({"result": "error", "code": error.code(), "message": error.to_string()})
+}
+
+pub fn error_response(error: &ChessError) -> Response {
+    let status = match error {
+        ChessError::GameNotFound(_) => StatusCode::NOT_FOUND,
+        ChessError::NoFreeSeat => StatusCode::CONFLICT,
+        ChessError::TooManyGames => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    (status, Json(error_json(error))).into_response()
+}
+
+fn find_game(state: &AppState, id: GameId) -> Option<Arc<GameHandle>> {
+    let game = state.games.get(id)?;
+    game.0.write().unwrap().update_clock();
+    Some(game)
+}
+
+fn game_not_found(id: GameId) -> Response {
+    error_response(&ChessError::GameNotFound(id))
+}
+
+async fn create_game(State(state): State<AppState>, Json(options): Json<GameOptions>) -> Response {
+    match state.games.create(options) {
+        Ok(game) => (StatusCode::CREATED, Json(game.0.read().unwrap().state())).into_response(),
+        Err(e) => error_response(&e),
+    }
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    phase: Option<GamePhase>,
+}
+
+/// Lists open and active games, or only the ones in `phase`
+async fn list_games(State(state): State<AppState>, Query(params): Query<ListParams>) -> Response {
+    let games: Vec<_> = state
+        .games
+        .list()
+        .iter()
+        .map(|game| game.0.read().unwrap())
+        .filter(|game| match params.phase {
+            Some(phase) => game.phase() == phase,
+            None => game.phase() != GamePhase::Finished,
+        })
+        .map(|game| game.summary())
+        .collect();
+    Json(games).into_response()
+}
+
+#[derive(Deserialize)]
+struct PageParams {
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+/// Lists finished games, most recent first, `per_page` at a time
+async fn list_finished_games(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> Response {
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params.per_page.unwrap_or(20).clamp(1, 100);
+
+    let finished: Vec<_> = state
+        .games
+        .list()
+        .iter()
+        .rev()
+        .map(|game| game.0.read().unwrap())
+        .filter(|game| game.phase() == GamePhase::Finished)
+        .map(|game| game.summary())
+        .collect();
+    let total = finished.len();
+    let games: Vec<_> = finished
+        .into_iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .collect();
+
+    Json(json!({
+        "page": page,
+        "per_page": per_page,
+        "total": total,
+        "games": games,
+    }))
+    .into_response()
+}
+
+async fn get_game(State(state): State<AppState>, Path(id): Path<GameId>) -> Response {
+    match find_game(&state, id) {
+        Some(game) => Json(game.0.read().unwrap().state()).into_response(),
+        None => game_not_found(id),
+    }
+}
+
+async fn get_moves(State(state): State<AppState>, Path(id): Path<GameId>) -> Response {
+    let Some(game) = find_game(&state, id) else {
+        return game_not_found(id);
+    };
+    let history = game.0.read().unwrap().board.history();
+    Json(history).into_response()
+}
+
+async fn get_pgn(State(state): State<AppState>, Path(id): Path<GameId>) -> Response {
+    match find_game(&state, id) {
+        Some(game) => (
+            [(header::CONTENT_TYPE, "application/x-chess-pgn")],
+            game.0.read().unwrap().to_pgn(),
+        )
+            .into_response(),
+        None => game_not_found(id),
+    }
+}
+
+#[derive(Deserialize)]
+struct HintParams {
+    square: String,
+}
+
+/// The legal destinations of the piece on `square`, the last move and the
+/// squares involved in a check
+async fn get_hints(
+    State(state): State<AppState>,
+    Path(id): Path<GameId>,
+    Query(params): Query<HintParams>,
+) -> Response {
+    let Some(game) = find_game(&state, id) else {
+        return game_not_found(id);
+    };
+    let square = match ChessVec::try_from(&mut params.square.clone()) {
+        Ok(square) => square,
+        Err(e) => return error_response(&e),
+    };
+    let hints = game.0.read().unwrap().hints(square);
+    Json(hints).into_response()
+}
+
+#[derive(Deserialize)]
+struct PositionEdit {
+    /// The position to edit, an empty board without it
+    fen: Option<String>,
+    #[serde(default)]
+    variant: Variant,
+    /// Commands of the board editor, as [`PositionBuilder::edit`] reads them
+    #[serde(default)]
+    commands: Vec<String>,
+}
+
+/// Applies the commands of the board editor to a position, answering with
+/// the FEN of the result and what keeps it from being played, if anything
+async fn edit_position(Json(edit): Json<PositionEdit>) -> Response {
+    let mut builder = match &edit.fen {
+        Some(fen) => match ChessBoard::from_variant_fen(fen, edit.variant) {
+            Ok(board) => PositionBuilder::from_board(&board),
+            Err(e) => return error_response(&e),
+        },
+        None => {
+            let mut builder = PositionBuilder::new();
+            builder.variant(edit.variant);
+            builder
+        }
+    };
+    for command in &edit.commands {
+        if let Err(e) = builder.edit(command) {
+            return error_response(&e);
+        }
+    }
+    let problem = builder.validate().err();
+    Json(json!({
+        "fen": builder.board().to_fen(),
+        "valid": problem.is_none(),
+        "problem": problem.as_ref().map(error_json),
+    }))
+    .into_response()
+}
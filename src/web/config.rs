@@ -0,0 +1,50 @@
+use std::{net::SocketAddr, path::PathBuf};
+use tracing::Level;
+
+/// Settings of the web server, read from the command line or the environment
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Where to listen
+    pub addr: SocketAddr,
+    /// Directory of the built frontend
+    pub static_dir: PathBuf,
+    /// Most verbose level logged
+    pub log_level: Level,
+    #[allow(missing_docs)]
+    pub limits: GameLimits,
+    /// Where the PGN of every game gets saved on shutdown
+    pub pgn_dir: Option<PathBuf>,
+}
+
+/// Bounds on what clients may ask of the server
+#[derive(Debug, Clone, Copy)]
+pub struct GameLimits {
+    /// Open and active games at once
+    pub max_games: usize,
+    /// Deepest search of the engine
+    pub max_engine_depth: u32,
+    /// Longest thinking time of the engine per move, in milliseconds
+    pub max_engine_time_ms: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            static_dir: PathBuf::from("frontend/dist/"),
+            log_level: Level::INFO,
+            limits: GameLimits::default(),
+            pgn_dir: None,
+        }
+    }
+}
+
+impl Default for GameLimits {
+    fn default() -> Self {
+        Self {
+            max_games: 1000,
+            max_engine_depth: 8,
+            max_engine_time_ms: 10_000,
+        }
+    }
+}
@@ -0,0 +1,748 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{hash_map::RandomState, BTreeMap},
+    fmt, fs,
+    hash::{BuildHasher, Hasher},
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Notify;
+
+use super::GameLimits;
+use crate::chess::{
+    ChessBoard, ChessColor, ChessError, ChessPiece, ChessVec, GameStatus, Move, Odds,
+    PositionBuilder, Variant,
+};
+use crate::engine::{self, SearchLimits, MAX_DEPTH};
+
+pub type GameId = u64;
+
+/// A game shared between its sockets, notified whenever it changes
+pub type GameHandle = (RwLock<Game>, Notify);
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub initial_secs: u64,
+    #[serde(default)]
+    pub increment_secs: u64,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    White,
+    Black,
+    #[default]
+    Random,
+}
+
+/// Where a player sits: the board, which is 0 but in Bughouse, and the color
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Seat {
+    pub board: usize,
+    pub color: ChessColor,
+}
+
+/// Options sent by the client creating a game
+#[derive(Clone, Default, Deserialize)]
+pub struct GameOptions {
+    pub time_control: Option<TimeControl>,
+    /// The color of the first player joining the game
+    #[serde(default)]
+    pub color: ColorChoice,
+    pub fen: Option<String>,
+    /// Starts from a Chess960 position, picked at random or by its number
+    pub chess960: Option<Chess960Choice>,
+    /// Starts a handicap game from the starting position without this material
+    pub odds: Option<Odds>,
+    /// The side giving the odds, the engine or else white by default
+    pub odds_by: Option<ChessColor>,
+    /// The rules the game is played with
+    #[serde(default)]
+    pub variant: Variant,
+    /// Seats the built-in engine against the first player, searching within these limits
+    pub engine: Option<SearchLimits>,
+}
+
+/// `true` for a random Chess960 position, or the number of one
+#[derive(Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum Chess960Choice {
+    Random(bool),
+    Index(u16),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GamePhase {
+    /// Waiting for a player to take a seat
+    Open,
+    Active,
+    Finished,
+}
+
+#[derive(Clone, Serialize)]
+pub struct GameResult {
+    pub result: &'static str,
+    pub reason: String,
+}
+
+pub struct Clock {
+    remaining: [Duration; 2],
+    increment: Duration,
+    /// When the clock of the side to move started running
+    last_tick: Option<Instant>,
+}
+
+pub struct Game {
+    pub id: GameId,
+    pub board: ChessBoard,
+    time_control: Option<TimeControl>,
+    first_color: ChessColor,
+    seats: [bool; 2],
+    /// Set once both seats were taken
+    started: bool,
+    clock: Option<Clock>,
+    /// The color played by the engine and how long it may search
+    engine: Option<(ChessColor, SearchLimits)>,
+    engine_thinking: bool,
+    result: Option<GameResult>,
+    created_at: u64,
+    finished_at: Option<u64>,
+    /// The second board of a Bughouse game, played under the same lock
+    partner: Option<Box<Game>>,
+    /// Pieces taken on this board, on their way to the partner board
+    passed: Vec<ChessPiece>,
+}
+
+pub struct GameRegistry {
+    games: RwLock<BTreeMap<GameId, Arc<GameHandle>>>,
+    next_id: AtomicU64,
+    limits: GameLimits,
+}
+
+fn squares_to_strings(squares: Vec<ChessVec>) -> Vec<String> {
+    squares.iter().map(ChessVec::to_string).collect()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+impl Seat {
+    /// Bughouse teams pair the white player of a board with the black
+    /// player of the other
+    pub fn team(self) -> usize {
+        (self.board + self.color as usize) % 2
+    }
+}
+
+impl fmt::Display for Seat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.board {
+            0 => write!(f, "{}", self.color),
+            board => write!(f, "{} on board {}", self.color, board + 1),
+        }
+    }
+}
+
+impl Clock {
+    fn new(time_control: TimeControl) -> Self {
+        Self {
+            remaining: [Duration::from_secs(time_control.initial_secs); 2],
+            increment: Duration::from_secs(time_control.increment_secs),
+            last_tick: None,
+        }
+    }
+
+    fn start(&mut self) {
+        self.last_tick = Some(Instant::now());
+    }
+
+    /// Time left for `color`, counting the running clock of the side to move
+    fn remaining(&self, color: ChessColor, turn: ChessColor) -> Duration {
+        let remaining = self.remaining[color as usize];
+        match self.last_tick {
+            Some(last_tick) if color == turn => remaining.saturating_sub(last_tick.elapsed()),
+            _ => remaining,
+        }
+    }
+
+    /// Stops the clock of `mover` after a move and starts the opponent's one
+    fn punch(&mut self, mover: ChessColor) {
+        let remaining = self.remaining(mover, mover);
+        self.remaining[mover as usize] = remaining + self.increment;
+        self.start();
+    }
+}
+
+impl Game {
+    fn new(id: GameId, options: GameOptions, limits: &GameLimits) -> Result<Self, ChessError> {
+        if options.fen.is_none() && !options.variant.has_standard_start() {
+            let setup = match (options.chess960, options.odds) {
+                (Some(Chess960Choice::Index(_) | Chess960Choice::Random(true)), _) => {
+                    Some("Chess960")
+                }
+                (_, Some(_)) => Some("odds"),
+                _ => None,
+            };
+            if let Some(setup) = setup {
+                return Err(ChessError::IncompatibleVariant {
+                    variant: options.variant,
+                    setup,
+                });
+            }
+        }
+        let mut game = Self::new_board(id, &options, limits)?;
+        if options.variant == Variant::Bughouse {
+            if game.engine.is_some() {
+                return Err(ChessError::EngineUnsupported(options.variant));
+            }
+            let mut partner = Self::new_board(id, &options, limits)?;
+            // the first two players to join are teammates
+            partner.first_color = game.first_color.opponent();
+            game.partner = Some(Box::new(partner));
+        }
+        Ok(game)
+    }
+
+    fn new_board(
+        id: GameId,
+        options: &GameOptions,
+        limits: &GameLimits,
+    ) -> Result<Self, ChessError> {
+        let first_color = match options.color {
+            ColorChoice::White => ChessColor::WHITE,
+            ColorChoice::Black => ChessColor::BLACK,
+            ColorChoice::Random if RandomState::new().build_hasher().finish() & 1 == 0 => {
+                ChessColor::WHITE
+            }
+            ColorChoice::Random => ChessColor::BLACK,
+        };
+
+        let odds_by = options.odds_by.unwrap_or(match options.engine {
+            Some(_) => first_color.opponent(),
+            None => ChessColor::WHITE,
+        });
+        let mut board = match (&options.fen, options.chess960, options.odds) {
+            (Some(fen), _, _) => {
+                let board = ChessBoard::from_variant_fen(fen, options.variant)?;
+                PositionBuilder::from_board(&board).build()?
+            }
+            (None, Some(Chess960Choice::Index(index)), _) => ChessBoard::chess960(index)?,
+            (None, Some(Chess960Choice::Random(true)), _) => ChessBoard::chess960_random(),
+            (None, _, Some(odds)) => ChessBoard::with_odds(odds, odds_by),
+            (None, _, None) => ChessBoard::new_variant(options.variant),
+        };
+        board.set_variant(options.variant);
+        if board.status().is_over() {
+            return Err(ChessError::GameOver);
+        }
+
+        let engine = options.engine.map(|engine_limits| {
+            let unlimited = engine_limits == SearchLimits::default();
+            let mut engine_limits = if unlimited {
+                SearchLimits::depth(4)
+            } else {
+                engine_limits
+            };
+            let depth = engine_limits.depth.unwrap_or(MAX_DEPTH);
+            engine_limits.depth = Some(depth.min(limits.max_engine_depth));
+            engine_limits.time_ms = Some(
+                engine_limits
+                    .time_ms
+                    .map_or(limits.max_engine_time_ms, |time| {
+                        time.min(limits.max_engine_time_ms)
+                    }),
+            );
+            (first_color.opponent(), engine_limits)
+        });
+        let mut seats = [false; 2];
+        if let Some((color, _)) = engine {
+            seats[color as usize] = true;
+        }
+
+        Ok(Self {
+            id,
+            board,
+            time_control: options.time_control,
+            first_color,
+            seats,
+            started: false,
+            clock: options.time_control.map(Clock::new),
+            engine,
+            engine_thinking: false,
+            result: None,
+            created_at: unix_time(),
+            finished_at: None,
+            partner: None,
+            passed: Vec::new(),
+        })
+    }
+
+    /// Every board of the game, the Bughouse partner board last
+    fn boards(&self) -> impl Iterator<Item = &Game> {
+        std::iter::once(self).chain(self.partner.as_deref())
+    }
+
+    /// The game on `board`, which only Bughouse has more than one of
+    pub fn on_board(&self, board: usize) -> &Game {
+        self.boards()
+            .nth(board)
+            .expect("seats are only on existing boards")
+    }
+
+    fn on_board_mut(&mut self, board: usize) -> &mut Game {
+        match board {
+            0 => self,
+            _ => self
+                .partner
+                .as_deref_mut()
+                .expect("seats are only on existing boards"),
+        }
+    }
+
+    pub fn phase(&self) -> GamePhase {
+        if self.result.is_some() {
+            GamePhase::Finished
+        } else if self.started {
+            GamePhase::Active
+        } else {
+            GamePhase::Open
+        }
+    }
+
+    /// Whether [`Game::join`] would find a seat
+    pub fn has_free_seat(&self) -> bool {
+        self.result.is_none() && self.boards().any(|game| game.seats != [true; 2])
+    }
+
+    /// Takes a free seat, preferring the color chosen by the game creator.
+    /// Bughouse boards are filled in turn, so teams form before opponents
+    /// come, and the game starts once all four players are seated
+    pub fn join(&mut self) -> Option<Seat> {
+        if self.result.is_some() {
+            return None;
+        }
+        let taken = |game: &Game| game.seats.iter().filter(|&&seat| seat).count();
+        let board = match self.partner.as_deref() {
+            Some(partner) if taken(partner) < taken(self) => 1,
+            _ => 0,
+        };
+        let game = self.on_board_mut(board);
+        let color = [game.first_color, game.first_color.opponent()]
+            .into_iter()
+            .find(|&color| !game.seats[color as usize])?;
+        game.seats[color as usize] = true;
+
+        if self.boards().all(|game| game.seats == [true; 2]) {
+            self.start();
+            if let Some(partner) = self.partner.as_deref_mut() {
+                partner.start();
+            }
+        }
+        Some(Seat { board, color })
+    }
+
+    fn start(&mut self) {
+        if !self.started {
+            self.started = true;
+            if let Some(clock) = self.clock.as_mut() {
+                clock.start();
+            }
+        }
+    }
+
+    /// Frees the seat of a disconnected player so they can join back
+    pub fn leave(&mut self, seat: Seat) {
+        let game = self.on_board_mut(seat.board);
+        game.seats[seat.color as usize] = false;
+        game.board.deselect_piece().ok();
+    }
+
+    /// Ends the game without a winner, e.g. when the server shuts down
+    pub fn abort(&mut self, reason: &str) {
+        if self.result.is_none() {
+            self.finish("*", reason.to_string());
+        }
+        self.link_boards();
+    }
+
+    fn finish(&mut self, result: &'static str, reason: String) {
+        self.result = Some(GameResult { result, reason });
+        self.finished_at = Some(unix_time());
+        if let Some(clock) = self.clock.as_mut() {
+            let turn = self.board.get_turn();
+            clock.remaining[turn as usize] = clock.remaining(turn, turn);
+            clock.last_tick = None;
+        }
+    }
+
+    /// Ends the game if the side to move ran out of time, on either board
+    pub fn update_clock(&mut self) {
+        self.update_board_clock();
+        if let Some(partner) = self.partner.as_deref_mut() {
+            partner.update_board_clock();
+        }
+        self.link_boards();
+    }
+
+    fn update_board_clock(&mut self) {
+        let turn = self.board.get_turn();
+        let flagged = self.result.is_none()
+            && self.clock.as_ref().is_some_and(|clock| {
+                clock.last_tick.is_some() && clock.remaining(turn, turn).is_zero()
+            });
+        if flagged {
+            let result = match turn {
+                ChessColor::WHITE => "0-1",
+                ChessColor::BLACK => "1-0",
+            };
+            self.finish(result, format!("{turn} ran out of time"));
+        }
+    }
+
+    /// Handles a message sent by the player on `seat`: either `resign`, a
+    /// drop such as `N@f3`, or a square to select a piece on, or to move the
+    /// selected piece to
+    pub fn play(&mut self, seat: Seat, msg: &str) -> Result<(), ChessError> {
+        self.update_clock();
+        let result = self.on_board_mut(seat.board).play_board(seat.color, msg);
+        self.link_boards();
+        result
+    }
+
+    fn play_board(&mut self, color: ChessColor, msg: &str) -> Result<(), ChessError> {
+        if self.result.is_some() {
+            return Err(ChessError::GameOver);
+        }
+
+        if msg.eq_ignore_ascii_case("resign") {
+            let result = match color {
+                ChessColor::WHITE => "0-1",
+                ChessColor::BLACK => "1-0",
+            };
+            self.finish(result, format!("{color} resigned"));
+            return Ok(());
+        }
+
+        if !self.started {
+            return Err(ChessError::WaitingForOpponent);
+        }
+        if self.board.get_turn() != color {
+            return Err(ChessError::NotYourTurn);
+        }
+
+        if msg.contains('@') {
+            self.board.make_move(msg.parse()?)?;
+            self.after_move(color);
+            return Ok(());
+        }
+
+        let pos = ChessVec::try_from(&mut msg.to_string())?;
+        let owns_piece = self.board[pos].is_some_and(|piece| piece.color == color);
+        // a Chess960 king castles by taking its own rook
+        let is_destination = self
+            .board
+            .get_selected()
+            .is_some_and(|from| self.board.legal_destinations(from).contains(&pos));
+        if !self.board.is_piece_selected() || owns_piece && !is_destination {
+            return self.board.select_piece(pos);
+        }
+
+        self.board.make_selected(pos)?;
+        self.after_move(color);
+        Ok(())
+    }
+
+    fn after_move(&mut self, mover: ChessColor) {
+        if let Some(clock) = self.clock.as_mut() {
+            clock.punch(mover);
+        }
+        if self.board.variant() == Variant::Bughouse {
+            self.passed.extend(self.board.last_capture());
+        }
+
+        let status = self.board.status();
+        if status.is_over() {
+            self.finish(status.result(), status.to_string());
+        }
+    }
+
+    /// Passes the pieces taken on each Bughouse board to the pocket of the
+    /// partner of the taker, and ends both boards once either ended
+    fn link_boards(&mut self) {
+        let Some(mut partner) = self.partner.take() else {
+            return;
+        };
+        Self::pass_to(self, &mut partner);
+        Self::pass_to(&mut partner, self);
+        self.partner = Some(partner);
+    }
+
+    fn pass_to(from: &mut Game, to: &mut Game) {
+        // the partner of the taker plays the color of the piece taken
+        for piece in from.passed.drain(..) {
+            to.board.add_to_pocket(piece.color, piece.piece_type);
+        }
+        if let (Some(ended), None) = (&from.result, &to.result) {
+            // colors are swapped between the boards, so is the result
+            let result = match ended.result {
+                "1-0" => "0-1",
+                "0-1" => "1-0",
+                result => result,
+            };
+            let reason = format!("{} on the other board", ended.reason);
+            to.finish(result, reason);
+        }
+    }
+
+    pub fn has_engine(&self) -> bool {
+        self.engine.is_some()
+    }
+
+    /// Claims the turn of the engine when it is to move, returning the position
+    /// to search and the limits to search it with
+    pub fn start_engine_turn(&mut self) -> Option<(ChessBoard, SearchLimits)> {
+        let (color, mut limits) = self.engine?;
+        if !self.started
+            || self.result.is_some()
+            || self.engine_thinking
+            || self.board.get_turn() != color
+        {
+            return None;
+        }
+
+        // keep the engine from losing on time
+        if let Some(clock) = &self.clock {
+            let budget = engine::time_budget(clock.remaining(color, color), clock.increment);
+            limits.time_ms = Some(limits.time_ms.map_or(budget, |time| time.min(budget)));
+        }
+
+        self.engine_thinking = true;
+        Some((self.board.clone(), limits))
+    }
+
+    /// Plays the move found by the engine, unless the game ended meanwhile
+    pub fn finish_engine_turn(&mut self, best_move: Option<Move>) {
+        self.engine_thinking = false;
+        self.update_clock();
+        let (Some((color, _)), Some(best_move)) = (self.engine, best_move) else {
+            return;
+        };
+        if self.result.is_some() {
+            return;
+        }
+
+        match self.board.make_move(best_move) {
+            Ok(()) => self.after_move(color),
+            Err(e) => tracing::error!("engine played {best_move} in game {}: {e}", self.id),
+        }
+    }
+
+    pub fn summary(&self) -> Value {
+        let seats = |game: &Game| {
+            json!({
+                "white": game.seats[ChessColor::WHITE as usize],
+                "black": game.seats[ChessColor::BLACK as usize],
+            })
+        };
+        let mut summary = json!({
+            "id": self.id,
+            "phase": self.phase(),
+            "time_control": self.time_control,
+            "seats": seats(self),
+            "engine": self.engine.map(|(color, limits)| json!({
+                "color": color.to_string(),
+                "limits": limits,
+            })),
+            "ply": self.board.ply_count(),
+            "result": self.result,
+            "created_at": self.created_at,
+            "finished_at": self.finished_at,
+        });
+        if let Some(partner) = &self.partner {
+            summary["partner_seats"] = seats(partner);
+        }
+        summary
+    }
+
+    /// The state of the game; Bughouse games show their first board, along
+    /// with both boards under `boards`
+    pub fn state(&self) -> Value {
+        self.state_of(0)
+    }
+
+    /// The state of the game as the players on `board` see it
+    pub fn state_of(&self, board: usize) -> Value {
+        let mut state = self.on_board(board).board_state();
+        if self.partner.is_some() {
+            state["board"] = json!(board);
+            state["boards"] = json!(self.boards().map(Game::board_state).collect::<Vec<_>>());
+        }
+        state
+    }
+
+    fn board_state(&self) -> Value {
+        let turn = self.board.get_turn();
+        let mut state = self.summary();
+        state["fen"] = json!(self.board.to_fen());
+        state["chess960"] = json!(self.board.is_chess960());
+        state["variant"] = json!(self.board.variant());
+        state["pockets"] = json!({
+            "white": self.board.pocket(ChessColor::WHITE),
+            "black": self.board.pocket(ChessColor::BLACK),
+        });
+        state["turn"] = json!(turn.to_string());
+        state["status"] = json!(match self.board.status() {
+            GameStatus::Ongoing if self.board.in_check(turn) => "check".to_string(),
+            status => status.to_string(),
+        });
+        state["clock"] = json!(self.clock.as_ref().map(|clock| json!({
+            "white": clock.remaining(ChessColor::WHITE, turn).as_millis() as u64,
+            "black": clock.remaining(ChessColor::BLACK, turn).as_millis() as u64,
+            "running": clock.last_tick.is_some(),
+        })));
+        state["selected"] = json!(self.board.get_selected().map(|pos| pos.to_string()));
+        state["last_move"] = json!(self.board.last_move().map(|mv| mv.to_string()));
+        state["check"] = json!(squares_to_strings(self.board.check_squares()));
+        state
+    }
+
+    /// The squares the piece on `square` can move to, along with the squares
+    /// a board needs to highlight
+    pub fn hints(&self, square: ChessVec) -> Value {
+        let destinations = match self.result {
+            Some(_) => Vec::new(),
+            None => self.board.legal_destinations(square),
+        };
+        json!({
+            "square": square.to_string(),
+            "destinations": squares_to_strings(destinations),
+            "last_move": self.board.last_move().map(|mv| mv.to_string()),
+            "check": squares_to_strings(self.board.check_squares()),
+        })
+    }
+
+    pub fn to_pgn(&self) -> String {
+        match &self.partner {
+            None => self.board.to_pgn(&self.pgn_tags()),
+            // both boards go in the same file, told apart by their `Board` tag
+            Some(partner) => [(self, "A"), (&**partner, "B")]
+                .map(|(game, name)| {
+                    let mut tags = game.pgn_tags();
+                    tags.push(("Board", name.to_string()));
+                    game.board.to_pgn(&tags)
+                })
+                .join("\n"),
+        }
+    }
+
+    fn pgn_tags(&self) -> Vec<(&'static str, String)> {
+        let mut tags = vec![
+            ("Event", "Casual game".to_string()),
+            ("Site", "chess-rs".to_string()),
+            ("Round", self.id.to_string()),
+        ];
+        if let Some(result) = &self.result {
+            tags.push(("Result", result.result.to_string()));
+            tags.push(("Termination", result.reason.clone()));
+        }
+        if let Some(time_control) = self.time_control {
+            tags.push((
+                "TimeControl",
+                format!(
+                    "{}+{}",
+                    time_control.initial_secs, time_control.increment_secs
+                ),
+            ));
+        }
+        tags
+    }
+}
+
+impl GameRegistry {
+    pub fn new(limits: GameLimits) -> Self {
+        Self {
+            games: RwLock::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+            limits,
+        }
+    }
+
+    pub fn create(&self, options: GameOptions) -> Result<Arc<GameHandle>, ChessError> {
+        let mut games = self.games.write().unwrap();
+        let ongoing = games
+            .values()
+            .filter(|game| game.0.read().unwrap().phase() != GamePhase::Finished)
+            .count();
+        if ongoing >= self.limits.max_games {
+            return Err(ChessError::TooManyGames);
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let game = Game::new(id, options, &self.limits)?;
+        let game = Arc::new((RwLock::new(game), Notify::new()));
+        games.insert(id, Arc::clone(&game));
+        Ok(game)
+    }
+
+    pub fn get(&self, id: GameId) -> Option<Arc<GameHandle>> {
+        self.games.read().unwrap().get(&id).cloned()
+    }
+
+    /// Seats the player in the oldest open game, or in a new default game
+    pub fn join_random(&self) -> Result<(Arc<GameHandle>, Seat), ChessError> {
+        let games = self.games.write().unwrap();
+        for game in games.values() {
+            let mut lock = game.0.write().unwrap();
+            if lock.phase() == GamePhase::Open && !lock.has_engine() {
+                if let Some(seat) = lock.join() {
+                    drop(lock);
+                    return Ok((Arc::clone(game), seat));
+                }
+            }
+        }
+        drop(games);
+
+        let game = self.create(GameOptions::default())?;
+        let seat = game.0.write().unwrap().join().unwrap();
+        Ok((game, seat))
+    }
+
+    /// Writes the PGN of every game to `dir`, one `game-<id>.pgn` file per game
+    pub fn save_pgns(&self, dir: &Path) -> io::Result<usize> {
+        fs::create_dir_all(dir)?;
+        let games = self.list();
+        for game in &games {
+            let game = game.0.read().unwrap();
+            fs::write(dir.join(format!("game-{}.pgn", game.id)), game.to_pgn())?;
+        }
+        Ok(games.len())
+    }
+
+    /// Aborts every game still going and wakes their sockets up
+    pub fn abort_all(&self, reason: &str) {
+        for game in self.list() {
+            game.0.write().unwrap().abort(reason);
+            game.1.notify_waiters();
+        }
+    }
+
+    /// Every game in creation order, with their clocks brought up to date
+    pub fn list(&self) -> Vec<Arc<GameHandle>> {
+        let games: Vec<_> = self.games.read().unwrap().values().cloned().collect();
+        for game in &games {
+            game.0.write().unwrap().update_clock();
+        }
+        games
+    }
+}
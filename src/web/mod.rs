@@ -0,0 +1,299 @@
+//! The game server: a websocket per player, a REST API and the frontend
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Query, State, WebSocketUpgrade,
+    },
+    response::{IntoResponse, Response},
+    routing::any,
+    Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::{io, sync::Arc, time::Duration};
+use tokio::{select, sync::watch};
+use tower_http::{services::ServeDir, trace::TraceLayer};
+
+use crate::chess::{ChessError, ChessVec, Variant};
+use crate::engine;
+
+mod api;
+mod config;
+mod game;
+#[cfg(test)]
+mod test_web;
+
+use api::{api_routes, error_json, error_response};
+pub use config::{GameLimits, ServerConfig};
+use game::{GameHandle, GameId, GameRegistry, Seat};
+
+/// Stores ongoing matches
+#[derive(Clone)]
+struct AppState {
+    games: Arc<GameRegistry>,
+    /// Turns true once the server starts shutting down
+    shutdown: watch::Receiver<bool>,
+}
+
+/// Serves games until the process gets a shutdown signal
+pub async fn start_web_server(config: ServerConfig) -> io::Result<()> {
+    tracing_subscriber::fmt()
+        .with_max_level(config.log_level)
+        .init();
+
+    let listener = tokio::net::TcpListener::bind(config.addr).await?;
+
+    let (shutdown_sender, shutdown) = watch::channel(false);
+    let shutdown_sender = Arc::new(shutdown_sender);
+    let games = Arc::new(GameRegistry::new(config.limits));
+    let app_state = AppState {
+        games: Arc::clone(&games),
+        shutdown,
+    };
+
+    let app = Router::new()
+        .route("/ws", any(ws_handler))
+        .nest("/api", api_routes())
+        .nest_service("/", ServeDir::new(&config.static_dir))
+        .layer(TraceLayer::new_for_http())
+        .with_state(app_state);
+
+    tracing::info!("listening on {}", listener.local_addr()?);
+    axum::serve(listener, app)
+        .with_graceful_shutdown({
+            let games = Arc::clone(&games);
+            let shutdown_sender = Arc::clone(&shutdown_sender);
+            async move {
+                shutdown_signal().await;
+                tracing::info!("shutting down..");
+                games.abort_all("server shutdown");
+                shutdown_sender.send_replace(true);
+            }
+        })
+        .await?;
+
+    // sockets hold a receiver until they said goodbye to their player
+    let sockets_closed = shutdown_sender.closed();
+    if tokio::time::timeout(Duration::from_secs(5), sockets_closed)
+        .await
+        .is_err()
+    {
+        tracing::warn!("some sockets didn't close in time");
+    }
+
+    if let Some(dir) = &config.pgn_dir {
+        let saved = games.save_pgns(dir)?;
+        tracing::info!("saved {saved} games to {}", dir.display());
+    }
+    Ok(())
+}
+
+/// Runs [`start_web_server`] on a new tokio runtime until the server shuts down
+pub fn run_web_server(config: ServerConfig) -> io::Result<()> {
+    tokio::runtime::Runtime::new()?.block_on(start_web_server(config))
+}
+
+/// Resolves on Ctrl+C, or when the process is asked to terminate
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("[Error]: failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("[Error]: failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    select! {
+        _ = ctrl_c => (),
+        _ = terminate => (),
+    }
+}
+
+#[derive(Deserialize)]
+struct WsParams {
+    /// Joins this game instead of a random open one
+    game: Option<GameId>,
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<WsParams>,
+    State(AppState { games, shutdown }): State<AppState>,
+) -> Response {
+    // turn the player away before the handshake when the game is full; the
+    // seat itself is only taken once the socket is up, so a handshake that
+    // fails holds none
+    if let Some(id) = params.game {
+        let Some(game) = games.get(id) else {
+            return error_response(&ChessError::GameNotFound(id));
+        };
+        if !game.0.read().unwrap().has_free_seat() {
+            return error_response(&ChessError::NoFreeSeat);
+        }
+    }
+    ws.on_upgrade(move |mut socket| async move {
+        match join_game(&games, params.game) {
+            Ok((game, seat)) => handle_socket(socket, game, seat, shutdown).await,
+            Err(e) => {
+                socket.send(error_json(&e).to_string().into()).await.ok();
+                socket.send(Message::Close(None)).await.ok();
+            }
+        }
+    })
+    .into_response()
+}
+
+/// Seats the player in game `id`, or in a random open game without one,
+/// and lets the engine move if the game just started on its turn
+fn join_game(
+    games: &GameRegistry,
+    id: Option<GameId>,
+) -> Result<(Arc<GameHandle>, Seat), ChessError> {
+    let (game, seat) = match id {
+        Some(id) => {
+            let game = games.get(id).ok_or(ChessError::GameNotFound(id))?;
+            let seat = game
+                .0
+                .write()
+                .unwrap()
+                .join()
+                .ok_or(ChessError::NoFreeSeat)?;
+            game.1.notify_waiters();
+            (game, seat)
+        }
+        None => games.join_random()?,
+    };
+    spawn_engine_turn(Arc::clone(&game));
+    Ok((game, seat))
+}
+
+/// Plays the game for the player on `seat`, sending them the state of the
+/// game whenever it changes; in Bughouse that is the state of their board,
+/// with both boards under `boards`
+async fn handle_socket(
+    mut socket: WebSocket,
+    game: Arc<GameHandle>,
+    seat: Seat,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let (lock, notice) = &*game;
+    let id = lock.read().unwrap().id;
+    tracing::info!("{seat} connected to game {id}..");
+
+    'session: loop {
+        // register before reading the game so no update is missed
+        let notified = notice.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let state = {
+            let mut game = lock.write().unwrap();
+            game.update_clock();
+            let board = &game.on_board(seat.board).board;
+            tracing::info!("\n{board}");
+            let mut state = game.state_of(seat.board);
+            state["color"] = json!(seat.color.to_string());
+            if board.variant() == Variant::Bughouse {
+                state["team"] = json!(seat.team());
+            }
+            json!({"result": "success", "message": board.to_fen(), "game": state})
+        };
+        if socket.send(state.to_string().into()).await.is_err() {
+            break;
+        }
+        // the player got the aborted game, now let them go
+        if *shutdown.borrow_and_update() {
+            socket.send(Message::Close(None)).await.ok();
+            break;
+        }
+
+        loop {
+            let msg = select! {
+                _ = &mut notified => continue 'session,
+                _ = shutdown.changed() => continue 'session,
+                msg = socket.recv() => msg,
+            };
+            let msg = match msg {
+                Some(Ok(Message::Text(msg))) => msg,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break 'session,
+                Some(Ok(_)) => continue,
+            };
+            tracing::info!("{seat}: {msg}");
+
+            // `hints <square>` only asks for the moves of a piece
+            let reply = if let Some(square) = msg.trim().strip_prefix("hints") {
+                match ChessVec::try_from(&mut square.to_string()) {
+                    Ok(pos) => {
+                        let hints = lock.read().unwrap().on_board(seat.board).hints(pos);
+                        json!({"result": "hints", "message": hints})
+                    }
+                    Err(e) => error_json(&e),
+                }
+            } else {
+                let move_result = lock.write().unwrap().play(seat, msg.trim());
+                match move_result {
+                    Ok(_) => {
+                        tracing::info!("{seat} took action");
+                        notice.notify_waiters();
+                        spawn_engine_turn(Arc::clone(&game));
+
+                        let hints = {
+                            let game = lock.read().unwrap();
+                            let game = game.on_board(seat.board);
+                            game.board
+                                .get_selected()
+                                .filter(|_| game.board.get_turn() == seat.color)
+                                .map(|pos| game.hints(pos))
+                        };
+                        match hints {
+                            Some(hints) => json!({"result": "hints", "message": hints}),
+                            None => continue 'session,
+                        }
+                    }
+                    Err(e) => {
+                        tracing::info!("{e}");
+                        error_json(&e)
+                    }
+                }
+            };
+            if socket.send(reply.to_string().into()).await.is_err() {
+                break 'session;
+            }
+        }
+    }
+
+    tracing::info!("{seat} left game {id}..");
+    lock.write().unwrap().leave(seat);
+    notice.notify_waiters();
+}
+
+/// Lets the engine search on a blocking task when it is its turn, then plays
+/// its move and wakes the sockets of the game up
+fn spawn_engine_turn(game: Arc<GameHandle>) {
+    let Some((board, limits)) = game.0.write().unwrap().start_engine_turn() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking(move || engine::search(&board, limits)).await;
+        let best_move = result.ok().and_then(|result| {
+            tracing::info!(
+                "engine searched {} nodes to depth {} in {:?}",
+                result.nodes,
+                result.depth,
+                result.time
+            );
+            result.best_move
+        });
+        game.0.write().unwrap().finish_engine_turn(best_move);
+        game.1.notify_waiters();
+    });
+}
@@ -0,0 +1,292 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use std::{sync::Arc, thread, time::Duration};
+use tokio::sync::watch;
+use tower::ServiceExt;
+
+use super::api::{api_routes, error_response};
+use super::game::{GameHandle, GameOptions, GameRegistry, Seat, TimeControl};
+use super::{AppState, GameLimits};
+use crate::chess::{ChessColor, ChessError, Variant, PAWN};
+
+/// A Bughouse game with its four players seated, and their seats
+fn bughouse(time_control: Option<TimeControl>) -> (Arc<GameHandle>, Vec<Seat>) {
+    let games = GameRegistry::new(GameLimits::default());
+    let game = games
+        .create(GameOptions {
+            variant: Variant::Bughouse,
+            time_control,
+            ..Default::default()
+        })
+        .unwrap();
+    let seats = (0..4)
+        .map(|_| game.0.write().unwrap().join().unwrap())
+        .collect();
+    (game, seats)
+}
+
+/// Plays the moves of the players on `board`, as the squares they click
+fn play(game: &GameHandle, seats: &[Seat], board: usize, squares: &[&str]) {
+    let mut game = game.0.write().unwrap();
+    for (i, square) in squares.iter().enumerate() {
+        let color = match i / 2 % 2 {
+            0 => ChessColor::WHITE,
+            _ => ChessColor::BLACK,
+        };
+        let seat = seats
+            .iter()
+            .find(|seat| seat.board == board && seat.color == color)
+            .unwrap();
+        game.play(*seat, square).unwrap();
+    }
+}
+
+fn result_of(game: &GameHandle, board: usize) -> (String, String) {
+    let summary = game.0.read().unwrap().on_board(board).summary();
+    let result = |field: &str| summary["result"][field].as_str().unwrap().to_string();
+    (result("result"), result("reason"))
+}
+
+#[test]
+fn test_bughouse_passes_captures() {
+    let (game, seats) = bughouse(None);
+    play(&game, &seats, 0, &["e2", "e4", "d7", "d5", "e4", "d5"]);
+
+    // the black pawn taken on the first board goes to the black player of
+    // the second one, the partner of its taker
+    let game = game.0.read().unwrap();
+    let pockets = |board: usize, color| game.on_board(board).board.pocket(color);
+    assert_eq!(pockets(1, ChessColor::BLACK), [PAWN]);
+    assert!(pockets(1, ChessColor::WHITE).is_empty());
+    assert!(pockets(0, ChessColor::WHITE).is_empty());
+    assert!(pockets(0, ChessColor::BLACK).is_empty());
+}
+
+#[test]
+fn test_bughouse_ends_both_boards() {
+    let (game, seats) = bughouse(None);
+    let white = *seats
+        .iter()
+        .find(|seat| seat.board == 0 && seat.color == ChessColor::WHITE)
+        .unwrap();
+    game.0.write().unwrap().play(white, "resign").unwrap();
+
+    // colors are swapped between the boards, and so is the result
+    assert_eq!(
+        result_of(&game, 0),
+        ("0-1".to_string(), "white resigned".to_string())
+    );
+    assert_eq!(
+        result_of(&game, 1),
+        (
+            "1-0".to_string(),
+            "white resigned on the other board".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_bughouse_flag_ends_both_boards() {
+    let time_control = TimeControl {
+        initial_secs: 1,
+        increment_secs: 60,
+    };
+    let (game, seats) = bughouse(Some(time_control));
+    // the increments keep the second board well clear of its flag
+    play(&game, &seats, 1, &["e2", "e4", "e7", "e5"]);
+    thread::sleep(Duration::from_millis(1100));
+    game.0.write().unwrap().update_clock();
+
+    assert_eq!(
+        result_of(&game, 0),
+        ("0-1".to_string(), "white ran out of time".to_string())
+    );
+    assert_eq!(
+        result_of(&game, 1),
+        (
+            "1-0".to_string(),
+            "white ran out of time on the other board".to_string()
+        )
+    );
+    assert_eq!(game.0.read().unwrap().state_of(1)["phase"], "finished");
+}
+
+/// The API routes over a fresh registry, which is returned to set games up
+fn api(limits: GameLimits) -> (Router, Arc<GameRegistry>) {
+    let games = Arc::new(GameRegistry::new(limits));
+    let (_, shutdown) = watch::channel(false);
+    let state = AppState {
+        games: Arc::clone(&games),
+        shutdown,
+    };
+    (api_routes().with_state(state), games)
+}
+
+/// Sends a request to the API, answering with the status and the body, as
+/// JSON when it is some
+async fn request(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    };
+    let response = app.clone().oneshot(request.unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    (status, body)
+}
+
+/// Seats both players of game `id` and plays their clicks in turn
+fn play_game(games: &GameRegistry, id: u64, squares: &[&str]) {
+    let game = games.get(id).unwrap();
+    let mut game = game.0.write().unwrap();
+    let seats = [game.join().unwrap(), game.join().unwrap()];
+    for (i, square) in squares.iter().enumerate() {
+        let turn = game.on_board(0).board.get_turn();
+        let seat = seats.iter().find(|seat| seat.color == turn).unwrap();
+        game.play(*seat, square)
+            .unwrap_or_else(|e| panic!("{i}: {e}"));
+    }
+}
+
+#[tokio::test]
+async fn test_api_games() {
+    let (app, games) = api(GameLimits::default());
+
+    let (status, game) = request(
+        &app,
+        Method::POST,
+        "/games",
+        Some(json!({"color": "white"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(game["id"], 1);
+    assert_eq!(game["phase"], "open");
+    assert_eq!(
+        game["fen"],
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    );
+    let options = json!({"variant": "horde", "odds": "queen"});
+    let (status, error) = request(&app, Method::POST, "/games", Some(options)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["code"], "incompatible_variant");
+
+    play_game(&games, 1, &["e2", "e4"]);
+    let (status, moves) = request(&app, Method::GET, "/games/1/moves", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(moves[0]["uci"], "e2e4");
+    assert_eq!(moves[0]["san"], "e4");
+    assert_eq!(moves.as_array().unwrap().len(), 1);
+
+    let (status, pgn) = request(&app, Method::GET, "/games/1/pgn", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let pgn = pgn.as_str().unwrap();
+    assert!(pgn.contains("[Round \"1\"]"));
+    assert!(pgn.contains("1. e4"));
+
+    let (status, hints) = request(&app, Method::GET, "/games/1/hints?square=g8", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(hints["destinations"], json!(["h6", "f6"]));
+    assert_eq!(hints["last_move"], "e2e4");
+    let (status, error) = request(&app, Method::GET, "/games/1/hints?square=z9", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["result"], "error");
+
+    let (status, listed) = request(&app, Method::GET, "/games", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(listed[0]["phase"], "active");
+    let (_, listed) = request(&app, Method::GET, "/games?phase=open", None).await;
+    assert_eq!(listed, json!([]));
+}
+
+#[tokio::test]
+async fn test_api_finished_games() {
+    let (app, games) = api(GameLimits::default());
+    for _ in 0..4 {
+        request(&app, Method::POST, "/games", Some(json!({}))).await;
+    }
+    for id in 1..=3 {
+        play_game(&games, id, &["resign"]);
+    }
+
+    let (status, page) = request(&app, Method::GET, "/games/finished?per_page=2", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 3);
+    // most recent first
+    let ids: Vec<_> = page["games"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|game| game["id"].clone())
+        .collect();
+    assert_eq!(ids, [json!(3), json!(2)]);
+    let (_, page) = request(&app, Method::GET, "/games/finished?page=2&per_page=2", None).await;
+    assert_eq!(page["page"], 2);
+    assert_eq!(page["games"][0]["id"], 1);
+    assert_eq!(page["games"].as_array().unwrap().len(), 1);
+
+    // the game left going is the only one listed by default
+    let (_, listed) = request(&app, Method::GET, "/games", None).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert_eq!(listed[0]["id"], 4);
+}
+
+#[tokio::test]
+async fn test_api_errors() {
+    let limits = GameLimits {
+        max_games: 1,
+        ..Default::default()
+    };
+    let (app, _) = api(limits);
+
+    for uri in [
+        "/games/7",
+        "/games/7/moves",
+        "/games/7/pgn",
+        "/games/7/hints?square=e2",
+    ] {
+        let (status, error) = request(&app, Method::GET, uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+        assert_eq!(error["code"], "game_not_found");
+    }
+
+    request(&app, Method::POST, "/games", Some(json!({}))).await;
+    let (status, error) = request(&app, Method::POST, "/games", Some(json!({}))).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(error["code"], "too_many_games");
+
+    // only the websocket turns players away from full games
+    assert_eq!(
+        error_response(&ChessError::NoFreeSeat).status(),
+        StatusCode::CONFLICT
+    );
+}
+
+#[tokio::test]
+async fn test_api_positions() {
+    let (app, _) = api(GameLimits::default());
+    let edit = json!({"commands": ["put Ke1", "put ke8"]});
+    let (status, position) = request(&app, Method::POST, "/positions", Some(edit)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(position["fen"], "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(position["valid"], true);
+
+    let edit = json!({"fen": "4k3/8/8/8/8/8/8/8 w - - 0 1"});
+    let (status, error) = request(&app, Method::POST, "/positions", Some(edit)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error["code"], "invalid_fen");
+}